@@ -10,6 +10,9 @@ var<uniform> color_scale: f32;
 @group(1) @binding(1)
 var<uniform> max_iterations: f32;
 
+@group(1) @binding(7)
+var<uniform> periodicity_tolerance: f32;
+
 @fragment
 fn fragment(
     @builtin(position) coord: vec4<f32>,
@@ -21,6 +24,13 @@ fn fragment(
     var z: vec2<f32> = c; 
     var iteration: f32 = 0.0;
     let four: f32 = 4.0;
+
+    // Brent-style periodicity detection, see mandelbrot_fragment.wgsl.
+    var saved_z: vec2<f32> = z;
+    var period_window: f32 = 1.0;
+    var period_step: f32 = 0.0;
+    var period: f32 = 0.0;
+    let tolerance_squared: f32 = periodicity_tolerance * periodicity_tolerance;
     
     while (iteration < max_iterations) {
         z.x = abs(z.x);
@@ -39,6 +49,19 @@ fn fragment(
         z.y = two_xy + c.y;
         
         iteration += 1.0;
+
+        period_step += 1.0;
+        let drift: vec2<f32> = z - saved_z;
+        if (dot(drift, drift) < tolerance_squared) {
+            period = period_step;
+            iteration = max_iterations;
+            break;
+        }
+        if (period_step >= period_window) {
+            saved_z = z;
+            period_step = 0.0;
+            period_window *= 2.0;
+        }
     }
 
     let x_squared: f32 = z.x * z.x;
@@ -54,7 +77,11 @@ fn fragment(
     let condition: f32 = step(0.99, color);
     let black_color: vec4<f32> = vec4(0.0, 0.0, 0.0, 1.0);
     let colormap_color: vec4<f32> = textureSample(colormap_texture, colormap_sampler, vec2<f32>(color, 0.5));
+
+    // Interior points with a detected cycle get a faint tint keyed on their period.
+    let period_color: vec4<f32> = textureSample(colormap_texture, colormap_sampler, vec2<f32>(fract(period * 0.1), 0.5));
+    let interior_color: vec4<f32> = select(black_color, vec4(period_color.rgb * 0.2, 1.0), period > 0.0);
     
-    let final_color: vec4<f32> = mix(colormap_color, black_color, condition);
+    let final_color: vec4<f32> = mix(colormap_color, interior_color, condition);
     return final_color;
 }
@@ -0,0 +1,95 @@
+// Coarse pass over a downsampled grid of the current view that counts how pixels finish,
+// so the CPU can tell whether max_iterations is cutting the image short.
+
+struct ProgressiveParams {
+    origin: vec2<f32>,
+    pixel_step: vec2<f32>,
+    julia_c: vec2<f32>,
+    size: vec2<u32>,
+    fractal: u32,
+    max_iterations: u32,
+    iteration_budget: u32,
+    color_scale: f32,
+    periodicity_tolerance: f32,
+    sample_cap: u32,
+    accumulate: u32,
+    previous_origin: vec2<f32>,
+    previous_pixel_step: vec2<f32>,
+    previous_size: vec2<u32>,
+    reuse_offset: vec2<i32>,
+    reuse: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> params: ProgressiveParams;
+
+// samples, escaped, proven interior, hit the iteration cap
+@group(0) @binding(1)
+var<storage, read_write> counters: array<atomic<u32>, 4>;
+
+const MANDELBROT: u32 = 0u;
+const JULIA: u32 = 1u;
+const BURNING_SHIP: u32 = 2u;
+
+const ESCAPE_RADIUS_SQUARED: f32 = 4.0;
+
+// The escape test of the renderer on screen, so the capped count measures the image shown. The
+// fragment shaders, like their CPU ports in kernels.rs, stop a Mandelbrot orbit once either
+// coordinate leaves -2..2; the compute renderer stops every orbit at radius 2.
+fn has_escaped(z: vec2<f32>) -> bool {
+#ifdef FRAGMENT_ESCAPE
+    if (params.fractal == MANDELBROT) {
+        return abs(z.x) > 2.0 || abs(z.y) > 2.0;
+    }
+#endif
+    return dot(z, z) > ESCAPE_RADIUS_SQUARED;
+}
+
+fn in_main_bulbs(c: vec2<f32>) -> bool {
+    let q: f32 = (c.x - 0.25) * (c.x - 0.25) + c.y * c.y;
+    return q * (q + (c.x - 0.25)) < 0.25 * c.y * c.y || (c.x + 1.0) * (c.x + 1.0) + c.y * c.y < 0.0625;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn stats(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size.x || id.y >= params.size.y) {
+        return;
+    }
+    atomicAdd(&counters[0], 1u);
+
+    let point: vec2<f32> = params.origin + vec2<f32>(id.xy) * params.pixel_step;
+    var c: vec2<f32> = point;
+    var z: vec2<f32> = vec2<f32>(0.0, 0.0);
+    if (params.fractal == JULIA) {
+        c = params.julia_c;
+        z = point;
+    } else if (params.fractal == MANDELBROT && in_main_bulbs(point)) {
+        atomicAdd(&counters[2], 1u);
+        return;
+    }
+
+    var saved_z: vec2<f32> = z;
+    let tolerance_squared: f32 = params.periodicity_tolerance * params.periodicity_tolerance;
+    var iteration: u32 = 0u;
+    while (iteration < params.max_iterations) {
+        if (params.fractal == BURNING_SHIP) {
+            z = abs(z);
+        }
+        z = vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+        iteration += 1u;
+
+        if (has_escaped(z)) {
+            atomicAdd(&counters[1], 1u);
+            return;
+        }
+        let drift: vec2<f32> = z - saved_z;
+        if (dot(drift, drift) < tolerance_squared) {
+            atomicAdd(&counters[2], 1u);
+            return;
+        }
+        if ((iteration & (iteration - 1u)) == 0u) {
+            saved_z = z;
+        }
+    }
+    atomicAdd(&counters[3], 1u);
+}
@@ -14,6 +14,9 @@ var<uniform> max_iterations: f32;
 @group(1) @binding(2)
 var<uniform> c: vec2<f32>;
 
+@group(1) @binding(7)
+var<uniform> periodicity_tolerance: f32;
+
 @fragment
 fn fragment(
     @builtin(position) coord: vec4<f32>,
@@ -26,7 +29,13 @@ fn fragment(
     let four: f32 = 4.0;
     let two: f32 = 2.0;
 
-    
+    // Brent-style periodicity detection, see mandelbrot_fragment.wgsl.
+    var saved_z: vec2<f32> = z;
+    var period_window: f32 = 1.0;
+    var period_step: f32 = 0.0;
+    var period: f32 = 0.0;
+    let tolerance_squared: f32 = periodicity_tolerance * periodicity_tolerance;
+
     while (iteration < max_iterations) {
         let x_squared: f32 = z.x * z.x;
         let y_squared: f32 = z.y * z.y;
@@ -41,13 +50,41 @@ fn fragment(
         z.y = two_xy + c.y;
         
         iteration += 1.0;
+
+        period_step += 1.0;
+        let drift: vec2<f32> = z - saved_z;
+        if (dot(drift, drift) < tolerance_squared) {
+            period = period_step;
+            iteration = max_iterations;
+            break;
+        }
+        if (period_step >= period_window) {
+            saved_z = z;
+            period_step = 0.0;
+            period_window *= 2.0;
+        }
     }
 
-    let basic_color = iteration / max_iterations;
-    // Here you may want to use an approximation for pow if possible
+    let x_squared: f32 = z.x * z.x;
+    let y_squared: f32 = z.y * z.y;
+
+    // Smoothed and cut off at the interior like burning_ship_fragment.wgsl. Points that start far
+    // outside the circle would smooth below zero, which pow can't take.
+    var smooth_value = iteration;
+    if (iteration < max_iterations) {
+        smooth_value = max(iteration + 1.0 - log2(log(x_squared + y_squared)), 0.0);
+    }
+    let basic_color = smooth_value / max_iterations;
     let color = pow(basic_color, 0.1) * (1.0 - color_scale) + color_scale;
 
+    let condition: f32 = step(0.99, color);
+    let black_color: vec4<f32> = vec4(0.0, 0.0, 0.0, 1.0);
     let colormap_color: vec4<f32> = textureSample(colormap_texture, colormap_sampler, vec2<f32>(color, 0.5));
-    return colormap_color;
+
+    // Interior points with a detected cycle get a faint tint keyed on their period.
+    let period_color: vec4<f32> = textureSample(colormap_texture, colormap_sampler, vec2<f32>(fract(period * 0.1), 0.5));
+    let interior_color: vec4<f32> = select(black_color, vec4(period_color.rgb * 0.2, 1.0), period > 0.0);
+
+    return mix(colormap_color, interior_color, condition);
 }
 
@@ -17,6 +17,9 @@ var<uniform> zoom: f32;
 @group(1) @binding(3)
 var<uniform> offset: vec2<f32>;
 
+@group(1) @binding(7)
+var<uniform> periodicity_tolerance: f32;
+
 @fragment
 fn fragment(
     @builtin(position) coord: vec4<f32>,
@@ -29,6 +32,14 @@ fn fragment(
     var iteration: f32 = 0.0;
     //let max_iterations: f32 = 1000.0;
 
+    // Brent-style periodicity detection: compare against a saved orbit point
+    // and double the comparison window every time it runs out.
+    var saved_z: vec2<f32> = z;
+    var period_window: f32 = 1.0;
+    var period_step: f32 = 0.0;
+    var period: f32 = 0.0;
+    let tolerance_squared: f32 = periodicity_tolerance * periodicity_tolerance;
+
     // Check for early exit
     let q: f32 = (c.x - 0.25) * (c.x - 0.25) + c.y * c.y;
     if (q * (q + (c.x - 0.25)) < 0.25 * c.y * c.y || (c.x + 1.0) * (c.x + 1.0) + c.y * c.y < 0.0625) {
@@ -45,6 +56,20 @@ fn fragment(
             z.x = x;
             z.y = y;
             iteration = iteration + 1.0;
+
+            // The orbit returned to the saved point, so it is cycling and will never escape.
+            period_step = period_step + 1.0;
+            let drift: vec2<f32> = z - saved_z;
+            if (dot(drift, drift) < tolerance_squared) {
+                period = period_step;
+                iteration = max_iterations;
+                break;
+            }
+            if (period_step >= period_window) {
+                saved_z = z;
+                period_step = 0.0;
+                period_window = period_window * 2.0;
+            }
         }
     }
 
@@ -57,8 +82,12 @@ fn fragment(
     let condition: f32 = step(0.99, color);
     let black_color: vec4<f32> = vec4(0.0, 0.0, 0.0, 1.0);
     let colormap_color: vec4<f32> = textureSample(colormap_texture, colormap_sampler, vec2<f32>(color, 0.5));
-    
-    let final_color: vec4<f32> = mix(colormap_color, black_color, condition);
+
+    // Interior points with a detected cycle get a faint tint keyed on their period.
+    let period_color: vec4<f32> = textureSample(colormap_texture, colormap_sampler, vec2<f32>(fract(period * 0.1), 0.5));
+    let interior_color: vec4<f32> = select(black_color, vec4(period_color.rgb * 0.2, 1.0), period > 0.0);
+
+    let final_color: vec4<f32> = mix(colormap_color, interior_color, condition);
     return final_color;
 
 }
@@ -0,0 +1,334 @@
+// Progressive escape-time renderer. Every pixel keeps its orbit in storage textures so the
+// iteration loop can be spread across frames with a fixed budget per dispatch. Iterating and
+// coloring are separate passes: `iterate` caches the result of each pixel in `iteration_data`
+// and only runs while pixels are unresolved, `colorize` maps that cache to the screen every frame.
+// Only the top-left params.size pixels of the textures are used, so the resolution can drop while
+// the camera moves without reallocating.
+
+struct ProgressiveParams {
+    origin: vec2<f32>,
+    pixel_step: vec2<f32>,
+    julia_c: vec2<f32>,
+    size: vec2<u32>,
+    fractal: u32,
+    max_iterations: u32,
+    iteration_budget: u32,
+    color_scale: f32,
+    periodicity_tolerance: f32,
+    sample_cap: u32,
+    accumulate: u32,
+    // Mapping of the previous frame, used to carry pixels over when the view changes.
+    previous_origin: vec2<f32>,
+    previous_pixel_step: vec2<f32>,
+    previous_size: vec2<u32>,
+    // Pixel (x, y) of this frame was pixel (x, y) + reuse_offset of the previous one.
+    reuse_offset: vec2<i32>,
+    reuse: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> params: ProgressiveParams;
+
+// z.x, z.y, dz.x, dz.y
+@group(0) @binding(1)
+var orbit_state: texture_storage_2d<rgba32float, read_write>;
+
+// iteration, status | sample index << 8, then the saved Brent point (as bits) while running or the period once interior
+@group(0) @binding(2)
+var progress_state: texture_storage_2d<rgba32uint, read_write>;
+
+@group(0) @binding(3)
+var output: texture_storage_2d<rgba16float, write>;
+
+@group(0) @binding(4)
+var colormap_texture: texture_2d<f32>;
+
+@group(0) @binding(5)
+var colormap_sampler: sampler;
+
+// Finished jittered samples (every sample but the center one): summed colormap position of the
+// escaped ones in x, their count in y, and the count of all of them in w. Colors are only looked up
+// when the pixel is drawn, so recoloring keeps the samples.
+@group(0) @binding(6)
+var accumulation: texture_storage_2d<rgba32float, read_write>;
+
+// Result of the center sample: smooth iteration count (or the interior code), final z, distance estimate.
+@group(0) @binding(7)
+var iteration_data: texture_storage_2d<rgba32float, read_write>;
+
+// Copies of the state textures taken right before a reset, read back by the reset pass.
+@group(0) @binding(8)
+var previous_orbit_state: texture_2d<f32>;
+
+@group(0) @binding(9)
+var previous_progress_state: texture_2d<u32>;
+
+@group(0) @binding(10)
+var previous_accumulation: texture_2d<f32>;
+
+@group(0) @binding(11)
+var previous_iteration_data: texture_2d<f32>;
+
+@group(0) @binding(12)
+var previous_output: texture_2d<f32>;
+
+// Pixels still iterating after an iterate pass. Read back so the renderer knows when it is done.
+@group(0) @binding(13)
+var<storage, read_write> busy_pixels: atomic<u32>;
+
+const MANDELBROT: u32 = 0u;
+const JULIA: u32 = 1u;
+const BURNING_SHIP: u32 = 2u;
+
+const RUNNING: u32 = 0u;
+const ESCAPED: u32 = 1u;
+const INTERIOR: u32 = 2u;
+
+const STATUS_MASK: u32 = 0xffu;
+const SAMPLE_SHIFT: u32 = 8u;
+
+// How much of the previous frame the reset pass may keep.
+const REUSE_NONE: u32 = 0u;
+const REUSE_PREVIEW: u32 = 1u;
+const REUSE_PIXELS: u32 = 2u;
+
+const ESCAPE_RADIUS_SQUARED: f32 = 4.0;
+
+// iteration_data.x for pixels that have not resolved yet. Interior pixels store -1 - period.
+const UNRESOLVED: f32 = -1.0e30;
+
+fn complex_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// Main cardioid and period-2 bulb test, same shortcut as mandelbrot_fragment.wgsl.
+fn in_main_bulbs(c: vec2<f32>) -> bool {
+    let q: f32 = (c.x - 0.25) * (c.x - 0.25) + c.y * c.y;
+    return q * (q + (c.x - 0.25)) < 0.25 * c.y * c.y || (c.x + 1.0) * (c.x + 1.0) + c.y * c.y < 0.0625;
+}
+
+// Subpixel offset of a sample. Sample 0 sits at the pixel center, the rest follow the
+// R2 low-discrepancy sequence so any prefix of samples stays well stratified.
+fn sample_jitter(sample_index: u32) -> vec2<f32> {
+    if (sample_index == 0u) {
+        return vec2<f32>(0.0, 0.0);
+    }
+    return fract(vec2<f32>(0.5, 0.5) + f32(sample_index) * vec2<f32>(0.7548776662, 0.5698402910)) - 0.5;
+}
+
+// Start the orbit for one subpixel sample of a pixel.
+fn start_sample(pixel: vec2<u32>, sample_index: u32) {
+    let point = params.origin + (vec2<f32>(pixel) + sample_jitter(sample_index)) * params.pixel_step;
+
+    var z: vec2<f32> = vec2<f32>(0.0, 0.0);
+    var dz: vec2<f32> = vec2<f32>(0.0, 0.0);
+    if (params.fractal == JULIA) {
+        z = point;
+        dz = vec2<f32>(1.0, 0.0);
+    }
+
+    let flags: u32 = RUNNING | (sample_index << SAMPLE_SHIFT);
+    textureStore(orbit_state, vec2<i32>(pixel), vec4<f32>(z, dz));
+    textureStore(progress_state, vec2<i32>(pixel), vec4<u32>(0u, flags, bitcast<u32>(z.x), bitcast<u32>(z.y)));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn reset(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size.x || id.y >= params.size.y) {
+        return;
+    }
+    let pixel = vec2<i32>(id.xy);
+
+    // After a pan by whole pixels the orbits of the overlapping region are still valid.
+    let source: vec2<i32> = pixel + params.reuse_offset;
+    let in_previous: bool = all(source >= vec2<i32>(0)) && all(source < vec2<i32>(params.size));
+    if (params.reuse == REUSE_PIXELS && in_previous) {
+        textureStore(orbit_state, pixel, textureLoad(previous_orbit_state, source, 0));
+        textureStore(progress_state, pixel, textureLoad(previous_progress_state, source, 0));
+        textureStore(accumulation, pixel, textureLoad(previous_accumulation, source, 0));
+        textureStore(iteration_data, pixel, textureLoad(previous_iteration_data, source, 0));
+        textureStore(output, pixel, textureLoad(previous_output, source, 0));
+        return;
+    }
+
+    start_sample(id.xy, 0u);
+    textureStore(accumulation, pixel, vec4<f32>(0.0));
+    textureStore(iteration_data, pixel, vec4<f32>(UNRESOLVED, 0.0, 0.0, 0.0));
+
+    // Show the previous image, rescaled to the new mapping, until this pixel resolves.
+    var preview: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    if (params.reuse != REUSE_NONE) {
+        let point: vec2<f32> = params.origin + vec2<f32>(id.xy) * params.pixel_step;
+        let previous_pixel: vec2<f32> = (point - params.previous_origin) / params.previous_pixel_step;
+        let previous_size: vec2<f32> = vec2<f32>(params.previous_size);
+        if (all(previous_pixel >= vec2<f32>(-0.5)) && all(previous_pixel <= previous_size - 0.5)) {
+            // The previous frame may only have covered part of the texture at a lower resolution.
+            let texel: vec2<f32> = clamp(previous_pixel, vec2<f32>(0.0), previous_size - 1.0);
+            let uv: vec2<f32> = (texel + 0.5) / vec2<f32>(textureDimensions(previous_output));
+            preview = textureSampleLevel(previous_output, colormap_sampler, uv, 0.0);
+        }
+    }
+    textureStore(output, pixel, preview);
+}
+
+// Encode a finished sample the way iteration_data stores it.
+fn sample_data(status: u32, iteration: u32, period: u32, z: vec2<f32>, dz: vec2<f32>) -> vec4<f32> {
+    if (status != ESCAPED) {
+        return vec4<f32>(-1.0 - f32(period), z, 0.0);
+    }
+    let radius_squared: f32 = dot(z, z);
+    let smooth_value: f32 = f32(iteration) + 1.0 - log2(log(max(radius_squared, 1.0001)));
+    let radius: f32 = sqrt(radius_squared);
+    let distance: f32 = 0.5 * radius * log(radius) / max(length(dz), 1.0e-30);
+    return vec4<f32>(smooth_value, z, distance);
+}
+
+// Where an escaped sample falls along the colormap before the color scale is applied.
+fn escape_position(smooth_value: f32) -> f32 {
+    return pow(clamp(smooth_value / f32(params.max_iterations), 0.0, 1.0), 0.3);
+}
+
+// Map a colormap position to a color with the current palette and color scale.
+fn escape_color(position: f32) -> vec3<f32> {
+    let color: f32 = position * (1.0 - params.color_scale) + params.color_scale;
+    return textureSampleLevel(colormap_texture, colormap_sampler, vec2<f32>(color, 0.5), 0.0).rgb;
+}
+
+// Interior points with a detected cycle get a faint tint keyed on their period.
+fn interior_color(data: vec4<f32>) -> vec3<f32> {
+    let period: f32 = -1.0 - data.x;
+    if (period > 0.0) {
+        let period_color: vec4<f32> = textureSampleLevel(colormap_texture, colormap_sampler, vec2<f32>(fract(period * 0.1), 0.5), 0.0);
+        return period_color.rgb * 0.2;
+    }
+    return vec3<f32>(0.0, 0.0, 0.0);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn iterate(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size.x || id.y >= params.size.y) {
+        return;
+    }
+    let pixel = vec2<i32>(id.xy);
+    let state = textureLoad(orbit_state, pixel);
+    var progress = textureLoad(progress_state, pixel);
+    var accumulated = textureLoad(accumulation, pixel);
+
+    var z: vec2<f32> = state.xy;
+    var dz: vec2<f32> = state.zw;
+    var iteration: u32 = progress.x;
+    let was_running: bool = (progress.y & STATUS_MASK) == RUNNING;
+    var status: u32 = progress.y & STATUS_MASK;
+    let sample_index: u32 = progress.y >> SAMPLE_SHIFT;
+    var period: u32 = 0u;
+
+    if (was_running) {
+        var c: vec2<f32> = params.origin + (vec2<f32>(id.xy) + sample_jitter(sample_index)) * params.pixel_step;
+        if (params.fractal == JULIA) {
+            c = params.julia_c;
+        }
+        var saved_z: vec2<f32> = vec2<f32>(bitcast<f32>(progress.z), bitcast<f32>(progress.w));
+        let tolerance_squared: f32 = params.periodicity_tolerance * params.periodicity_tolerance;
+        var stop: u32 = min(iteration + params.iteration_budget, params.max_iterations);
+        if (params.fractal == MANDELBROT && iteration == 0u && in_main_bulbs(c)) {
+            status = INTERIOR;
+            stop = iteration;
+        }
+
+        while (iteration < stop) {
+            if (params.fractal == BURNING_SHIP) {
+                z = abs(z);
+            }
+            if (params.fractal == JULIA) {
+                dz = 2.0 * complex_mul(z, dz);
+            } else {
+                dz = 2.0 * complex_mul(z, dz) + vec2<f32>(1.0, 0.0);
+            }
+            z = vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+            iteration += 1u;
+
+            if (dot(z, z) > ESCAPE_RADIUS_SQUARED) {
+                status = ESCAPED;
+                break;
+            }
+
+            // Brent periodicity check. The saved point was taken at the last power of two
+            // below this iteration, so the distance to it is the period.
+            let drift: vec2<f32> = z - saved_z;
+            if (dot(drift, drift) < tolerance_squared) {
+                status = INTERIOR;
+                period = iteration - select(1u << firstLeadingBit(iteration - 1u), 0u, iteration == 1u);
+                break;
+            }
+            if ((iteration & (iteration - 1u)) == 0u) {
+                saved_z = z;
+            }
+        }
+
+        if (status == RUNNING && iteration >= params.max_iterations) {
+            status = INTERIOR;
+        }
+
+        let flags: u32 = status | (sample_index << SAMPLE_SHIFT);
+        if (status == INTERIOR) {
+            progress = vec4<u32>(iteration, flags, period, 0u);
+        } else {
+            progress = vec4<u32>(iteration, flags, bitcast<u32>(saved_z.x), bitcast<u32>(saved_z.y));
+        }
+        textureStore(orbit_state, pixel, vec4<f32>(z, dz));
+        textureStore(progress_state, pixel, progress);
+    } else if (status == INTERIOR) {
+        period = progress.z;
+    }
+
+    if (status == RUNNING) {
+        atomicAdd(&busy_pixels, 1u);
+        return;
+    }
+    if (was_running) {
+        let data: vec4<f32> = sample_data(status, iteration, period, z, dz);
+        if (sample_index == 0u) {
+            // The center sample is cached so it can be recolored without iterating again.
+            textureStore(iteration_data, pixel, data);
+        } else {
+            if (data.x >= 0.0) {
+                accumulated += vec4<f32>(escape_position(data.x), 1.0, 0.0, 0.0);
+            }
+            accumulated.w += 1.0;
+            textureStore(accumulation, pixel, accumulated);
+        }
+    }
+    // Keep refining with the next jittered sample while the camera holds still.
+    if (params.accumulate != 0u && accumulated.w + 1.0 < f32(params.sample_cap)) {
+        start_sample(id.xy, sample_index + 1u);
+        atomicAdd(&busy_pixels, 1u);
+    }
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn colorize(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size.x || id.y >= params.size.y) {
+        return;
+    }
+    let pixel = vec2<i32>(id.xy);
+    let data: vec4<f32> = textureLoad(iteration_data, pixel);
+    // Unresolved pixels keep whatever the reset pass left there until they escape or are proven interior.
+    if (data.x == UNRESOLVED) {
+        return;
+    }
+    // Escaped samples are colored by their average position, interior ones like the center sample
+    // or black, each weighted by its share of the samples.
+    let accumulated: vec4<f32> = textureLoad(accumulation, pixel);
+    var position_sum: f32 = accumulated.x;
+    var escaped: f32 = accumulated.y;
+    if (data.x >= 0.0) {
+        position_sum += escape_position(data.x);
+        escaped += 1.0;
+    }
+    let total: f32 = accumulated.w + 1.0;
+    var color: vec3<f32> = (total - escaped) * interior_color(data);
+    if (escaped > 0.0) {
+        color += escaped * escape_color(position_sum / escaped);
+    }
+    textureStore(output, pixel, vec4<f32>(color / total, 1.0));
+}
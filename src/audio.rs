@@ -0,0 +1,1103 @@
+use std::f32::consts::TAU;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use bevy::audio::{AddAudioSource, AudioSinkPlayback, Decodable};
+use bevy::prelude::*;
+use bevy::render::camera::CameraUpdateSystem;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use futures_lite::future;
+use rodio::Source;
+
+use crate::export::{ExportSettings, ExportState, SequenceView};
+use crate::fractals::{AnimationUpdateToggle, FractalType};
+use crate::materials::{ActiveFractalParams, FractalMaterials};
+use crate::pancam::PanCamState;
+use crate::progressive::{PinnedFrame, ProgressiveSettings, PALETTES};
+use crate::timeline::timeline_playback_system;
+
+// Analysis frames per second of audio. Playback and export both sample between them.
+const ANALYSIS_RATE: f32 = 60.0;
+// Samples per FFT, about 46 ms at 44.1 kHz. Must be a power of two.
+const FFT_SIZE: usize = 2048;
+// A beat is bass this many times louder than its average over the last BEAT_WINDOW seconds.
+const BEAT_THRESHOLD: f32 = 1.5;
+const BEAT_WINDOW: f32 = 1.0;
+// Seconds after a beat before the next one can start.
+const MIN_BEAT_INTERVAL: f32 = 0.25;
+// Seconds for the beat envelope to fall to 1/e.
+const BEAT_DECAY: f32 = 0.15;
+// Feature values are divided by this percentile of the track, so most of it sits in 0 to 1.
+const NORMALIZE_PERCENTILE: f32 = 0.95;
+// Bass below this is digital silence and never starts a beat.
+const SILENCE: f32 = 1e-6;
+// Loudest zoom pulses can be, as a factor of the projection scale.
+const MIN_ZOOM_FACTOR: f32 = 0.1;
+
+// What the music measures, each normalized to 0 to 1.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Feature {
+    Bass,
+    LowMid,
+    HighMid,
+    Treble,
+    // Loudness of the whole signal.
+    Level,
+    // Jumps to 1 on each detected beat and decays back towards 0.
+    Beat,
+}
+
+const FEATURE_COUNT: usize = 6;
+
+type Features = [f32; FEATURE_COUNT];
+
+impl Feature {
+    pub const ALL: [Feature; FEATURE_COUNT] = [
+        Feature::Bass,
+        Feature::LowMid,
+        Feature::HighMid,
+        Feature::Treble,
+        Feature::Level,
+        Feature::Beat,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Feature::Bass => "Bass",
+            Feature::LowMid => "Low mids",
+            Feature::HighMid => "High mids",
+            Feature::Treble => "Treble",
+            Feature::Level => "Level",
+            Feature::Beat => "Beat",
+        }
+    }
+
+    // Frequency range in Hz summed for the spectral bands.
+    fn band(self) -> Option<(f32, f32)> {
+        match self {
+            Feature::Bass => Some((20.0, 150.0)),
+            Feature::LowMid => Some((150.0, 600.0)),
+            Feature::HighMid => Some((600.0, 2500.0)),
+            Feature::Treble => Some((2500.0, 10000.0)),
+            Feature::Level | Feature::Beat => None,
+        }
+    }
+}
+
+// What a feature can drive.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Target {
+    ColorScale,
+    JuliaCx,
+    JuliaCy,
+    // Zooms in by a factor of 1 + amount at full value, for that frame only.
+    Zoom,
+    // Steps through the palettes, one per unit of amount at full value, on either renderer.
+    PaletteOffset,
+}
+
+impl Target {
+    pub const ALL: [Target; 5] = [
+        Target::ColorScale,
+        Target::JuliaCx,
+        Target::JuliaCy,
+        Target::Zoom,
+        Target::PaletteOffset,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Target::ColorScale => "Color scale",
+            Target::JuliaCx => "Julia c (real)",
+            Target::JuliaCy => "Julia c (imaginary)",
+            Target::Zoom => "Zoom pulse",
+            Target::PaletteOffset => "Palette offset",
+        }
+    }
+}
+
+// One row of the mapping table: the feature's value times amount is added to the target.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Modulation {
+    pub feature: Feature,
+    pub target: Target,
+    pub amount: f32,
+}
+
+// User-facing settings for the audio-reactive mode.
+#[derive(Resource, Clone, PartialEq)]
+pub struct AudioReactive {
+    // FLAC, OGG Vorbis or WAV file to load.
+    pub path: String,
+    pub mappings: Vec<Modulation>,
+    // Frames per second of audio written by the frame export.
+    pub export_fps: u32,
+}
+
+impl Default for AudioReactive {
+    fn default() -> Self {
+        AudioReactive {
+            path: String::new(),
+            mappings: vec![
+                Modulation {
+                    feature: Feature::Bass,
+                    target: Target::Zoom,
+                    amount: 0.1,
+                },
+                Modulation {
+                    feature: Feature::Beat,
+                    target: Target::ColorScale,
+                    amount: 0.25,
+                },
+                Modulation {
+                    feature: Feature::LowMid,
+                    target: Target::JuliaCx,
+                    amount: 0.04,
+                },
+                Modulation {
+                    feature: Feature::Treble,
+                    target: Target::JuliaCy,
+                    amount: 0.04,
+                },
+            ],
+            export_fps: 30,
+        }
+    }
+}
+
+// Plays the loaded track and modulates the fractal while active.
+#[derive(Resource, Default)]
+pub(crate) struct MusicUpdateToggle {
+    pub active: bool,
+}
+
+// Features of a whole track, one set per 1 / ANALYSIS_RATE seconds.
+#[derive(Clone, Default)]
+struct AudioAnalysis {
+    frames: Vec<Features>,
+}
+
+impl AudioAnalysis {
+    fn duration(&self) -> f32 {
+        self.frames.len() as f32 / ANALYSIS_RATE
+    }
+
+    // Features `time` seconds in, interpolated between analysis frames.
+    fn sample(&self, time: f32) -> Features {
+        let Some(last) = self.frames.len().checked_sub(1) else {
+            return [0.0; FEATURE_COUNT];
+        };
+        let position = (time * ANALYSIS_RATE).max(0.0);
+        let index = (position as usize).min(last);
+        let next = (index + 1).min(last);
+        let t = (position - index as f32).clamp(0.0, 1.0);
+        let mut features = [0.0; FEATURE_COUNT];
+        for (i, value) in features.iter_mut().enumerate() {
+            *value = self.frames[index][i] + (self.frames[next][i] - self.frames[index][i]) * t;
+        }
+        features
+    }
+}
+
+// What a set of features turns the base parameters, zoom and palette into.
+struct Modulated {
+    params: ActiveFractalParams,
+    // Factor the projection scale is multiplied by.
+    zoom: f32,
+    palette: usize,
+}
+
+fn modulate(
+    mappings: &[Modulation],
+    features: &Features,
+    base: &ActiveFractalParams,
+    base_palette: usize,
+) -> Modulated {
+    let mut params = *base;
+    let mut zoom = 0.0;
+    let mut palette_offset = 0.0;
+    for modulation in mappings {
+        let value = features[modulation.feature as usize] * modulation.amount;
+        match modulation.target {
+            Target::ColorScale => params.color_scale += value,
+            Target::JuliaCx => params.julia_c.x += value,
+            Target::JuliaCy => params.julia_c.y += value,
+            Target::Zoom => zoom += value,
+            Target::PaletteOffset => palette_offset += value,
+        }
+    }
+    params.color_scale = params.color_scale.clamp(0.0, 1.0);
+    let palette = (base_palette as i64 + palette_offset.round() as i64)
+        .rem_euclid(PALETTES.len() as i64) as usize;
+    Modulated {
+        params,
+        zoom: (1.0 / (1.0 + zoom)).clamp(MIN_ZOOM_FACTOR, 1.0 / MIN_ZOOM_FACTOR),
+        palette,
+    }
+}
+
+// e^(-2 pi i k / n) for the first half of an n point FFT.
+fn twiddles(n: usize) -> Vec<[f32; 2]> {
+    (0..n / 2)
+        .map(|k| {
+            let (sin, cos) = (-TAU * k as f32 / n as f32).sin_cos();
+            [cos, sin]
+        })
+        .collect()
+}
+
+// In-place iterative radix-2 FFT. The length must be a power of two, twice the twiddle count.
+fn fft(re: &mut [f32], im: &mut [f32], twiddles: &[[f32; 2]]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let stride = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let [cos, sin] = twiddles[k * stride];
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+// Band energies, level and beats of a mono signal.
+fn analyze(samples: &[f32], sample_rate: u32) -> AudioAnalysis {
+    let hop = sample_rate as f32 / ANALYSIS_RATE;
+    let count = (samples.len() as f32 / hop).ceil() as usize;
+    let window: Vec<f32> = (0..FFT_SIZE)
+        .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+        .collect();
+    let twiddles = twiddles(FFT_SIZE);
+    let bin_width = sample_rate as f32 / FFT_SIZE as f32;
+    let mut re = vec![0.0; FFT_SIZE];
+    let mut im = vec![0.0; FFT_SIZE];
+
+    let mut frames: Vec<Features> = (0..count)
+        .map(|frame| {
+            // Each window is centered on its frame's time.
+            let center = (frame as f32 * hop) as usize;
+            for (i, (value, weight)) in re.iter_mut().zip(&window).enumerate() {
+                let sample = (center + i)
+                    .checked_sub(FFT_SIZE / 2)
+                    .and_then(|index| samples.get(index))
+                    .copied()
+                    .unwrap_or(0.0);
+                *value = sample * weight;
+            }
+            im.fill(0.0);
+            fft(&mut re, &mut im, &twiddles);
+
+            let mut features = [0.0; FEATURE_COUNT];
+            for feature in Feature::ALL {
+                let Some((low, high)) = feature.band() else {
+                    continue;
+                };
+                let first = ((low / bin_width).ceil() as usize).max(1);
+                let last = ((high / bin_width).floor() as usize).min(FFT_SIZE / 2);
+                if last >= first {
+                    let power: f32 = (first..=last)
+                        .map(|bin| re[bin] * re[bin] + im[bin] * im[bin])
+                        .sum();
+                    features[feature as usize] = (power / (last - first + 1) as f32).sqrt();
+                }
+            }
+            let hop_samples =
+                &samples[center.min(samples.len())..(center + hop as usize).min(samples.len())];
+            if !hop_samples.is_empty() {
+                let power: f32 = hop_samples.iter().map(|s| s * s).sum();
+                features[Feature::Level as usize] = (power / hop_samples.len() as f32).sqrt();
+            }
+            features
+        })
+        .collect();
+
+    // Beats are onsets in the bass, found before normalizing; the ratio doesn't depend on it.
+    let history = (BEAT_WINDOW * ANALYSIS_RATE) as usize;
+    let min_interval = (MIN_BEAT_INTERVAL * ANALYSIS_RATE) as usize;
+    let mut last_beat: Option<usize> = None;
+    for frame in 0..count {
+        let bass = frames[frame][Feature::Bass as usize];
+        let recent = &frames[frame.saturating_sub(history)..frame];
+        if !recent.is_empty() {
+            let mean = recent
+                .iter()
+                .map(|features| features[Feature::Bass as usize])
+                .sum::<f32>()
+                / recent.len() as f32;
+            let spaced = !matches!(last_beat, Some(last) if frame - last < min_interval);
+            if spaced && bass > SILENCE && bass > BEAT_THRESHOLD * mean {
+                last_beat = Some(frame);
+            }
+        }
+        frames[frame][Feature::Beat as usize] = last_beat.map_or(0.0, |last| {
+            (-((frame - last) as f32 / ANALYSIS_RATE) / BEAT_DECAY).exp()
+        });
+    }
+
+    for feature in Feature::ALL {
+        if feature == Feature::Beat {
+            continue;
+        }
+        let mut values: Vec<f32> = frames.iter().map(|f| f[feature as usize]).collect();
+        values.sort_by(f32::total_cmp);
+        let Some(&reference) = values
+            .get(((values.len().saturating_sub(1)) as f32 * NORMALIZE_PERCENTILE).round() as usize)
+        else {
+            continue;
+        };
+        if reference > 0.0 {
+            for features in &mut frames {
+                features[feature as usize] = (features[feature as usize] / reference).min(1.0);
+            }
+        }
+    }
+    AudioAnalysis { frames }
+}
+
+// Decode a whole file to mono samples and its sample rate.
+fn decode(bytes: Vec<u8>) -> Result<(Vec<f32>, u32), String> {
+    let decoder = rodio::Decoder::new(Cursor::new(bytes)).map_err(|err| err.to_string())?;
+    let channels = decoder.channels().max(1) as usize;
+    let sample_rate = decoder.sample_rate();
+    let interleaved: Vec<i16> = decoder.collect();
+    let samples = interleaved
+        .chunks(channels)
+        .map(|frame| {
+            frame.iter().map(|&sample| sample as f32).sum::<f32>() / (channels as f32 * 32768.0)
+        })
+        .collect();
+    Ok((samples, sample_rate))
+}
+
+struct LoadedTrack {
+    name: String,
+    bytes: Vec<u8>,
+    analysis: AudioAnalysis,
+}
+
+fn load_track(path: PathBuf) -> Result<LoadedTrack, String> {
+    let bytes =
+        std::fs::read(&path).map_err(|err| format!("could not read {}: {err}", path.display()))?;
+    let (samples, sample_rate) = decode(bytes.clone())
+        .map_err(|err| format!("could not decode {}: {err}", path.display()))?;
+    if samples.is_empty() || sample_rate == 0 {
+        return Err(format!("{} holds no audio", path.display()));
+    }
+    Ok(LoadedTrack {
+        name: path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+        bytes,
+        analysis: analyze(&samples, sample_rate),
+    })
+}
+
+// How far the audio output has read into the track, counted by the decoder feeding it, so the
+// fractal follows what is heard rather than the frame clock.
+#[derive(Default)]
+struct PlaybackPosition {
+    samples: AtomicU64,
+    // Interleaved samples per second of the decoded track.
+    rate: AtomicU32,
+}
+
+impl PlaybackPosition {
+    fn seconds(&self) -> f32 {
+        match self.rate.load(Ordering::Relaxed) {
+            0 => 0.0,
+            rate => (self.samples.load(Ordering::Relaxed) as f64 / rate as f64) as f32,
+        }
+    }
+
+    fn reset(&self) {
+        self.samples.store(0, Ordering::Relaxed);
+    }
+}
+
+// The track as an audio asset. It loops by itself rather than with PlaybackSettings::LOOP, whose
+// buffered repeat stops reading from the decoder after the first pass.
+#[derive(Asset, TypePath, Clone)]
+struct MusicSource {
+    bytes: Arc<[u8]>,
+    position: Arc<PlaybackPosition>,
+}
+
+type TrackDecoder = rodio::Decoder<Cursor<Arc<[u8]>>>;
+
+struct MusicDecoder {
+    bytes: Arc<[u8]>,
+    decoder: TrackDecoder,
+    position: Arc<PlaybackPosition>,
+}
+
+impl Decodable for MusicSource {
+    type DecoderItem = i16;
+    type Decoder = MusicDecoder;
+
+    fn decoder(&self) -> MusicDecoder {
+        // The bytes already decoded once when the track was loaded.
+        let decoder =
+            rodio::Decoder::new(Cursor::new(self.bytes.clone())).expect("a loaded track decodes");
+        self.position.rate.store(
+            decoder.channels() as u32 * decoder.sample_rate(),
+            Ordering::Relaxed,
+        );
+        MusicDecoder {
+            bytes: self.bytes.clone(),
+            decoder,
+            position: self.position.clone(),
+        }
+    }
+}
+
+impl Iterator for MusicDecoder {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = match self.decoder.next() {
+            Some(sample) => sample,
+            None => {
+                // Start the track over, and the position with it.
+                self.decoder = rodio::Decoder::new(Cursor::new(self.bytes.clone())).ok()?;
+                self.position.reset();
+                self.decoder.next()?
+            }
+        };
+        self.position.samples.fetch_add(1, Ordering::Relaxed);
+        Some(sample)
+    }
+}
+
+impl Source for MusicDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+// The loaded track and where playback is in it.
+#[derive(Resource, Default)]
+struct Track {
+    loading: Option<Task<Result<LoadedTrack, String>>>,
+    name: String,
+    analysis: AudioAnalysis,
+    source: Option<Handle<MusicSource>>,
+    // Shared with the source, and through it with the decoder playing the track.
+    position: Arc<PlaybackPosition>,
+    // Playback position in seconds.
+    time: f32,
+    error: Option<String>,
+}
+
+// Marks the entity playing the track.
+#[derive(Component)]
+struct Music;
+
+// What the music modulates from, taken when it starts playing and put back when it stops.
+#[derive(Resource, Default)]
+struct ModulationBase {
+    snapshot: Option<(FractalType, ActiveFractalParams, usize)>,
+}
+
+// Zoom factor the music puts on the camera projection. It is applied after the camera systems
+// and taken off again before them, so pancam, history and the timeline never see it.
+#[derive(Resource)]
+struct ZoomPulse {
+    target: f32,
+    applied: f32,
+}
+
+impl Default for ZoomPulse {
+    fn default() -> Self {
+        ZoomPulse {
+            target: 1.0,
+            applied: 1.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<MusicSource>()
+            .init_resource::<MusicUpdateToggle>()
+            .init_resource::<AudioReactive>()
+            .init_resource::<Track>()
+            .init_resource::<ModulationBase>()
+            .init_resource::<ZoomPulse>()
+            .add_systems(PreUpdate, remove_zoom_pulse_system)
+            .add_systems(
+                Update,
+                (
+                    track_load_system,
+                    // After the timeline, so a timeline started this frame pauses the music.
+                    music_playback_system.after(timeline_playback_system),
+                    audio_ui_system,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_zoom_pulse_system.before(CameraUpdateSystem),
+            );
+    }
+}
+
+// Finish loading a track and make it ready to play.
+fn track_load_system(
+    mut commands: Commands,
+    mut track: ResMut<Track>,
+    mut music_toggle: ResMut<MusicUpdateToggle>,
+    mut audio_sources: ResMut<Assets<MusicSource>>,
+    players: Query<Entity, With<Music>>,
+) {
+    let Some(task) = track.loading.as_mut() else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    track.loading = None;
+    match result {
+        Ok(loaded) => {
+            for entity in &players {
+                commands.entity(entity).despawn();
+            }
+            music_toggle.active = false;
+            track.name = loaded.name;
+            track.analysis = loaded.analysis;
+            track.source = Some(audio_sources.add(MusicSource {
+                bytes: loaded.bytes.into(),
+                position: track.position.clone(),
+            }));
+            track.time = 0.0;
+            track.error = None;
+        }
+        Err(err) => {
+            error!("Could not load music: {err}");
+            track.error = Some(err);
+        }
+    }
+}
+
+// Play or pause the track with the toggle and modulate the fractal from its analysis.
+#[allow(clippy::too_many_arguments)]
+fn music_playback_system(
+    mut commands: Commands,
+    mut track: ResMut<Track>,
+    mut music_toggle: ResMut<MusicUpdateToggle>,
+    mut animation: ResMut<AnimationUpdateToggle>,
+    settings: Res<AudioReactive>,
+    fractal_type: Res<FractalType>,
+    mut materials: FractalMaterials,
+    mut progressive_settings: ResMut<ProgressiveSettings>,
+    mut base: ResMut<ModulationBase>,
+    mut pulse: ResMut<ZoomPulse>,
+    players: Query<Option<&AudioSink>, With<Music>>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    // The music and the timeline both drive the parameters, so only one plays at a time.
+    if music_toggle.active && track.source.is_none() {
+        music_toggle.active = false;
+    }
+    if music_toggle.is_changed() && music_toggle.active {
+        animation.active = false;
+    } else if music_toggle.active && animation.active {
+        music_toggle.active = false;
+    }
+
+    let player = players.get_single().ok();
+    match (music_toggle.active, player, &track.source) {
+        (true, None, Some(source)) => {
+            track.position.reset();
+            commands.spawn((
+                AudioSourceBundle {
+                    source: source.clone(),
+                    settings: PlaybackSettings::ONCE,
+                },
+                Music,
+            ));
+        }
+        (active, Some(Some(sink)), _) => {
+            if active && sink.is_paused() {
+                sink.play();
+            } else if !active && !sink.is_paused() {
+                sink.pause();
+            }
+        }
+        _ => {}
+    }
+
+    if !music_toggle.active {
+        if let Some((fractal, params, palette)) = base.snapshot.take() {
+            materials.restore(&fractal, &params);
+            if progressive_settings.palette != palette {
+                progressive_settings.palette = palette;
+            }
+        }
+        pulse.target = 1.0;
+        return;
+    }
+
+    // Playback never settles, so keep waking the app when it updates reactively.
+    redraw.send(RequestRedraw);
+    // The position stands still while the sink is paused and wraps with the track.
+    track.time = track.position.seconds().min(track.analysis.duration());
+
+    // Switching fractals while playing puts the old one back and modulates the new one.
+    if let Some((fractal, params, _)) = base.snapshot {
+        if fractal != *fractal_type {
+            materials.restore(&fractal, &params);
+            base.snapshot = None;
+        }
+    }
+    if base.snapshot.is_none() {
+        let Some(params) = materials.active(&fractal_type) else {
+            return;
+        };
+        base.snapshot = Some((*fractal_type, params, progressive_settings.palette));
+    }
+    let Some((_, params, palette)) = base.snapshot else {
+        return;
+    };
+    let modulated = modulate(
+        &settings.mappings,
+        &track.analysis.sample(track.time),
+        &params,
+        palette,
+    );
+    materials.restore(&fractal_type, &modulated.params);
+    if progressive_settings.palette != modulated.palette {
+        progressive_settings.palette = modulated.palette;
+    }
+    pulse.target = modulated.zoom;
+}
+
+fn remove_zoom_pulse_system(
+    mut pulse: ResMut<ZoomPulse>,
+    mut cameras: Query<&mut OrthographicProjection, With<PanCamState>>,
+) {
+    if pulse.applied == 1.0 {
+        return;
+    }
+    if let Ok(mut proj) = cameras.get_single_mut() {
+        proj.scale /= pulse.applied;
+    }
+    pulse.applied = 1.0;
+}
+
+fn apply_zoom_pulse_system(
+    mut pulse: ResMut<ZoomPulse>,
+    mut cameras: Query<&mut OrthographicProjection, With<PanCamState>>,
+) {
+    if pulse.target == 1.0 {
+        return;
+    }
+    if let Ok(mut proj) = cameras.get_single_mut() {
+        proj.scale *= pulse.target;
+        pulse.applied = pulse.target;
+    }
+}
+
+// The Audio window: load a track, play it, edit the mapping table and export synced frames.
+#[allow(clippy::too_many_arguments)]
+fn audio_ui_system(
+    mut commands: Commands,
+    mut ctx: EguiContexts,
+    mut settings: ResMut<AudioReactive>,
+    mut track: ResMut<Track>,
+    mut music_toggle: ResMut<MusicUpdateToggle>,
+    base: Res<ModulationBase>,
+    fractal_type: Res<FractalType>,
+    materials: FractalMaterials,
+    progressive_settings: Res<ProgressiveSettings>,
+    mut export_state: ResMut<ExportState>,
+    export_settings: Res<ExportSettings>,
+    cameras: Query<(&Camera, &OrthographicProjection, &Transform), With<PanCamState>>,
+    players: Query<Entity, With<Music>>,
+) {
+    // Widgets edit a copy, so the settings only count as changed on real edits.
+    let mut edited = settings.clone();
+    let mut load = false;
+    let mut play = music_toggle.active;
+    let mut stop = false;
+    let mut start_export = false;
+
+    egui::Window::new("Audio")
+        .default_open(false)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut edited.path)
+                        .hint_text("path/to/track.flac")
+                        .desired_width(200.0),
+                );
+                load = ui
+                    .add_enabled(track.loading.is_none(), egui::Button::new("Load"))
+                    .on_hover_text("Decode and analyze a FLAC, OGG Vorbis or WAV file")
+                    .clicked();
+            });
+            if track.loading.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Analyzing…");
+                });
+            }
+            if let Some(err) = &track.error {
+                ui.colored_label(egui::Color32::from_rgb(230, 80, 60), err);
+            }
+            if track.source.is_none() {
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label(&track.name);
+                ui.label(format!(
+                    "{:.1} / {:.1} s",
+                    track.time,
+                    track.analysis.duration()
+                ));
+            });
+            ui.horizontal(|ui| {
+                let label = if play { "Pause" } else { "Play" };
+                if ui.button(label).clicked() {
+                    play = !play;
+                }
+                stop = ui.button("Stop").clicked();
+            });
+            let features = track.analysis.sample(track.time);
+            egui::Grid::new("audio_features").show(ui, |ui| {
+                for feature in Feature::ALL {
+                    ui.label(feature.label());
+                    ui.add(egui::ProgressBar::new(features[feature as usize]).desired_width(160.0));
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            ui.label("Mappings");
+            let mut remove = None;
+            egui::Grid::new("audio_mappings").show(ui, |ui| {
+                for (index, modulation) in edited.mappings.iter_mut().enumerate() {
+                    egui::ComboBox::from_id_source(("audio_feature", index))
+                        .selected_text(modulation.feature.label())
+                        .show_ui(ui, |ui| {
+                            for feature in Feature::ALL {
+                                ui.selectable_value(
+                                    &mut modulation.feature,
+                                    feature,
+                                    feature.label(),
+                                );
+                            }
+                        });
+                    egui::ComboBox::from_id_source(("audio_target", index))
+                        .selected_text(modulation.target.label())
+                        .show_ui(ui, |ui| {
+                            for target in Target::ALL {
+                                ui.selectable_value(&mut modulation.target, target, target.label());
+                            }
+                        });
+                    ui.add(egui::DragValue::new(&mut modulation.amount).speed(0.005));
+                    if ui.small_button("✕").clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(index) = remove {
+                edited.mappings.remove(index);
+            }
+            if ui.button("Add mapping").clicked() {
+                edited.mappings.push(Modulation {
+                    feature: Feature::Bass,
+                    target: Target::ColorScale,
+                    amount: 0.1,
+                });
+            }
+
+            ui.separator();
+            match export_state.progress() {
+                Some((done, total)) => {
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                            .text(format!("Exporting image {done} of {total}")),
+                    );
+                }
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.label("Frame rate:");
+                        ui.add(
+                            egui::DragValue::new(&mut edited.export_fps)
+                                .clamp_range(1..=120)
+                                .suffix(" fps"),
+                        );
+                        start_export = ui
+                            .button("Export frames")
+                            .on_hover_text(
+                                "Render the current view for every frame of the track, as \
+                                 numbered PNGs to mux with the audio",
+                            )
+                            .clicked();
+                    });
+                }
+            }
+        });
+
+    if load && !edited.path.trim().is_empty() {
+        let path = PathBuf::from(edited.path.trim());
+        track.loading = Some(AsyncComputeTaskPool::get().spawn(async move { load_track(path) }));
+    }
+    if stop {
+        play = false;
+        track.time = 0.0;
+        for entity in &players {
+            commands.entity(entity).despawn();
+        }
+    }
+    if play != music_toggle.active {
+        music_toggle.active = play;
+    }
+    if start_export {
+        // While playing, export from the unmodulated parameters rather than this frame's.
+        let base = match base.snapshot {
+            Some((fractal, params, palette)) if fractal == *fractal_type => Some((params, palette)),
+            _ => materials
+                .active(&fractal_type)
+                .map(|params| (params, progressive_settings.palette)),
+        };
+        match (base, cameras.get_single()) {
+            (Some((params, palette)), Ok((camera, proj, transform))) => {
+                if let Some(view) = SequenceView::of_camera(camera, proj, transform) {
+                    let frames = audio_frames(&track.analysis, &edited, &params, palette, view);
+                    export_state.request_sequence("audio", frames, &export_settings);
+                }
+            }
+            (_, Err(err)) => error!("Could not export audio frames: {err}"),
+            (None, _) => {}
+        }
+    }
+    if edited != *settings {
+        *settings = edited;
+    }
+}
+
+// One frame per 1 / export_fps seconds of the track, modulated from the base parameters and view.
+// Everything comes from the analysis, so the same file and settings always give the same frames.
+fn audio_frames(
+    analysis: &AudioAnalysis,
+    settings: &AudioReactive,
+    base: &ActiveFractalParams,
+    base_palette: usize,
+    view: SequenceView,
+) -> Vec<PinnedFrame> {
+    let fps = settings.export_fps.max(1) as f32;
+    let count = (analysis.duration() * fps).ceil() as usize;
+    (0..count)
+        .map(|frame| {
+            let features = analysis.sample(frame as f32 / fps);
+            let modulated = modulate(&settings.mappings, &features, base, base_palette);
+            PinnedFrame {
+                fractal: modulated.params.fractal,
+                center: view.center,
+                pixel_step: view.pixel_step * modulated.zoom,
+                julia_c: modulated.params.julia_c,
+                color_scale: modulated.params.color_scale,
+                periodicity_tolerance: modulated.params.periodicity_tolerance,
+                palette: modulated.palette,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_finds_a_pure_tone() {
+        let bin = 37;
+        let mut re: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| (TAU * bin as f32 * i as f32 / FFT_SIZE as f32).sin())
+            .collect();
+        let mut im = vec![0.0; FFT_SIZE];
+        fft(&mut re, &mut im, &twiddles(FFT_SIZE));
+        let loudest = (0..FFT_SIZE / 2)
+            .max_by(|&a, &b| {
+                let power = |k: usize| re[k] * re[k] + im[k] * im[k];
+                power(a).total_cmp(&power(b))
+            })
+            .unwrap();
+        assert_eq!(loudest, bin);
+        // A unit sine puts half its amplitude times n in each of its two bins.
+        let magnitude = (re[bin] * re[bin] + im[bin] * im[bin]).sqrt();
+        assert!((magnitude - FFT_SIZE as f32 / 2.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn beats_follow_bass_hits() {
+        let sample_rate = 44100;
+        let hits: Vec<f32> = (0..8).map(|i| 0.25 + 0.5 * i as f32).collect();
+        let samples: Vec<f32> = (0..4 * sample_rate)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let in_hit = hits.iter().any(|&hit| (hit..hit + 0.05).contains(&t));
+                if in_hit {
+                    (TAU * 80.0 * t).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let analysis = analyze(&samples, sample_rate);
+        let onsets: Vec<f32> = analysis
+            .frames
+            .iter()
+            .enumerate()
+            .filter(|(_, features)| features[Feature::Beat as usize] == 1.0)
+            .map(|(frame, _)| frame as f32 / ANALYSIS_RATE)
+            .collect();
+        assert_eq!(onsets.len(), hits.len());
+        for (onset, hit) in onsets.iter().zip(&hits) {
+            assert!((onset - hit).abs() < 0.05, "beat at {onset}, hit at {hit}");
+        }
+        // Bands are normalized to the track.
+        for features in &analysis.frames {
+            assert!(features.iter().all(|value| (0.0..=1.0).contains(value)));
+        }
+    }
+
+    #[test]
+    fn zoom_pulses_shrink_the_view() {
+        let base = ActiveFractalParams {
+            fractal: 1,
+            color_scale: 0.9,
+            max_iterations: 500.0,
+            julia_c: Vec2::new(-0.8, 0.156),
+            periodicity_tolerance: 1e-5,
+        };
+        let mappings = AudioReactive::default().mappings;
+        let mut features = [0.0; FEATURE_COUNT];
+        features[Feature::Bass as usize] = 1.0;
+        features[Feature::Beat as usize] = 1.0;
+        let modulated = modulate(&mappings, &features, &base, PALETTES.len() - 1);
+        assert!((modulated.zoom - 1.0 / 1.1).abs() < 1e-6);
+        // The beat pushes the color scale past its range.
+        assert_eq!(modulated.params.color_scale, 1.0);
+        assert_eq!(modulated.params.julia_c, base.julia_c);
+        assert_eq!(modulated.palette, PALETTES.len() - 1);
+    }
+
+    // A 16-bit mono WAV of `samples` samples at `rate` Hz.
+    fn wav(samples: u32, rate: u32) -> Vec<u8> {
+        let data = samples * 2;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&rate.to_le_bytes());
+        bytes.extend_from_slice(&(rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data.to_le_bytes());
+        bytes.resize(bytes.len() + data as usize, 0);
+        bytes
+    }
+
+    #[test]
+    fn playback_position_counts_played_samples_and_loops() {
+        let source = MusicSource {
+            bytes: wav(1000, 1000).into(),
+            position: Arc::default(),
+        };
+        let mut decoder = source.decoder();
+        // Nothing has been heard until the output reads from the decoder.
+        assert_eq!(source.position.seconds(), 0.0);
+        decoder.by_ref().take(250).for_each(drop);
+        assert!((source.position.seconds() - 0.25).abs() < 1e-6);
+        // Past the end the track starts over.
+        decoder.by_ref().take(1000).for_each(drop);
+        assert!((source.position.seconds() - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn audio_frames_pin_the_modulated_view() {
+        let base = ActiveFractalParams {
+            fractal: 1,
+            color_scale: 0.5,
+            max_iterations: 500.0,
+            julia_c: Vec2::new(-0.8, 0.156),
+            periodicity_tolerance: 1e-5,
+        };
+        let mut bass = [0.0; FEATURE_COUNT];
+        bass[Feature::Bass as usize] = 1.0;
+        let analysis = AudioAnalysis {
+            frames: vec![[0.0; FEATURE_COUNT], bass],
+        };
+        let settings = AudioReactive {
+            mappings: vec![Modulation {
+                feature: Feature::Bass,
+                target: Target::Zoom,
+                amount: 1.0,
+            }],
+            export_fps: ANALYSIS_RATE as u32,
+            ..default()
+        };
+        let view = SequenceView {
+            center: Vec2::new(-0.5, 0.1),
+            pixel_step: 0.01,
+        };
+        let frames = audio_frames(&analysis, &settings, &base, 2, view);
+        assert_eq!(frames.len(), 2);
+        for frame in &frames {
+            assert_eq!(frame.fractal, base.fractal);
+            assert_eq!(frame.center, view.center);
+            assert_eq!(frame.julia_c, base.julia_c);
+            assert_eq!(frame.palette, 2);
+        }
+        // Full bass halves the pixel spacing, zooming in 2x.
+        assert_eq!(frames[0].pixel_step, 0.01);
+        assert!((frames[1].pixel_step - 0.005).abs() < 1e-9);
+    }
+}
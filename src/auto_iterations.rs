@@ -0,0 +1,474 @@
+use std::borrow::Cow;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_graph::{self, RenderGraph};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::window::PrimaryWindow;
+use wgpu::{BufferAsyncError, Maintain};
+
+use crate::fractals::{world_to_complex, FractalType, WORLD_UNITS_PER_COMPLEX_UNIT};
+use crate::materials::FractalMaterials;
+use crate::pancam::{PanCamState, PanCamSystemSet};
+use crate::progressive::{ProgressiveParams, ProgressiveSettings};
+
+const WORKGROUP_SIZE: u32 = 8;
+const STATS_NODE: &str = "iteration_stats";
+// Columns of the downsampled grid the statistics are gathered on; rows follow the window aspect.
+const STATS_GRID_WIDTH: u32 = 64;
+// Frames between two statistics passes.
+const STATS_INTERVAL: u32 = 10;
+// Four u32 counters: samples, escaped, interior, capped.
+const STATS_BUFFER_SIZE: u64 = 16;
+
+// Fraction of capped pixels above which iterations are raised, and below which they are lowered.
+const CAPPED_HIGH: f32 = 0.01;
+const CAPPED_LOW: f32 = 0.001;
+// Relative change needed before a new iteration count is applied, to avoid flicker.
+const CHANGE_THRESHOLD: f32 = 1.2;
+// Minimum seconds between two changes of the iteration count.
+const CHANGE_COOLDOWN: f32 = 0.5;
+
+// Settings and state for choosing max_iterations automatically.
+#[derive(Resource, Clone, PartialEq)]
+pub struct AutoIterations {
+    pub enabled: bool,
+    // Bounds the automatic value never leaves.
+    pub min_iterations: u32,
+    pub max_iterations: u32,
+    // Iterations at the initial magnification.
+    pub base_iterations: u32,
+    // Iterations added for every factor of ten of magnification.
+    pub iterations_per_decade: f32,
+    // Value currently applied to the materials and the progressive renderer; 0 until first applied.
+    pub current: u32,
+    // Multiplier learned from the capped pixel statistics.
+    feedback: f32,
+    last_change: f32,
+}
+
+impl Default for AutoIterations {
+    fn default() -> Self {
+        AutoIterations {
+            enabled: false,
+            min_iterations: 100,
+            max_iterations: 50_000,
+            base_iterations: 500,
+            iterations_per_decade: 1000.0,
+            current: 0,
+            feedback: 1.0,
+            last_change: f32::NEG_INFINITY,
+        }
+    }
+}
+
+impl AutoIterations {
+    // Iterations wanted at a magnification, before the bounds are applied.
+    pub fn estimate(&self, magnification: f32) -> f32 {
+        let decades = magnification.max(1.0).log10();
+        (self.base_iterations as f32 + self.iterations_per_decade * decades) * self.feedback
+    }
+
+    // Nudge the feedback multiplier from the share of sampled pixels that hit the cap.
+    fn apply_stats(&mut self, stats: &IterationStats) {
+        if stats.samples == 0 {
+            return;
+        }
+        let capped = stats.capped as f32 / stats.samples as f32;
+        if capped > CAPPED_HIGH {
+            self.feedback = (self.feedback * 1.25).min(64.0);
+        } else if capped < CAPPED_LOW {
+            self.feedback = (self.feedback * 0.9).max(0.25);
+        }
+    }
+}
+
+// Counters read back from the statistics pass that the feedback uses.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IterationStats {
+    pub max_iterations: u32,
+    pub samples: u32,
+    pub capped: u32,
+}
+
+// Latest statistics, shared between the main and render worlds.
+#[derive(Resource, Clone, Default)]
+struct LatestIterationStats(Arc<Mutex<Option<IterationStats>>>);
+
+// Set on frames where the render world should run a statistics pass.
+#[derive(Resource, Clone, Default, ExtractResource)]
+struct IterationStatsRequest {
+    params: Option<ProgressiveParams>,
+    // Count escapes the way the compute renderer does rather than the fragment shaders.
+    compute_renderer: bool,
+}
+
+#[derive(Default)]
+pub struct AutoIterationsPlugin;
+
+impl Plugin for AutoIterationsPlugin {
+    fn build(&self, app: &mut App) {
+        let latest = LatestIterationStats::default();
+        app.init_resource::<AutoIterations>()
+            .init_resource::<IterationStatsRequest>()
+            .insert_resource(latest.clone())
+            .add_plugins(ExtractResourcePlugin::<IterationStatsRequest>::default())
+            .add_systems(Update, auto_iterations_system.after(PanCamSystemSet));
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(latest).add_systems(
+            Render,
+            (
+                prepare_iteration_stats.in_set(RenderSet::PrepareBindGroups),
+                read_iteration_stats.in_set(RenderSet::Cleanup),
+            ),
+        );
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(STATS_NODE, IterationStatsNode::default());
+        render_graph.add_node_edge(STATS_NODE, bevy::render::main_graph::node::CAMERA_DRIVER);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<IterationStatsPipeline>();
+        render_app.init_resource::<IterationStatsBuffers>();
+    }
+}
+
+// System to pick max_iterations from the zoom depth and the capped pixel feedback.
+#[allow(clippy::too_many_arguments)]
+fn auto_iterations_system(
+    time: Res<Time>,
+    mut auto: ResMut<AutoIterations>,
+    latest: Res<LatestIterationStats>,
+    mut request: ResMut<IterationStatsRequest>,
+    mut materials: FractalMaterials,
+    mut progressive: ResMut<ProgressiveSettings>,
+    fractal_type: Res<FractalType>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection, &PanCamState)>,
+    mut frames: Local<u32>,
+) {
+    request.params = None;
+    if !auto.enabled {
+        if auto.current != 0 {
+            auto.current = 0;
+        }
+        return;
+    }
+    let (Ok(window), Ok((transform, projection, pancam))) =
+        (primary_window.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    let stats = latest.0.lock().ok().and_then(|mut stats| stats.take());
+    if let Some(stats) = stats {
+        // Statistics gathered with an older iteration count say nothing about the current one.
+        if stats.max_iterations == auto.current {
+            auto.apply_stats(&stats);
+        }
+    }
+
+    let target = auto
+        .estimate(pancam.magnification(projection.scale))
+        .clamp(
+            auto.min_iterations as f32,
+            auto.max_iterations.max(auto.min_iterations) as f32,
+        )
+        .round() as u32;
+    let now = time.elapsed_seconds();
+    let ratio = target as f32 / auto.current.max(1) as f32;
+    let outside_band = !(1.0 / CHANGE_THRESHOLD..=CHANGE_THRESHOLD).contains(&ratio);
+    // A freshly spawned fractal starts with its default iterations, so reapply to it right away.
+    let stale_material = materials
+        .active(&fractal_type)
+        .map_or(false, |active| active.max_iterations != auto.current as f32);
+    let retarget = auto.current == 0 || (outside_band && now - auto.last_change >= CHANGE_COOLDOWN);
+    if retarget {
+        auto.current = target;
+        auto.last_change = now;
+    }
+    if retarget || stale_material {
+        materials.set_max_iterations(auto.current as f32);
+        progressive.max_iterations = auto.current;
+    }
+
+    *frames += 1;
+    if *frames % STATS_INTERVAL != 0 {
+        return;
+    }
+    let Some(active) = materials.active(&fractal_type) else {
+        return;
+    };
+    let aspect = window.height() / window.width().max(1.0);
+    let grid = UVec2::new(
+        STATS_GRID_WIDTH,
+        ((STATS_GRID_WIDTH as f32 * aspect).round() as u32).max(1),
+    );
+    let spacing = window.width() * projection.scale / WORLD_UNITS_PER_COMPLEX_UNIT / grid.x as f32;
+    let center = world_to_complex(transform.translation.truncate());
+    request.compute_renderer = progressive.enabled;
+    request.params = Some(ProgressiveParams {
+        origin: center - (grid.as_vec2() / 2.0 - 0.5) * spacing,
+        pixel_step: Vec2::splat(spacing),
+        julia_c: active.julia_c,
+        size: grid,
+        fractal: active.fractal,
+        max_iterations: auto.current,
+        periodicity_tolerance: active.periodicity_tolerance,
+        ..default()
+    });
+}
+
+#[derive(Resource)]
+struct IterationStatsPipeline {
+    layout: BindGroupLayout,
+    // The same pass with the fragment shaders' and the compute renderer's escape test.
+    fragment_escape: CachedComputePipelineId,
+    compute_escape: CachedComputePipelineId,
+}
+
+impl IterationStatsPipeline {
+    fn pipeline(&self, compute_renderer: bool) -> CachedComputePipelineId {
+        if compute_renderer {
+            self.compute_escape
+        } else {
+            self.fragment_escape
+        }
+    }
+}
+
+impl FromWorld for IterationStatsPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("iteration_stats_bind_group_layout"),
+                    entries: &[
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: Some(ProgressiveParams::min_size()),
+                            },
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let shader = world
+            .resource::<AssetServer>()
+            .load("shaders/iteration_stats.wgsl");
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let queue = |shader_defs: Vec<ShaderDefVal>| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(Cow::from("iteration_stats")),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs,
+                entry_point: Cow::from("stats"),
+            })
+        };
+        let fragment_escape = queue(vec!["FRAGMENT_ESCAPE".into()]);
+        let compute_escape = queue(vec![]);
+        IterationStatsPipeline {
+            layout,
+            fragment_escape,
+            compute_escape,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct IterationStatsBuffers {
+    uniform: UniformBuffer<ProgressiveParams>,
+    counters: Buffer,
+    // Mappable copy of the counters for the CPU to read.
+    staging: Buffer,
+    bind_group: Option<BindGroup>,
+    // Iteration count of the pass recorded this frame, if any.
+    pending: Option<u32>,
+    // Iteration count of the pass whose counters are being mapped, and where the map result
+    // arrives. No new pass is recorded until the staging buffer is free again.
+    mapping: Option<(u32, Mutex<Receiver<Result<(), BufferAsyncError>>>)>,
+}
+
+impl FromWorld for IterationStatsBuffers {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let counters = render_device.create_buffer(&BufferDescriptor {
+            label: Some("iteration_stats_counters"),
+            size: STATS_BUFFER_SIZE,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let staging = render_device.create_buffer(&BufferDescriptor {
+            label: Some("iteration_stats_staging"),
+            size: STATS_BUFFER_SIZE,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        IterationStatsBuffers {
+            uniform: UniformBuffer::default(),
+            counters,
+            staging,
+            bind_group: None,
+            pending: None,
+            mapping: None,
+        }
+    }
+}
+
+fn prepare_iteration_stats(
+    request: Res<IterationStatsRequest>,
+    pipeline: Res<IterationStatsPipeline>,
+    mut buffers: ResMut<IterationStatsBuffers>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    buffers.bind_group = None;
+    buffers.pending = None;
+    let (Some(params), None) = (request.params, &buffers.mapping) else {
+        return;
+    };
+
+    buffers.uniform.set(params);
+    buffers.uniform.write_buffer(&render_device, &render_queue);
+    let Some(uniform) = buffers.uniform.binding() else {
+        return;
+    };
+    let bind_group = render_device.create_bind_group(
+        "iteration_stats_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((uniform, buffers.counters.as_entire_binding())),
+    );
+    buffers.bind_group = Some(bind_group);
+    buffers.pending = Some(params.max_iterations);
+}
+
+// Start mapping the staging buffer once a pass was submitted, and hand the counters to the main
+// world on a later frame when the map has completed. Never waits on the GPU.
+fn read_iteration_stats(
+    mut buffers: ResMut<IterationStatsBuffers>,
+    render_device: Res<RenderDevice>,
+    latest: Res<LatestIterationStats>,
+) {
+    if let Some(max_iterations) = buffers.pending.take() {
+        let (sender, receiver) = mpsc::channel();
+        buffers
+            .staging
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        buffers.mapping = Some((max_iterations, Mutex::new(receiver)));
+    }
+    let Some((max_iterations, receiver)) = &buffers.mapping else {
+        return;
+    };
+    render_device.wgpu_device().poll(Maintain::Poll);
+    let result = match receiver.lock() {
+        Ok(receiver) => receiver.try_recv(),
+        Err(_) => return,
+    };
+    let max_iterations = *max_iterations;
+    match result {
+        Err(mpsc::TryRecvError::Empty) => return,
+        Ok(Ok(())) => {
+            let counters: Vec<u32> = buffers
+                .staging
+                .slice(..)
+                .get_mapped_range()
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+            buffers.staging.unmap();
+            if let Ok(mut stats) = latest.0.lock() {
+                *stats = Some(IterationStats {
+                    max_iterations,
+                    samples: counters[0],
+                    capped: counters[3],
+                });
+            }
+        }
+        // A failed map leaves the buffer unmapped; the next pass simply tries again.
+        Ok(Err(_)) | Err(mpsc::TryRecvError::Disconnected) => {}
+    }
+    buffers.mapping = None;
+}
+
+// Render graph node that clears the counters, runs the statistics pass and copies the result out.
+#[derive(Default)]
+struct IterationStatsNode {
+    ready: bool,
+}
+
+impl render_graph::Node for IterationStatsNode {
+    fn update(&mut self, world: &mut World) {
+        let compute_renderer = world
+            .get_resource::<IterationStatsRequest>()
+            .is_some_and(|request| request.compute_renderer);
+        let pipeline = world
+            .resource::<IterationStatsPipeline>()
+            .pipeline(compute_renderer);
+        self.ready = matches!(
+            world
+                .resource::<PipelineCache>()
+                .get_compute_pipeline_state(pipeline),
+            CachedPipelineState::Ok(_)
+        );
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let buffers = world.resource::<IterationStatsBuffers>();
+        let request = world.resource::<IterationStatsRequest>();
+        let (true, Some(bind_group), Some(params)) =
+            (self.ready, &buffers.bind_group, request.params)
+        else {
+            return Ok(());
+        };
+        let Some(pipeline) = world.resource::<PipelineCache>().get_compute_pipeline(
+            world
+                .resource::<IterationStatsPipeline>()
+                .pipeline(request.compute_renderer),
+        ) else {
+            return Ok(());
+        };
+
+        let workgroups = (params.size + (WORKGROUP_SIZE - 1)) / WORKGROUP_SIZE;
+        let encoder = render_context.command_encoder();
+        encoder.clear_buffer(&buffers.counters, 0, None);
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&buffers.counters, 0, &buffers.staging, 0, STATS_BUFFER_SIZE);
+        Ok(())
+    }
+}
@@ -0,0 +1,961 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::MouseWheel;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::{egui, EguiContext, EguiContexts, EguiSet};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::gamepad::{gamepad_controls_ui, GamepadControls};
+
+// The bindings that differ from the defaults are kept in this file in the config directory, read
+// at startup and written whenever they are edited.
+pub const BINDINGS_FILE: &str = "bindings.ron";
+// The gamepad stick and trigger tuning is kept next to it the same way.
+pub const GAMEPAD_FILE: &str = "gamepad.ron";
+// Written at the top of the bindings file, which is meant to be edited by hand as well.
+const BINDINGS_HEADER: &str = "\
+// Key, mouse and gamepad bindings. Each action takes a list of bindings; an input is one of
+// Key(<KeyCode>), Mouse(Left | Right | Middle | Other(n)), DoubleClick(<mouse button>), ScrollUp,
+// ScrollDown or Gamepad(<GamepadButtonType>, e.g. South, East, North, West, Select, Start), and
+// `modifiers: (shift: true, ctrl: true, alt: true)` lists the modifier keys that must be held.
+// Actions left out keep their default bindings. The Key Bindings window rewrites this file.
+";
+// Longest gap in seconds, and furthest cursor travel in logical pixels, between the two presses
+// of a double click.
+const DOUBLE_CLICK_TIME: f64 = 0.35;
+const DOUBLE_CLICK_DISTANCE: f32 = 6.0;
+
+// The back and forward mouse side buttons, numbered the way each platform reports them.
+#[cfg(target_os = "windows")]
+const SIDE_BUTTONS: [(MouseButton, MouseButton); 1] =
+    [(MouseButton::Other(1), MouseButton::Other(2))];
+#[cfg(target_os = "macos")]
+const SIDE_BUTTONS: [(MouseButton, MouseButton); 1] =
+    [(MouseButton::Other(3), MouseButton::Other(4))];
+// X11, then Wayland, which passes the evdev codes through.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SIDE_BUTTONS: [(MouseButton, MouseButton); 2] = [
+    (MouseButton::Other(8), MouseButton::Other(9)),
+    (MouseButton::Other(275), MouseButton::Other(276)),
+];
+
+// Everything the user can trigger from the keyboard, mouse buttons, scroll wheel or gamepad buttons.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    CycleFractal,
+    CyclePalette,
+    ToggleAnimation,
+    ToggleMusic,
+    Pan,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    ZoomBoost,
+    BoxZoom,
+    ClickZoomIn,
+    ClickZoomOut,
+    ResetView,
+    HistoryBack,
+    HistoryForward,
+    ShowOrbit,
+    ToggleMinimap,
+    ToggleHelp,
+}
+
+impl Action {
+    pub const ALL: [Action; 21] = [
+        Action::CycleFractal,
+        Action::CyclePalette,
+        Action::ToggleAnimation,
+        Action::ToggleMusic,
+        Action::Pan,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ZoomBoost,
+        Action::BoxZoom,
+        Action::ClickZoomIn,
+        Action::ClickZoomOut,
+        Action::ResetView,
+        Action::HistoryBack,
+        Action::HistoryForward,
+        Action::ShowOrbit,
+        Action::ToggleMinimap,
+        Action::ToggleHelp,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::CycleFractal => "Cycle fractal",
+            Action::CyclePalette => "Cycle palette",
+            Action::ToggleAnimation => "Play or pause the timeline",
+            Action::ToggleMusic => "Play or pause the music",
+            Action::Pan => "Pan (drag)",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::PanUp => "Pan up",
+            Action::PanDown => "Pan down",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::ZoomBoost => "Zoom faster (hold)",
+            Action::BoxZoom => "Zoom to box (drag)",
+            Action::ClickZoomIn => "Zoom in 2x at cursor",
+            Action::ClickZoomOut => "Zoom out 2x at cursor",
+            Action::ResetView => "Reset view",
+            Action::HistoryBack => "Previous view",
+            Action::HistoryForward => "Next view",
+            Action::ShowOrbit => "Show orbit at cursor (hold)",
+            Action::ToggleMinimap => "Show or hide the minimap",
+            Action::ToggleHelp => "Show controls",
+        }
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        let inputs = match self {
+            Action::CycleFractal => vec![
+                InputKind::Key(KeyCode::Space),
+                InputKind::Gamepad(GamepadButtonType::South),
+            ],
+            Action::CyclePalette => vec![
+                InputKind::Key(KeyCode::C),
+                InputKind::Gamepad(GamepadButtonType::East),
+            ],
+            Action::ToggleAnimation => vec![
+                InputKind::Key(KeyCode::P),
+                InputKind::Gamepad(GamepadButtonType::North),
+            ],
+            Action::ToggleMusic => vec![InputKind::Key(KeyCode::M)],
+            Action::Pan => vec![
+                InputKind::Mouse(MouseButton::Left),
+                InputKind::Mouse(MouseButton::Middle),
+            ],
+            Action::PanLeft => vec![InputKind::Key(KeyCode::Left), InputKind::Key(KeyCode::A)],
+            Action::PanRight => vec![InputKind::Key(KeyCode::Right), InputKind::Key(KeyCode::D)],
+            Action::PanUp => vec![InputKind::Key(KeyCode::Up), InputKind::Key(KeyCode::W)],
+            Action::PanDown => vec![InputKind::Key(KeyCode::Down), InputKind::Key(KeyCode::S)],
+            Action::ZoomIn => vec![
+                InputKind::ScrollUp,
+                InputKind::Key(KeyCode::Equals),
+                InputKind::Key(KeyCode::NumpadAdd),
+                InputKind::Key(KeyCode::PageUp),
+            ],
+            Action::ZoomOut => vec![
+                InputKind::ScrollDown,
+                InputKind::Key(KeyCode::Minus),
+                InputKind::Key(KeyCode::NumpadSubtract),
+                InputKind::Key(KeyCode::PageDown),
+            ],
+            Action::ZoomBoost => vec![InputKind::Key(KeyCode::ShiftLeft)],
+            Action::BoxZoom => vec![InputKind::Mouse(MouseButton::Left)],
+            Action::ClickZoomIn => vec![InputKind::DoubleClick(MouseButton::Left)],
+            Action::ClickZoomOut => vec![InputKind::Mouse(MouseButton::Right)],
+            Action::ResetView => vec![
+                InputKind::Key(KeyCode::Home),
+                InputKind::Gamepad(GamepadButtonType::Start),
+            ],
+            Action::HistoryBack => {
+                let mut inputs = vec![
+                    InputKind::Key(KeyCode::BracketLeft),
+                    InputKind::Key(KeyCode::WebBack),
+                    InputKind::Gamepad(GamepadButtonType::LeftTrigger),
+                ];
+                inputs.extend(SIDE_BUTTONS.iter().map(|(back, _)| InputKind::Mouse(*back)));
+                inputs
+            }
+            Action::HistoryForward => {
+                let mut inputs = vec![
+                    InputKind::Key(KeyCode::BracketRight),
+                    InputKind::Key(KeyCode::WebForward),
+                    InputKind::Gamepad(GamepadButtonType::RightTrigger),
+                ];
+                inputs.extend(
+                    SIDE_BUTTONS
+                        .iter()
+                        .map(|(_, forward)| InputKind::Mouse(*forward)),
+                );
+                inputs
+            }
+            Action::ShowOrbit => vec![InputKind::Key(KeyCode::O)],
+            Action::ToggleMinimap => vec![InputKind::Key(KeyCode::N)],
+            Action::ToggleHelp => vec![
+                InputKind::Key(KeyCode::F1),
+                InputKind::Gamepad(GamepadButtonType::Select),
+            ],
+        };
+        let modifiers = match self {
+            // Ctrl keeps the box drag apart from the plain drag that pans.
+            Action::BoxZoom => Modifiers {
+                ctrl: true,
+                ..default()
+            },
+            _ => Modifiers::default(),
+        };
+        inputs
+            .into_iter()
+            .map(|input| Binding { modifiers, input })
+            .collect()
+    }
+}
+
+// Modifier keys that must be held for a binding to trigger. Either side of the keyboard counts.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    // Modifiers held right now. Bindings only trigger when these match exactly. A modifier key that
+    // is bound to an action itself, like the zoom boost, counts as that action and not as a
+    // modifier, so holding it doesn't block the other bindings.
+    fn held(keys: &Input<KeyCode>, bindings: &Bindings) -> Self {
+        let held = |pair: [KeyCode; 2]| {
+            pair.into_iter()
+                .any(|key| keys.pressed(key) && !bindings.binds_key(key))
+        };
+        Modifiers {
+            shift: held([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+            ctrl: held([KeyCode::ControlLeft, KeyCode::ControlRight]),
+            alt: held([KeyCode::AltLeft, KeyCode::AltRight]),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Modifiers::default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum InputKind {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // The second press of a quick double click.
+    DoubleClick(MouseButton),
+    ScrollUp,
+    ScrollDown,
+    // A button on any connected gamepad.
+    Gamepad(GamepadButtonType),
+}
+
+fn is_modifier_key(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Binding {
+    #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+    pub modifiers: Modifiers,
+    pub input: InputKind,
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        match self.input {
+            InputKind::Key(key) => write!(f, "{key:?}"),
+            InputKind::Mouse(button) => write!(f, "Mouse {button:?}"),
+            InputKind::DoubleClick(button) => write!(f, "Double-click {button:?}"),
+            InputKind::ScrollUp => write!(f, "Scroll Up"),
+            InputKind::ScrollDown => write!(f, "Scroll Down"),
+            InputKind::Gamepad(button) => write!(f, "Gamepad {button:?}"),
+        }
+    }
+}
+
+// The action to binding registry. Every action is present, possibly with no bindings.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings(
+            Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_bindings()))
+                .collect(),
+        )
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    fn get_mut(&mut self, action: Action) -> &mut Vec<Binding> {
+        self.0.entry(action).or_default()
+    }
+
+    // Parse a bindings file. Actions the file doesn't mention keep their default bindings.
+    pub fn from_ron(text: &str) -> Result<Self, ron::error::SpannedError> {
+        let mut bindings: Bindings = ron::from_str(text)?;
+        for action in Action::ALL {
+            bindings
+                .0
+                .entry(action)
+                .or_insert_with(|| action.default_bindings());
+        }
+        Ok(bindings)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    // Only the actions whose bindings differ from the defaults, which is what gets saved.
+    pub fn overrides(&self) -> Bindings {
+        Bindings(
+            self.0
+                .iter()
+                .filter(|(action, bindings)| **bindings != action.default_bindings())
+                .map(|(action, bindings)| (*action, bindings.clone()))
+                .collect(),
+        )
+    }
+
+    // Whether a key is bound to an action on its own, as opposed to being held as a modifier.
+    fn binds_key(&self, key: KeyCode) -> bool {
+        self.0
+            .values()
+            .flatten()
+            .any(|binding| binding.input == InputKind::Key(key))
+    }
+
+    // Other actions that share `binding` with `action`.
+    pub fn conflicts(&self, action: Action, binding: &Binding) -> Vec<Action> {
+        self.0
+            .iter()
+            .filter(|(other, bindings)| **other != action && bindings.contains(binding))
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        self.0.iter().any(|(action, bindings)| {
+            bindings
+                .iter()
+                .any(|binding| !self.conflicts(*action, binding).is_empty())
+        })
+    }
+
+    // Comma separated bindings of an action for display.
+    pub fn describe(&self, action: Action) -> String {
+        let bindings = self.get(action);
+        if bindings.is_empty() {
+            return "Unbound".to_string();
+        }
+        bindings
+            .iter()
+            .map(Binding::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// The binding slot waiting for the next input, if any. Actions don't trigger while it is set.
+#[derive(Resource, Default)]
+pub struct BindingCapture {
+    target: Option<(Action, Option<usize>)>,
+    // A mouse press that becomes a plain click binding unless a second press follows in time.
+    pending_click: Option<(MouseButton, Modifiers, f64)>,
+}
+
+impl BindingCapture {
+    pub fn is_active(&self) -> bool {
+        self.target.is_some()
+    }
+}
+
+// Mouse buttons whose press this frame completed a double click.
+#[derive(Resource, Default)]
+pub struct DoubleClicks {
+    double_clicked: Vec<MouseButton>,
+    // Time and cursor position of the last press of each button.
+    last_press: HashMap<MouseButton, (f64, Option<Vec2>)>,
+}
+
+impl DoubleClicks {
+    pub fn just_double_clicked(&self, button: MouseButton) -> bool {
+        self.double_clicked.contains(&button)
+    }
+}
+
+// Whether an egui widget, like a text field, has keyboard focus. Actions don't trigger while it
+// does, so typing into the field doesn't also run whatever the keys are bound to.
+#[derive(Resource, Default)]
+pub struct EguiWantsKeyboard(bool);
+
+// Queries actions through the current bindings instead of fixed keys.
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    bindings: Res<'w, Bindings>,
+    capture: Res<'w, BindingCapture>,
+    egui_keyboard: Res<'w, EguiWantsKeyboard>,
+    keys: Res<'w, Input<KeyCode>>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    double_clicks: Res<'w, DoubleClicks>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+}
+
+impl ActionInput<'_> {
+    // Input is meant for the binding being captured or the focused egui widget, if any.
+    fn is_blocked(&self) -> bool {
+        self.capture.is_active() || self.egui_keyboard.0
+    }
+
+    fn triggered(&self, action: Action, just_pressed: bool) -> bool {
+        if self.is_blocked() {
+            return false;
+        }
+        let held = Modifiers::held(&self.keys, &self.bindings);
+        self.bindings.get(action).iter().any(|binding| {
+            binding.modifiers == held
+                && match (binding.input, just_pressed) {
+                    (InputKind::Key(key), false) => self.keys.pressed(key),
+                    (InputKind::Key(key), true) => self.keys.just_pressed(key),
+                    (InputKind::Mouse(button), false) => self.mouse_buttons.pressed(button),
+                    (InputKind::Mouse(button), true) => self.mouse_buttons.just_pressed(button),
+                    // A double click is momentary, held or not.
+                    (InputKind::DoubleClick(button), _) => {
+                        self.double_clicks.just_double_clicked(button)
+                    }
+                    (InputKind::Gamepad(button_type), false) => self
+                        .gamepad_buttons
+                        .get_pressed()
+                        .any(|button| button.button_type == button_type),
+                    (InputKind::Gamepad(button_type), true) => self
+                        .gamepad_buttons
+                        .get_just_pressed()
+                        .any(|button| button.button_type == button_type),
+                    (InputKind::ScrollUp | InputKind::ScrollDown, _) => false,
+                }
+        })
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.triggered(action, false)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.triggered(action, true)
+    }
+
+    // Turn a scroll amount (positive is up) into zoom-in amount (positive zooms in), following
+    // whichever zoom action the scroll direction is bound to. Zero if it's bound to neither.
+    pub fn zoom_scroll(&self, scroll: f32) -> f32 {
+        if self.is_blocked() || scroll == 0.0 {
+            return 0.0;
+        }
+        let input = if scroll > 0.0 {
+            InputKind::ScrollUp
+        } else {
+            InputKind::ScrollDown
+        };
+        let held = Modifiers::held(&self.keys, &self.bindings);
+        let bound = |action| {
+            self.bindings
+                .get(action)
+                .iter()
+                .any(|binding| binding.input == input && binding.modifiers == held)
+        };
+        if bound(Action::ZoomIn) {
+            scroll.abs()
+        } else if bound(Action::ZoomOut) {
+            -scroll.abs()
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct HelpOverlay {
+    pub visible: bool,
+}
+
+#[derive(Default)]
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_bindings())
+            .insert_resource(load_gamepad_controls())
+            .init_resource::<BindingCapture>()
+            .init_resource::<HelpOverlay>()
+            .init_resource::<DoubleClicks>()
+            .init_resource::<EguiWantsKeyboard>()
+            .add_systems(
+                PreUpdate,
+                (
+                    track_double_clicks_system.after(InputSystem),
+                    check_egui_wants_keyboard.after(EguiSet::BeginFrame),
+                ),
+            )
+            .add_systems(
+                Update,
+                (help_toggle_system, bindings_ui_system, help_overlay_system),
+            )
+            // After the action systems, so the captured input doesn't also trigger its old action.
+            .add_systems(
+                PostUpdate,
+                (
+                    capture_binding_system,
+                    save_bindings_system,
+                    save_gamepad_controls_system,
+                ),
+            );
+    }
+}
+
+// Where a settings file lives in the per-user config directory, if the platform has one.
+pub fn config_path(file: &str) -> Option<PathBuf> {
+    ProjectDirs::from("", "", "bevy_mandelbrot").map(|dirs| dirs.config_dir().join(file))
+}
+
+// Write a settings file into the config directory, creating the directory first.
+pub fn save_config(file: &str, text: &str) -> Result<(), String> {
+    let path = config_path(file).ok_or("no config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(&path, text).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+// Read a settings file from the config directory. None when it doesn't exist or can't be read.
+fn read_config(file: &str) -> Option<String> {
+    let path = config_path(file)?;
+    match std::fs::read_to_string(&path) {
+        Ok(text) => Some(text),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            error!("Could not read {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+fn load_bindings() -> Bindings {
+    let Some(text) = read_config(BINDINGS_FILE) else {
+        return Bindings::default();
+    };
+    Bindings::from_ron(&text).unwrap_or_else(|err| {
+        error!("Could not parse {BINDINGS_FILE}, using the default bindings: {err}");
+        Bindings::default()
+    })
+}
+
+fn load_gamepad_controls() -> GamepadControls {
+    let Some(text) = read_config(GAMEPAD_FILE) else {
+        return GamepadControls::default();
+    };
+    ron::from_str(&text).unwrap_or_else(|err| {
+        error!("Could not parse {GAMEPAD_FILE}, using the default gamepad tuning: {err}");
+        GamepadControls::default()
+    })
+}
+
+fn check_egui_wants_keyboard(
+    contexts: Query<&EguiContext>,
+    mut wants_keyboard: ResMut<EguiWantsKeyboard>,
+) {
+    let wants = contexts.iter().any(|ctx| ctx.get().wants_keyboard_input());
+    if wants_keyboard.0 != wants {
+        wants_keyboard.0 = wants;
+    }
+}
+
+// Record which mouse buttons were double clicked this frame.
+fn track_double_clicks_system(
+    mut double_clicks: ResMut<DoubleClicks>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    windows: Query<&Window>,
+    time: Res<Time>,
+) {
+    double_clicks.double_clicked.clear();
+    let now = time.elapsed_seconds_f64();
+    let cursor = windows.iter().find_map(Window::cursor_position);
+    for button in mouse_buttons.get_just_pressed() {
+        let previous = double_clicks.last_press.insert(*button, (now, cursor));
+        let double = previous.is_some_and(|(at, position)| {
+            now - at <= DOUBLE_CLICK_TIME
+                && match (position, cursor) {
+                    (Some(a), Some(b)) => a.distance(b) <= DOUBLE_CLICK_DISTANCE,
+                    _ => true,
+                }
+        });
+        if double {
+            double_clicks.double_clicked.push(*button);
+            // A third quick press starts a new double click rather than finishing another one.
+            double_clicks.last_press.remove(button);
+        }
+    }
+}
+
+// Write the edited bindings back whenever they change.
+fn save_bindings_system(bindings: Res<Bindings>) {
+    if !bindings.is_changed() || bindings.is_added() {
+        return;
+    }
+    let result = bindings
+        .overrides()
+        .to_ron()
+        .map_err(|err| err.to_string())
+        .and_then(|text| save_config(BINDINGS_FILE, &format!("{BINDINGS_HEADER}{text}\n")));
+    if let Err(err) = result {
+        error!("Could not save {BINDINGS_FILE}: {err}");
+    }
+}
+
+// Write the gamepad tuning back whenever it changes.
+fn save_gamepad_controls_system(controls: Res<GamepadControls>) {
+    if !controls.is_changed() || controls.is_added() {
+        return;
+    }
+    let result = ron::ser::to_string_pretty(&*controls, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|text| save_config(GAMEPAD_FILE, &text));
+    if let Err(err) = result {
+        error!("Could not save {GAMEPAD_FILE}: {err}");
+    }
+}
+
+// Assign the next key, mouse button, scroll direction or gamepad button to the slot being rebound.
+fn capture_binding_system(
+    mut capture: ResMut<BindingCapture>,
+    mut bindings: ResMut<Bindings>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    double_clicks: Res<DoubleClicks>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut scroll_events: EventReader<MouseWheel>,
+    time: Res<Time>,
+) {
+    let scrolled: f32 = scroll_events.read().map(|event| event.y).sum();
+    let Some((action, index)) = capture.target else {
+        capture.pending_click = None;
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        capture.target = None;
+        return;
+    }
+
+    let now = time.elapsed_seconds_f64();
+    let mut held = Modifiers::held(&keys, &bindings);
+    let input = if let Some(key) = keys.get_just_pressed().find(|key| !is_modifier_key(**key)) {
+        Some(InputKind::Key(*key))
+    } else if let Some(button) = mouse_buttons.get_just_pressed().next() {
+        if double_clicks.just_double_clicked(*button) {
+            Some(InputKind::DoubleClick(*button))
+        } else {
+            capture.pending_click = Some((*button, held, now));
+            None
+        }
+    } else if let Some(button) = gamepad_buttons.get_just_pressed().next() {
+        Some(InputKind::Gamepad(button.button_type))
+    } else if scrolled > 0.0 {
+        Some(InputKind::ScrollUp)
+    } else if scrolled < 0.0 {
+        Some(InputKind::ScrollDown)
+    } else {
+        // A modifier pressed and released on its own binds the modifier key itself.
+        keys.get_just_released()
+            .find(|key| is_modifier_key(**key))
+            .filter(|_| keys.get_pressed().all(|key| is_modifier_key(*key)))
+            .map(|key| InputKind::Key(*key))
+    };
+    // A press is only known to be a single click once the double click time has passed.
+    let input = input.or_else(|| match capture.pending_click {
+        Some((button, modifiers, at)) if now - at > DOUBLE_CLICK_TIME => {
+            held = modifiers;
+            Some(InputKind::Mouse(button))
+        }
+        _ => None,
+    });
+    let Some(input) = input else {
+        return;
+    };
+
+    let binding = Binding {
+        modifiers: held,
+        input,
+    };
+    let slots = bindings.get_mut(action);
+    match index {
+        Some(index) if index < slots.len() => slots[index] = binding,
+        _ => slots.push(binding),
+    }
+    capture.target = None;
+    capture.pending_click = None;
+}
+
+fn help_toggle_system(actions: ActionInput, mut overlay: ResMut<HelpOverlay>) {
+    if actions.just_pressed(Action::ToggleHelp) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+// Rebinding window: every action with its bindings, conflicts flagged in red.
+fn bindings_ui_system(
+    mut ctx: EguiContexts,
+    mut bindings: ResMut<Bindings>,
+    mut capture: ResMut<BindingCapture>,
+    mut gamepad_controls: ResMut<GamepadControls>,
+) {
+    let mut edit = None;
+    egui::Window::new("Key Bindings")
+        .default_open(false)
+        .show(ctx.ctx_mut(), |ui| {
+            egui::Grid::new("bindings").striped(true).show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.label());
+                    ui.horizontal(|ui| {
+                        for (index, binding) in bindings.get(action).iter().enumerate() {
+                            let conflicts = bindings.conflicts(action, binding);
+                            let text = if capture.target == Some((action, Some(index))) {
+                                egui::RichText::new("Press an input...").italics()
+                            } else if conflicts.is_empty() {
+                                egui::RichText::new(binding.to_string())
+                            } else {
+                                egui::RichText::new(binding.to_string()).color(egui::Color32::RED)
+                            };
+                            let mut response = ui.button(text);
+                            if !conflicts.is_empty() {
+                                let names: Vec<_> =
+                                    conflicts.iter().map(|other| other.label()).collect();
+                                response = response
+                                    .on_hover_text(format!("Also bound to: {}", names.join(", ")));
+                            }
+                            if response.clicked() {
+                                capture.target = Some((action, Some(index)));
+                            }
+                            if ui.small_button("x").clicked() {
+                                edit = Some((action, index));
+                            }
+                        }
+                        let adding = capture.target == Some((action, None));
+                        if ui
+                            .button(if adding { "Press an input..." } else { "+" })
+                            .clicked()
+                        {
+                            capture.target = Some((action, None));
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+            if bindings.has_conflicts() {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    "Inputs shown in red trigger more than one action.",
+                );
+            }
+            if capture.is_active() {
+                ui.label(
+                    "Press a key, mouse or gamepad button, double-click, or scroll. Escape cancels.",
+                );
+            }
+            if ui.button("Reset to Defaults").clicked() {
+                *bindings = Bindings::default();
+                capture.target = None;
+            }
+            ui.collapsing("Gamepad Sticks and Triggers", |ui| {
+                // Edit a copy so the tuning is only saved when a slider actually moves.
+                let mut controls = gamepad_controls.clone();
+                gamepad_controls_ui(ui, &mut controls);
+                gamepad_controls.set_if_neq(controls);
+            });
+        });
+    if let Some((action, index)) = edit {
+        bindings.get_mut(action).remove(index);
+        capture.target = None;
+    }
+}
+
+// Overlay listing every action and its current bindings.
+fn help_overlay_system(mut ctx: EguiContexts, overlay: Res<HelpOverlay>, bindings: Res<Bindings>) {
+    if !overlay.visible {
+        return;
+    }
+    egui::Window::new("Controls")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx.ctx_mut(), |ui| {
+            egui::Grid::new("controls").striped(true).show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.label());
+                    ui.label(bindings.describe(action));
+                    ui.end_row();
+                }
+            });
+            ui.label(format!(
+                "Press {} to close.",
+                bindings.describe(Action::ToggleHelp)
+            ));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(input: InputKind) -> Binding {
+        Binding {
+            modifiers: Modifiers::default(),
+            input,
+        }
+    }
+
+    #[test]
+    fn default_bindings_have_no_conflicts() {
+        assert!(!Bindings::default().has_conflicts());
+    }
+
+    #[test]
+    fn bindings_round_trip_through_ron() {
+        let mut bindings = Bindings::default();
+        bindings.get_mut(Action::CycleFractal).push(Binding {
+            modifiers: Modifiers {
+                ctrl: true,
+                ..default()
+            },
+            input: InputKind::Mouse(MouseButton::Right),
+        });
+        let text = bindings.to_ron().unwrap();
+        assert_eq!(Bindings::from_ron(&text).unwrap(), bindings);
+    }
+
+    #[test]
+    fn missing_actions_keep_their_defaults() {
+        let bindings = Bindings::from_ron("{ CycleFractal: [(input: Key(Tab))] }").unwrap();
+        assert_eq!(
+            bindings.get(Action::CycleFractal),
+            &[plain(InputKind::Key(KeyCode::Tab))]
+        );
+        assert_eq!(
+            bindings.get(Action::ToggleHelp),
+            Action::ToggleHelp.default_bindings().as_slice()
+        );
+    }
+
+    #[test]
+    fn shared_bindings_are_conflicts() {
+        let mut bindings = Bindings::default();
+        let space = plain(InputKind::Key(KeyCode::Space));
+        bindings.get_mut(Action::ToggleAnimation).push(space);
+        assert_eq!(
+            bindings.conflicts(Action::CycleFractal, &space),
+            vec![Action::ToggleAnimation]
+        );
+        assert!(bindings.has_conflicts());
+    }
+
+    #[test]
+    fn only_overrides_are_saved() {
+        let mut bindings = Bindings::default();
+        assert_eq!(bindings.overrides().to_ron().unwrap(), "{}");
+        bindings.get_mut(Action::ShowOrbit)[0] = plain(InputKind::Key(KeyCode::Tab));
+        let text = bindings.overrides().to_ron().unwrap();
+        assert!(!text.contains("CycleFractal"));
+        let loaded = Bindings::from_ron(&format!("{BINDINGS_HEADER}{text}")).unwrap();
+        assert_eq!(loaded, bindings);
+    }
+
+    #[test]
+    fn bound_modifier_keys_are_not_modifiers() {
+        let bindings = Bindings::default();
+        let mut keys = Input::<KeyCode>::default();
+        // Left shift is the zoom boost, so it doesn't turn Space into Shift+Space.
+        keys.press(KeyCode::ShiftLeft);
+        assert!(Modifiers::held(&keys, &bindings).is_empty());
+        keys.press(KeyCode::ShiftRight);
+        assert_eq!(
+            Modifiers::held(&keys, &bindings),
+            Modifiers {
+                shift: true,
+                ..default()
+            }
+        );
+    }
+
+    #[test]
+    fn focused_text_edit_blocks_actions() {
+        #[derive(Resource, Default)]
+        struct Triggered(bool);
+
+        let mut app = App::new();
+        app.init_resource::<Bindings>()
+            .init_resource::<BindingCapture>()
+            .init_resource::<DoubleClicks>()
+            .init_resource::<EguiWantsKeyboard>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Triggered>()
+            .add_systems(
+                Update,
+                (
+                    check_egui_wants_keyboard,
+                    |actions: ActionInput, mut triggered: ResMut<Triggered>| {
+                        triggered.0 = actions.just_pressed(Action::CycleFractal);
+                    },
+                )
+                    .chain(),
+            );
+        let entity = app.world.spawn(EguiContext::default()).id();
+        let ctx = app.world.get::<EguiContext>(entity).unwrap().get().clone();
+        let mut path = String::new();
+        let mut press_space_with_focus = |focus: bool| {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let response = ui.text_edit_singleline(&mut path);
+                    if focus {
+                        response.request_focus();
+                    } else {
+                        response.surrender_focus();
+                    }
+                });
+            });
+            let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+            keys.reset_all();
+            keys.press(KeyCode::Space);
+            app.update();
+            app.world.resource::<Triggered>().0
+        };
+        // Typing a space into the field doesn't also cycle the fractal.
+        assert!(!press_space_with_focus(true));
+        assert!(press_space_with_focus(false));
+    }
+}
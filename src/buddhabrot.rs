@@ -0,0 +1,873 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use bevy::math::DVec2;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::tasks::{block_on, AsyncComputeTaskPool, ComputeTaskPool, Task, TaskPool};
+use bevy::window::{PrimaryWindow, RequestRedraw};
+use futures_lite::future;
+
+use crate::export::{timestamped_export_path, EXPORT_DIRECTORY};
+use crate::fractals::{complex_to_world, world_to_complex, WORLD_UNITS_PER_COMPLEX_UNIT};
+use crate::materials::JuliaMaterial;
+use crate::pancam::PanCamSystemSet;
+use crate::progressive::JULIA_SETTLE_SECONDS;
+
+// Cells per side of the grid used to importance sample c values.
+const IMPORTANCE_GRID_SIZE: usize = 128;
+// Relative weight of grid cells whose orbits miss the view or the set boundary, so they still
+// get sampled.
+const IMPORTANCE_FLOOR: f64 = 0.02;
+// Iterations used to probe where the orbits of a grid cell go.
+const IMPORTANCE_PROBE_ITERATIONS: u32 = 256;
+// Half width of the square orbits are started from; every escaping orbit starts in it.
+const SAMPLE_RADIUS: f64 = 2.0;
+// Sample weights are stored as fixed point so the density can use integer atomics.
+const WEIGHT_SCALE: f64 = 16.0;
+// Orbits traced per batch while exporting.
+const EXPORT_BATCH: u32 = 1_000_000;
+
+// Which orbits are accumulated into the density.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DensityMode {
+    // Escaping Mandelbrot orbits.
+    Buddhabrot,
+    // Mandelbrot orbits that survive the iteration limit.
+    AntiBuddhabrot,
+    // Escaping orbits of the Julia set for the current Julia constant.
+    Julia,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToneMapping {
+    Log,
+    Sqrt,
+}
+
+// User-facing settings for the orbit density renderer.
+#[derive(Resource, Clone, PartialEq)]
+pub struct BuddhabrotSettings {
+    pub enabled: bool,
+    pub mode: DensityMode,
+    // Iteration limits for the red, green and blue channels. Escaping orbits add to the channels
+    // whose limit they escaped within, anti-Buddhabrot orbits to those whose limit they survived.
+    pub channel_iterations: [u32; 3],
+    // Use the per-channel limits (Nebulabrot) instead of the red limit for every channel.
+    pub nebulabrot: bool,
+    // Orbits traced per frame across all threads.
+    pub samples_per_frame: u32,
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
+    // Export resolution as a multiple of the window size.
+    pub export_scale: u32,
+    // Orbits traced for an export, in millions.
+    pub export_samples: u32,
+}
+
+impl Default for BuddhabrotSettings {
+    fn default() -> Self {
+        BuddhabrotSettings {
+            enabled: false,
+            mode: DensityMode::Buddhabrot,
+            channel_iterations: [5000, 500, 50],
+            nebulabrot: true,
+            samples_per_frame: 200_000,
+            tone_mapping: ToneMapping::Log,
+            exposure: 1.0,
+            export_scale: 4,
+            export_samples: 200,
+        }
+    }
+}
+
+impl BuddhabrotSettings {
+    // Escape limit applied to each channel.
+    pub fn limits(&self) -> [u32; 3] {
+        let limits = if self.nebulabrot {
+            self.channel_iterations
+        } else {
+            [self.channel_iterations[0]; 3]
+        };
+        limits.map(|limit| limit.max(1))
+    }
+
+    fn source(&self, julia_c: DVec2) -> OrbitSource {
+        OrbitSource {
+            mode: self.mode,
+            limits: self.limits(),
+            // Only Julia orbits depend on the constant; leaving it out of the other modes keeps
+            // their density when c moves.
+            julia_c: if self.mode == DensityMode::Julia {
+                julia_c
+            } else {
+                DVec2::ZERO
+            },
+        }
+    }
+}
+
+// Julia constant of the live Julia material, falling back to the material's default.
+fn julia_c(julia_materials: &Assets<JuliaMaterial>) -> DVec2 {
+    julia_materials
+        .iter()
+        .next()
+        .map_or(DVec2::new(0.3, 0.8), |(_, material)| material.c.as_dvec2())
+}
+
+// Maps the complex plane onto the pixels of a density buffer; pixel (0, 0) is centered on origin.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct DensityView {
+    origin: DVec2,
+    pixel_step: f64,
+    size: UVec2,
+}
+
+impl DensityView {
+    // View of the given camera at `size` pixels, each covering `pixel_step` complex units.
+    fn centered(center: DVec2, pixel_step: f64, size: UVec2) -> Self {
+        DensityView {
+            origin: center - (size.as_dvec2() / 2.0 - 0.5) * pixel_step,
+            pixel_step,
+            size,
+        }
+    }
+
+    fn center(&self) -> DVec2 {
+        self.origin + (self.size.as_dvec2() / 2.0 - 0.5) * self.pixel_step
+    }
+
+    fn pixel_index(&self, z: DVec2) -> Option<usize> {
+        let pixel = ((z - self.origin) / self.pixel_step).round();
+        if pixel.x < 0.0
+            || pixel.y < 0.0
+            || pixel.x >= self.size.x as f64
+            || pixel.y >= self.size.y as f64
+        {
+            return None;
+        }
+        Some(pixel.y as usize * self.size.x as usize + pixel.x as usize)
+    }
+}
+
+// Small xorshift generator; the samples only need to be well spread, not unpredictable.
+struct SampleRng(u64);
+
+impl SampleRng {
+    fn new(seed: u64) -> Self {
+        SampleRng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// Main cardioid and period-2 bulb test; those orbits never escape.
+fn in_main_bulbs(c: DVec2) -> bool {
+    let q = (c.x - 0.25) * (c.x - 0.25) + c.y * c.y;
+    q * (q + (c.x - 0.25)) < 0.25 * c.y * c.y || (c.x + 1.0) * (c.x + 1.0) + c.y * c.y < 0.0625
+}
+
+// The orbits a density buffer collects.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct OrbitSource {
+    mode: DensityMode,
+    limits: [u32; 3],
+    julia_c: DVec2,
+}
+
+impl OrbitSource {
+    fn max_iterations(&self) -> u32 {
+        self.limits.into_iter().max().unwrap_or(1)
+    }
+
+    // Starting z and c of the orbit for a point drawn from the sampling square.
+    fn start(&self, sample: DVec2) -> (DVec2, DVec2) {
+        match self.mode {
+            DensityMode::Julia => (sample, self.julia_c),
+            DensityMode::Buddhabrot | DensityMode::AntiBuddhabrot => (DVec2::ZERO, sample),
+        }
+    }
+
+    // Trace a sample for up to `max_iterations`, returning whether it escaped and how many of its
+    // points, mirrored ones included, land in the view.
+    fn probe(&self, sample: DVec2, max_iterations: u32, view: &DensityView) -> (bool, usize) {
+        let (mut z, c) = self.start(sample);
+        let mut visits = 0;
+        for _ in 0..max_iterations {
+            z = DVec2::new(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+            if z.length_squared() > 4.0 {
+                return (true, visits);
+            }
+            visits += std::iter::once(z)
+                .chain(self.mirror(z))
+                .filter(|point| view.pixel_index(*point).is_some())
+                .count();
+        }
+        (false, visits)
+    }
+
+    // The parts of the source the importance grid depends on.
+    fn grid_key(&self) -> OrbitSource {
+        OrbitSource {
+            limits: [self.max_iterations().min(IMPORTANCE_PROBE_ITERATIONS); 3],
+            ..*self
+        }
+    }
+
+    // Whether a sample can be skipped because its orbit can't contribute.
+    fn skip(&self, sample: DVec2) -> bool {
+        self.mode == DensityMode::Buddhabrot && in_main_bulbs(sample)
+    }
+
+    // Number of leading orbit points that go into a channel with the given limit.
+    fn channel_points(&self, escaped: bool, orbit_length: usize, limit: u32) -> usize {
+        let limit = limit as usize;
+        match self.mode {
+            DensityMode::AntiBuddhabrot if orbit_length >= limit => limit,
+            DensityMode::AntiBuddhabrot => 0,
+            _ if escaped && orbit_length < limit => orbit_length,
+            _ => 0,
+        }
+    }
+
+    // The other point every orbit point implies. Mandelbrot orbits are symmetric about the real
+    // axis. Julia orbits have no such partner: -z0 has the same orbit as z0 from z1 on, so their
+    // density is centred on c rather than symmetric under negation.
+    fn mirror(&self, z: DVec2) -> Option<DVec2> {
+        match self.mode {
+            DensityMode::Julia => None,
+            DensityMode::Buddhabrot | DensityMode::AntiBuddhabrot => Some(DVec2::new(z.x, -z.y)),
+        }
+    }
+
+    // Whether a grid cell whose probes escaped `escaped` out of `probes` times deserves samples.
+    fn important(&self, escaped: usize, probes: usize) -> bool {
+        match self.mode {
+            DensityMode::AntiBuddhabrot => escaped < probes,
+            DensityMode::Buddhabrot | DensityMode::Julia => escaped > 0 && escaped < probes,
+        }
+    }
+}
+
+// Distribution over a coarse grid of starting points that favours the cells whose orbits make
+// up most of the image: those straddling the set boundary, or inside it for the anti-Buddhabrot,
+// weighted by how much of their orbits lands in the view.
+struct ImportanceGrid {
+    source: OrbitSource,
+    view: DensityView,
+    cumulative: Vec<f64>,
+}
+
+impl ImportanceGrid {
+    fn new(pool: &TaskPool, source: &OrbitSource, view: &DensityView) -> Self {
+        let source = source.grid_key();
+        let probe = source.max_iterations();
+        let view = *view;
+        // Share of each cell's probe orbit points that land in the view, zero for cells that
+        // don't contribute.
+        let shares: Vec<f64> = pool
+            .scope(|scope| {
+                for row in 0..IMPORTANCE_GRID_SIZE {
+                    scope.spawn(async move {
+                        (row * IMPORTANCE_GRID_SIZE..(row + 1) * IMPORTANCE_GRID_SIZE)
+                            .map(|cell| Self::probe_cell(&source, &view, cell, probe))
+                            .collect::<Vec<_>>()
+                    });
+                }
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let max_share = shares.iter().copied().fold(0.0, f64::max);
+        let mut cumulative = Vec::with_capacity(shares.len());
+        let mut total = 0.0;
+        for share in shares {
+            total += IMPORTANCE_FLOOR;
+            if max_share > 0.0 {
+                total += share / max_share;
+            }
+            cumulative.push(total);
+        }
+        ImportanceGrid {
+            source,
+            view,
+            cumulative,
+        }
+    }
+
+    // Whether the grid was built for the same orbits and view.
+    fn matches(&self, source: &OrbitSource, view: &DensityView) -> bool {
+        self.source == source.grid_key() && self.view == *view
+    }
+
+    fn probe_cell(source: &OrbitSource, view: &DensityView, cell: usize, probe: u32) -> f64 {
+        let corner = Self::cell_origin(cell);
+        let size = Self::cell_size();
+        let probes = [
+            corner,
+            corner + DVec2::new(size, 0.0),
+            corner + DVec2::new(0.0, size),
+            corner + DVec2::splat(size),
+            corner + DVec2::splat(size / 2.0),
+        ];
+        let (escaped, visits) = probes.iter().fold((0, 0), |(escaped, visits), sample| {
+            let (escapes, sample_visits) = source.probe(*sample, probe, view);
+            (escaped + escapes as usize, visits + sample_visits)
+        });
+        if !source.important(escaped, probes.len()) {
+            return 0.0;
+        }
+        visits as f64 / (probes.len() as f64 * probe as f64 * 2.0)
+    }
+
+    fn cell_size() -> f64 {
+        2.0 * SAMPLE_RADIUS / IMPORTANCE_GRID_SIZE as f64
+    }
+
+    fn cell_origin(cell: usize) -> DVec2 {
+        let (x, y) = (cell % IMPORTANCE_GRID_SIZE, cell / IMPORTANCE_GRID_SIZE);
+        DVec2::new(x as f64, y as f64) * Self::cell_size() - SAMPLE_RADIUS
+    }
+
+    // Pick a cell for a uniform random number, along with the weight that undoes the bias.
+    fn pick(&self, u: f64) -> (usize, f64) {
+        let total = *self.cumulative.last().unwrap_or(&1.0);
+        let target = u * total;
+        let cell = self
+            .cumulative
+            .partition_point(|&sum| sum <= target)
+            .min(self.cumulative.len() - 1);
+        let previous = if cell == 0 {
+            0.0
+        } else {
+            self.cumulative[cell - 1]
+        };
+        let weight = total / (self.cumulative.len() as f64 * (self.cumulative[cell] - previous));
+        (cell, weight)
+    }
+}
+
+// Per-pixel orbit visit counts for three channels, shared between worker threads.
+struct DensityBuffer {
+    view: DensityView,
+    source: OrbitSource,
+    counts: Vec<AtomicU32>,
+    samples: u64,
+}
+
+impl DensityBuffer {
+    fn new(view: DensityView, source: OrbitSource) -> Self {
+        let len = view.size.x as usize * view.size.y as usize * 3;
+        DensityBuffer {
+            view,
+            source,
+            counts: (0..len).map(|_| AtomicU32::new(0)).collect(),
+            samples: 0,
+        }
+    }
+
+    // Start over for a new view, reusing the allocation when the size allows it.
+    fn reset(&mut self, view: DensityView, source: OrbitSource) {
+        if view.size != self.view.size {
+            *self = DensityBuffer::new(view, source);
+            return;
+        }
+        for count in &self.counts {
+            count.store(0, Ordering::Relaxed);
+        }
+        self.view = view;
+        self.source = source;
+        self.samples = 0;
+    }
+
+    // Trace `samples` orbits split over the threads of the pool.
+    fn trace(&mut self, pool: &TaskPool, grid: &ImportanceGrid, samples: u32, seed: u64) {
+        let threads = pool.thread_num().max(1);
+        let per_thread = samples / threads as u32 + 1;
+        let buffer = &*self;
+        pool.scope(|scope| {
+            for thread in 0..threads {
+                let seed = seed
+                    .wrapping_mul(threads as u64)
+                    .wrapping_add(thread as u64);
+                scope.spawn(async move { buffer.trace_batch(grid, per_thread, seed) });
+            }
+        });
+        self.samples += per_thread as u64 * threads as u64;
+    }
+
+    fn trace_batch(&self, grid: &ImportanceGrid, samples: u32, seed: u64) {
+        let mut rng = SampleRng::new(seed);
+        let source = self.source;
+        let max_iterations = source.max_iterations();
+        let mut orbit: Vec<DVec2> = Vec::with_capacity(max_iterations as usize);
+        for _ in 0..samples {
+            let (cell, weight) = grid.pick(rng.next_f64());
+            let sample = ImportanceGrid::cell_origin(cell)
+                + DVec2::new(rng.next_f64(), rng.next_f64()) * ImportanceGrid::cell_size();
+            if source.skip(sample) {
+                continue;
+            }
+
+            orbit.clear();
+            let (mut z, c) = source.start(sample);
+            let mut escaped = false;
+            for _ in 0..max_iterations {
+                z = DVec2::new(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+                if z.length_squared() > 4.0 {
+                    escaped = true;
+                    break;
+                }
+                orbit.push(z);
+            }
+            let channel_points = self
+                .source
+                .limits
+                .map(|limit| source.channel_points(escaped, orbit.len(), limit));
+            if channel_points.iter().all(|points| *points == 0) {
+                continue;
+            }
+
+            let contribution = (weight * WEIGHT_SCALE).round().max(1.0) as u32;
+            // Mandelbrot orbits count twice, once for their mirror image.
+            for (index, z) in orbit.iter().enumerate() {
+                for point in std::iter::once(*z).chain(source.mirror(*z)) {
+                    let Some(pixel) = self.view.pixel_index(point) else {
+                        continue;
+                    };
+                    for (channel, points) in channel_points.iter().enumerate() {
+                        if index < *points {
+                            self.add(pixel * 3 + channel, contribution);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Add to a count, saturating instead of wrapping around so long exports stay bright.
+    fn add(&self, index: usize, contribution: u32) {
+        let _ = self.counts[index].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            (count < u32::MAX).then(|| count.saturating_add(contribution))
+        });
+    }
+
+    // Tone map the counts into RGBA8 pixels, normalizing each channel by its brightest pixel.
+    fn tone_map(&self, pool: &TaskPool, tone_mapping: ToneMapping, exposure: f32, data: &mut [u8]) {
+        let mut max = [0u32; 3];
+        for (index, count) in self.counts.iter().enumerate() {
+            max[index % 3] = max[index % 3].max(count.load(Ordering::Relaxed));
+        }
+        let map = move |value: f32| match tone_mapping {
+            ToneMapping::Log => value.ln_1p(),
+            ToneMapping::Sqrt => value.sqrt(),
+        };
+        let scale = max.map(|max| exposure / map(max.max(1) as f32));
+
+        let pixels = self.view.size.x as usize * self.view.size.y as usize;
+        let pixels_per_chunk = (pixels / pool.thread_num().max(1)).max(1);
+        pool.scope(|scope| {
+            for (chunk_index, chunk) in data[..pixels * 4]
+                .chunks_mut(pixels_per_chunk * 4)
+                .enumerate()
+            {
+                scope.spawn(async move {
+                    let first = chunk_index * pixels_per_chunk;
+                    for (offset, pixel) in chunk.chunks_exact_mut(4).enumerate() {
+                        for channel in 0..3 {
+                            let count =
+                                self.counts[(first + offset) * 3 + channel].load(Ordering::Relaxed);
+                            let value = map(count as f32) * scale[channel];
+                            pixel[channel] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                        }
+                        pixel[3] = 255;
+                    }
+                });
+            }
+        });
+    }
+}
+
+// Marker for the sprite showing the density image in front of the camera.
+#[derive(Component)]
+pub struct BuddhabrotDisplay;
+
+// A batch of orbits traced and tone mapped on a background thread. The density and grid move
+// into the task and come back with the pixels.
+struct TraceBatch {
+    density: DensityBuffer,
+    grid: ImportanceGrid,
+    pixels: Vec<u8>,
+}
+
+// Live render state of the density renderer.
+#[derive(Resource, Default)]
+pub struct BuddhabrotState {
+    // Density and grid between batches; both are inside `tracing` while a batch runs.
+    density: Option<DensityBuffer>,
+    grid: Option<ImportanceGrid>,
+    tracing: Option<Task<TraceBatch>>,
+    // View and sample count of the image on display.
+    shown: Option<(DensityView, u64)>,
+    image: Handle<Image>,
+    frame: u64,
+    export: Option<Task<Result<PathBuf, String>>>,
+    export_requested: bool,
+}
+
+impl BuddhabrotState {
+    // Orbits traced into the displayed view so far.
+    pub fn samples(&self) -> u64 {
+        self.shown.map_or(0, |(_, samples)| samples)
+    }
+
+    pub fn is_exporting(&self) -> bool {
+        self.export.is_some() || self.export_requested
+    }
+
+    // Render the current view at export resolution in the background and save it as a PNG.
+    pub fn request_export(&mut self) {
+        self.export_requested = true;
+    }
+}
+
+#[derive(Default)]
+pub struct BuddhabrotPlugin;
+
+impl Plugin for BuddhabrotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuddhabrotSettings>()
+            .init_resource::<BuddhabrotState>()
+            .add_systems(Startup, setup_buddhabrot_display)
+            .add_systems(
+                Update,
+                (buddhabrot_system, buddhabrot_export_system).after(PanCamSystemSet),
+            );
+    }
+}
+
+fn setup_buddhabrot_display(mut commands: Commands) {
+    commands.spawn((
+        SpriteBundle {
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        BuddhabrotDisplay,
+    ));
+}
+
+// System to refine the density of the current view in the background and show the latest
+// result over the escape-time renderers.
+#[allow(clippy::too_many_arguments)]
+fn buddhabrot_system(
+    settings: Res<BuddhabrotSettings>,
+    mut state: ResMut<BuddhabrotState>,
+    mut images: ResMut<Assets<Image>>,
+    julia_materials: Res<Assets<JuliaMaterial>>,
+    time: Res<Time>,
+    // The Julia constant last seen and when it last changed.
+    mut julia_motion: Local<(DVec2, f32)>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    mut display_query: Query<
+        (
+            &mut Transform,
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut Visibility,
+        ),
+        (With<BuddhabrotDisplay>, Without<Camera2d>),
+    >,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let Ok((mut display_transform, mut sprite, mut texture, mut visibility)) =
+        display_query.get_single_mut()
+    else {
+        return;
+    };
+    let state = &mut *state;
+
+    // Pick up the batch that finished since the last frame.
+    if let Some(task) = state.tracing.as_mut() {
+        if let Some(batch) = block_on(future::poll_once(task)) {
+            state.tracing = None;
+            let view = batch.density.view;
+            state.shown = Some((view, batch.density.samples));
+            show_pixels(&mut images, &mut state.image, view.size, batch.pixels);
+            state.density = Some(batch.density);
+            state.grid = Some(batch.grid);
+        }
+    }
+
+    if !settings.enabled {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    let (Ok(window), Ok((camera_transform, projection))) =
+        (primary_window.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    // One density pixel per logical pixel keeps the buffer small on high-DPI screens.
+    let size = UVec2::new(window.width() as u32, window.height() as u32).max(UVec2::ONE);
+    let center = world_to_complex(camera_transform.translation.truncate()).as_dvec2();
+    let pixel_step = (projection.scale / WORLD_UNITS_PER_COMPLEX_UNIT) as f64;
+    let view = DensityView::centered(center, pixel_step, size);
+    let source = settings.source(julia_c(&julia_materials));
+
+    // Julia orbits start over for every constant, so while c moves, e.g. along its path, keep the
+    // last image instead of rebuilding the grid and restarting each batch.
+    let now = time.elapsed_seconds();
+    if source.julia_c != julia_motion.0 {
+        *julia_motion = (source.julia_c, now);
+    }
+    let julia_settling = source.mode == DensityMode::Julia
+        && state.shown.is_some()
+        && now - julia_motion.1 < JULIA_SETTLE_SECONDS;
+
+    if state.tracing.is_none() && !julia_settling {
+        let density = state.density.take();
+        let grid = state.grid.take();
+        let (samples, seed) = (settings.samples_per_frame, state.frame);
+        let (tone_mapping, exposure) = (settings.tone_mapping, settings.exposure);
+        state.tracing = Some(AsyncComputeTaskPool::get().spawn(async move {
+            let pool = ComputeTaskPool::get();
+            // The grid only follows the fields it depends on, so a moving Julia constant doesn't
+            // rebuild it outside Julia mode.
+            let grid = match grid {
+                Some(grid) if grid.matches(&source, &view) => grid,
+                _ => ImportanceGrid::new(pool, &source, &view),
+            };
+            let mut density = match density {
+                Some(density) if density.view == view && density.source == source => density,
+                Some(mut density) => {
+                    density.reset(view, source);
+                    density
+                }
+                None => DensityBuffer::new(view, source),
+            };
+            density.trace(pool, &grid, samples, seed);
+            let mut pixels = vec![0u8; view.size.x as usize * view.size.y as usize * 4];
+            density.tone_map(pool, tone_mapping, exposure, &mut pixels);
+            TraceBatch {
+                density,
+                grid,
+                pixels,
+            }
+        }));
+        state.frame = state.frame.wrapping_add(1);
+    }
+
+    // The image keeps refining, so keep frames coming in idle mode too.
+    redraw.send(RequestRedraw);
+    let Some((view, _)) = state.shown else {
+        return;
+    };
+    // Cover the view the image was traced for, in front of the other renderers.
+    let world_step = (view.pixel_step * WORLD_UNITS_PER_COMPLEX_UNIT as f64) as f32;
+    display_transform.translation = complex_to_world(view.center().as_vec2()).extend(2.0);
+    display_transform.scale = Vec3::new(world_step, world_step, 1.0);
+    sprite.custom_size = Some(view.size.as_vec2());
+    if *texture != state.image {
+        *texture = state.image.clone();
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+}
+
+// Copy tone mapped pixels into the display image, replacing it when the size changed.
+fn show_pixels(
+    images: &mut Assets<Image>,
+    image: &mut Handle<Image>,
+    size: UVec2,
+    pixels: Vec<u8>,
+) {
+    match images.get_mut(&*image) {
+        Some(shown) if shown.size() == size => shown.data = pixels,
+        _ => {
+            *image = images.add(Image::new(
+                Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                pixels,
+                TextureFormat::Rgba8UnormSrgb,
+            ));
+        }
+    }
+}
+
+// Start requested exports on a background thread and report them when they finish.
+fn buddhabrot_export_system(
+    settings: Res<BuddhabrotSettings>,
+    mut state: ResMut<BuddhabrotState>,
+    julia_materials: Res<Assets<JuliaMaterial>>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    if let Some(task) = state.export.as_mut() {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return;
+        };
+        match result {
+            Ok(path) => info!("Exported {}", path.display()),
+            Err(err) => error!("Could not export orbit density: {err}"),
+        }
+        state.export = None;
+    }
+    if !state.export_requested {
+        return;
+    }
+    state.export_requested = false;
+    let (Ok(window), Ok((camera_transform, projection))) =
+        (primary_window.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    let scale = settings.export_scale.max(1);
+    let size = UVec2::new(window.width() as u32, window.height() as u32).max(UVec2::ONE) * scale;
+    let center = world_to_complex(camera_transform.translation.truncate()).as_dvec2();
+    let pixel_step = (projection.scale / WORLD_UNITS_PER_COMPLEX_UNIT) as f64 / scale as f64;
+    let view = DensityView::centered(center, pixel_step, size);
+    let source = settings.source(julia_c(&julia_materials));
+    let settings = settings.clone();
+    let name = match source.mode {
+        DensityMode::Buddhabrot => "buddhabrot",
+        DensityMode::AntiBuddhabrot => "anti-buddhabrot",
+        DensityMode::Julia => "julia-orbits",
+    };
+    let path = timestamped_export_path(name);
+    state.export = Some(
+        AsyncComputeTaskPool::get()
+            .spawn(async move { render_export(view, source, &settings, path) }),
+    );
+}
+
+fn render_export(
+    view: DensityView,
+    source: OrbitSource,
+    settings: &BuddhabrotSettings,
+    path: PathBuf,
+) -> Result<PathBuf, String> {
+    let pool = ComputeTaskPool::get();
+    let grid = ImportanceGrid::new(pool, &source, &view);
+    let mut density = DensityBuffer::new(view, source);
+    let total = settings.export_samples.max(1) as u64 * 1_000_000;
+    let mut batch = 0;
+    while density.samples < total {
+        density.trace(pool, &grid, EXPORT_BATCH, batch);
+        batch += 1;
+    }
+
+    let mut data = vec![0u8; view.size.x as usize * view.size.y as usize * 4];
+    density.tone_map(pool, settings.tone_mapping, settings.exposure, &mut data);
+    std::fs::create_dir_all(EXPORT_DIRECTORY).map_err(|err| err.to_string())?;
+    image::RgbaImage::from_raw(view.size.x, view.size.y, data)
+        .ok_or_else(|| "image buffer has the wrong size".to_string())?
+        .save(&path)
+        .map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> &'static TaskPool {
+        ComputeTaskPool::get_or_init(TaskPool::default)
+    }
+
+    #[test]
+    fn counts_saturate() {
+        let source = BuddhabrotSettings::default().source(DVec2::ZERO);
+        let density =
+            DensityBuffer::new(DensityView::centered(DVec2::ZERO, 0.1, UVec2::ONE), source);
+        density.counts[0].store(u32::MAX - 5, Ordering::Relaxed);
+        density.add(0, 16);
+        density.add(0, 16);
+        assert_eq!(density.counts[0].load(Ordering::Relaxed), u32::MAX);
+    }
+
+    #[test]
+    fn moving_julia_constant_only_changes_julia_sources() {
+        let mut settings = BuddhabrotSettings::default();
+        let a = settings.source(DVec2::new(0.3, 0.8));
+        let b = settings.source(DVec2::new(-0.4, 0.6));
+        assert_eq!(a, b);
+
+        settings.mode = DensityMode::Julia;
+        let a = settings.source(DVec2::new(0.3, 0.8));
+        let b = settings.source(DVec2::new(-0.4, 0.6));
+        assert_ne!(a, b);
+        assert_eq!(a.grid_key().limits, [IMPORTANCE_PROBE_ITERATIONS; 3]);
+    }
+
+    #[test]
+    fn grid_favours_cells_whose_orbits_cross_the_view() {
+        let source = BuddhabrotSettings::default().source(DVec2::ZERO);
+        let whole = DensityView::centered(DVec2::ZERO, 0.01, UVec2::splat(400));
+        let corner = DensityView::centered(DVec2::new(-0.75, 0.1), 0.0005, UVec2::splat(100));
+        let whole_grid = ImportanceGrid::new(pool(), &source, &whole);
+        let corner_grid = ImportanceGrid::new(pool(), &source, &corner);
+        assert!(whole_grid.matches(&source, &whole));
+        assert!(!whole_grid.matches(&source, &corner));
+
+        // Fraction of the weight on cells that are more than floor.
+        let boosted = |grid: &ImportanceGrid| {
+            let mut previous = 0.0;
+            let cells = grid
+                .cumulative
+                .iter()
+                .filter(|sum| {
+                    let weight = **sum - previous;
+                    previous = **sum;
+                    weight > IMPORTANCE_FLOOR * 1.5
+                })
+                .count();
+            cells as f64 / grid.cumulative.len() as f64
+        };
+        // Only some of the boundary orbits pass through a small view.
+        assert!(boosted(&corner_grid) > 0.0);
+        assert!(boosted(&corner_grid) < boosted(&whole_grid));
+    }
+
+    #[test]
+    fn julia_density_is_not_point_symmetric() {
+        let source = BuddhabrotSettings {
+            mode: DensityMode::Julia,
+            ..default()
+        }
+        .source(DVec2::new(0.3, 0.5));
+        let view = DensityView::centered(DVec2::ZERO, 0.0625, UVec2::splat(64));
+        let grid = ImportanceGrid::new(pool(), &source, &view);
+        let mut density = DensityBuffer::new(view, source);
+        density.trace(pool(), &grid, 200_000, 1);
+
+        let count = |x: usize, y: usize| {
+            (0..3)
+                .map(|channel| density.counts[(y * 64 + x) * 3 + channel].load(Ordering::Relaxed))
+                .sum::<u32>() as f64
+        };
+        let (mut total, mut difference) = (0.0, 0.0);
+        for y in 0..64 {
+            for x in 0..64 {
+                total += count(x, y);
+                difference += (count(x, y) - count(63 - x, 63 - y)).abs();
+            }
+        }
+        // The orbits pile up around c, not equally around c and -c.
+        assert!(total > 0.0);
+        assert!(difference / total > 0.2, "asymmetry {}", difference / total);
+    }
+}
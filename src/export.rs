@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use futures_lite::future;
+
+use crate::fractals::{world_to_complex, WORLD_UNITS_PER_COMPLEX_UNIT};
+use crate::pancam::PanCamSystemSet;
+use crate::progressive::{CapturedFrames, PinnedFrame, ProgressiveSettings, ProgressiveView};
+
+// Directory exported images are written to, relative to the working directory.
+pub const EXPORT_DIRECTORY: &str = "renders";
+
+// Path for a new export, named after the current time. Exports started within the same second,
+// which may not be on disk yet, and names already taken get a counter so nothing is overwritten.
+pub fn timestamped_export_path(name: &str) -> PathBuf {
+    // Second and counter of the last path handed out.
+    static LAST: Mutex<(u64, u32)> = Mutex::new((0, 0));
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let mut last = LAST.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut count = if last.0 == timestamp { last.1 + 1 } else { 0 };
+    loop {
+        let stem = match count {
+            0 => format!("{name}-{timestamp}"),
+            _ => format!("{name}-{timestamp}-{count}"),
+        };
+        // Sequences are written to a directory named like the image would be.
+        let directory = PathBuf::from(EXPORT_DIRECTORY).join(stem);
+        let path = directory.with_extension("png");
+        if !path.exists() && !directory.exists() {
+            *last = (timestamp, count);
+            return path;
+        }
+        count += 1;
+    }
+}
+
+// User-facing settings for offline exports.
+#[derive(Resource, Clone, PartialEq)]
+pub struct ExportSettings {
+    pub samples_per_pixel: u32,
+    // Frames an export waits for every pixel to resolve before it saves the image as it is.
+    pub max_frames: u32,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            samples_per_pixel: 64,
+            max_frames: 1200,
+        }
+    }
+}
+
+enum ExportStage {
+    Starting,
+    // Frames rendered at the export sample count so far.
+    Rendering(u32),
+    // Waiting for the copy of the output requested under this id.
+    Capturing(u32),
+    Saving(Task<Result<(), String>>),
+}
+
+struct ExportJob {
+    // Images still to write, in order: the frame to pin the renderer to, or None for the live
+    // view, and the file to write it to.
+    images: VecDeque<(Option<PinnedFrame>, PathBuf)>,
+    // Images in the whole export.
+    total: usize,
+    samples_per_pixel: u32,
+    max_frames: u32,
+    stage: ExportStage,
+    // Progressive settings to put back once the last image has been written.
+    previous: Option<ProgressiveSettings>,
+}
+
+// Tracks the export currently in flight, if any.
+#[derive(Resource, Default)]
+pub struct ExportState {
+    job: Option<ExportJob>,
+}
+
+impl ExportState {
+    pub fn is_exporting(&self) -> bool {
+        self.job.is_some()
+    }
+
+    // Images written and images in all, while an export runs.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        let job = self.job.as_ref()?;
+        Some((job.total - job.images.len(), job.total))
+    }
+
+    // Queue an export of the current view with a fixed number of samples per pixel.
+    pub fn request(&mut self, settings: &ExportSettings) {
+        self.start(
+            VecDeque::from([(None, timestamped_export_path("fractal"))]),
+            settings,
+        );
+    }
+
+    // Queue an image sequence, rendered one pinned frame at a time like a single export, as
+    // numbered PNGs in a new directory under renders.
+    pub fn request_sequence(
+        &mut self,
+        name: &str,
+        frames: Vec<PinnedFrame>,
+        settings: &ExportSettings,
+    ) {
+        if self.job.is_some() || frames.is_empty() {
+            return;
+        }
+        let directory = timestamped_export_path(name).with_extension("");
+        if let Err(err) = std::fs::create_dir_all(&directory) {
+            error!("Could not create {}: {err}", directory.display());
+            return;
+        }
+        let images = frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, frame)| (Some(frame), directory.join(format!("frame-{index:05}.png"))))
+            .collect();
+        self.start(images, settings);
+    }
+
+    fn start(
+        &mut self,
+        images: VecDeque<(Option<PinnedFrame>, PathBuf)>,
+        settings: &ExportSettings,
+    ) {
+        if self.job.is_some() {
+            return;
+        }
+        self.job = Some(ExportJob {
+            total: images.len(),
+            images,
+            samples_per_pixel: settings.samples_per_pixel.max(1),
+            max_frames: settings.max_frames.max(1),
+            stage: ExportStage::Starting,
+            previous: None,
+        });
+    }
+}
+
+// The part of the plane a sequence frame covers, at the window's physical resolution and
+// without the camera's rotation.
+#[derive(Clone, Copy, Debug)]
+pub struct SequenceView {
+    pub center: Vec2,
+    // Complex distance between neighbouring physical pixels.
+    pub pixel_step: f32,
+}
+
+impl SequenceView {
+    pub fn of_camera(
+        camera: &Camera,
+        proj: &OrthographicProjection,
+        transform: &Transform,
+    ) -> Option<Self> {
+        let size = camera.physical_viewport_size()?;
+        Some(SequenceView {
+            center: world_to_complex(transform.translation.truncate()),
+            pixel_step: proj.area.height() / size.y.max(1) as f32 / WORLD_UNITS_PER_COMPLEX_UNIT,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExportSettings>()
+            .init_resource::<ExportState>()
+            .add_systems(Update, export_system.after(PanCamSystemSet));
+    }
+}
+
+// Drive an export: pin the progressive renderer to the requested sample count, wait for it to
+// converge, then save a copy of its output. The jitter pattern only depends on the sample index,
+// so the same view always produces the same image, and nothing drawn on top of the fractal, like
+// egui windows, can end up in it. Sequences go through the same steps once per frame, with the
+// renderer pinned to each frame in turn, so they match what the renderer shows on screen.
+fn export_system(
+    mut state: ResMut<ExportState>,
+    mut settings: ResMut<ProgressiveSettings>,
+    mut view: ResMut<ProgressiveView>,
+    captured: Res<CapturedFrames>,
+) {
+    let Some(job) = state.job.as_mut() else {
+        return;
+    };
+    match &mut job.stage {
+        ExportStage::Starting => {
+            job.previous = Some(settings.clone());
+            settings.enabled = true;
+            settings.temporal_accumulation = true;
+            settings.samples_per_pixel = job.samples_per_pixel;
+            settings.freeze_parameters = true;
+            settings.pinned = job.images.front().and_then(|(frame, _)| *frame);
+            job.stage = ExportStage::Rendering(0);
+        }
+        ExportStage::Rendering(frames) => {
+            if !view.enabled
+                || view.params.sample_cap != job.samples_per_pixel
+                || view.pinned != settings.pinned
+            {
+                return;
+            }
+            *frames += 1;
+            if !view.is_finished() {
+                if *frames < job.max_frames {
+                    return;
+                }
+                if let Some((_, path)) = job.images.front() {
+                    warn!(
+                        "Exporting {} before every pixel resolved; raise the export frame limit for a finished image",
+                        path.display()
+                    );
+                }
+            }
+            job.stage = ExportStage::Capturing(view.request_capture());
+        }
+        ExportStage::Capturing(id) => {
+            let Some(frame) = captured.take(*id) else {
+                return;
+            };
+            let Some((_, path)) = job.images.front().cloned() else {
+                return;
+            };
+            job.stage = ExportStage::Saving(AsyncComputeTaskPool::get().spawn(async move {
+                std::fs::create_dir_all(EXPORT_DIRECTORY)
+                    .map_err(|err| format!("could not create {EXPORT_DIRECTORY}: {err}"))?;
+                let pixels = frame.to_rgba8();
+                image::save_buffer(
+                    &path,
+                    &pixels,
+                    frame.size.x,
+                    frame.size.y,
+                    image::ColorType::Rgba8,
+                )
+                .map_err(|err| format!("could not write {}: {err}", path.display()))
+            }));
+        }
+        ExportStage::Saving(task) => {
+            let Some(result) = block_on(future::poll_once(task)) else {
+                return;
+            };
+            let Some((_, path)) = job.images.pop_front() else {
+                return;
+            };
+            match result {
+                Ok(()) if job.total == 1 => info!("Exported {}", path.display()),
+                Ok(()) => {}
+                Err(err) => error!("Could not export: {err}"),
+            }
+            // On to the next frame of a sequence.
+            if let Some((frame, _)) = job.images.front() {
+                settings.pinned = *frame;
+                job.stage = ExportStage::Rendering(0);
+                return;
+            }
+            if job.total > 1 {
+                if let Some(directory) = path.parent() {
+                    info!("Exported {} frames to {}", job.total, directory.display());
+                }
+            }
+            if let Some(previous) = job.previous.take() {
+                *settings = previous;
+            }
+            state.job = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_started_together_get_their_own_paths() {
+        let paths: Vec<PathBuf> = (0..3).map(|_| timestamped_export_path("fractal")).collect();
+        assert_ne!(paths[0], paths[1]);
+        assert_ne!(paths[1], paths[2]);
+        assert_ne!(paths[0], paths[2]);
+        assert!(paths
+            .iter()
+            .all(|path| path.extension().is_some_and(|ext| ext == "png")));
+    }
+}
@@ -1,8 +1,14 @@
 use bevy::prelude::*;
 use bevy::sprite::MaterialMesh2dBundle;
 use bevy::sprite::Mesh2dHandle;
+use bevy::window::RequestRedraw;
 use bevy_asset::AssetServer;
+use serde::{Deserialize, Serialize};
 
+use crate::audio::MusicUpdateToggle;
+use crate::bindings::{Action, ActionInput};
+use crate::gamepad::GamepadAxes;
+use crate::kernels::Kernel;
 use crate::materials::{
     prepare_burning_ship_material, BurningShipEntity, BurningShipMaterial, BurningShipUniforms,
 };
@@ -11,15 +17,63 @@ use crate::materials::{
     prepare_mandelbrot_material, MandelbrotEntity, MandelbrotMaterial, MandelbrotUniforms,
 };
 
+use crate::progressive::{ProgressiveSettings, PALETTES};
 use crate::PanCamState;
 
-#[derive(Resource)]
-enum FractalType {
+// Default distance below which an orbit is considered to be cycling.
+const DEFAULT_PERIODICITY_TOLERANCE: f32 = 1e-5;
+
+// Side length of the quad the fractal shaders are drawn on, in world units.
+pub const FRACTAL_QUAD_SIZE: f32 = 10000.0;
+// Vertical offset of the fractal quad in world space.
+pub const FRACTAL_QUAD_Y: f32 = 0.5;
+// The shaders map uv * 4.0 - 2.0 across the quad, so this many world units span one complex unit.
+pub const WORLD_UNITS_PER_COMPLEX_UNIT: f32 = FRACTAL_QUAD_SIZE / 4.0;
+
+// Convert a world position on the fractal quad to the complex plane coordinate the shaders use.
+pub fn world_to_complex(world: Vec2) -> Vec2 {
+    Vec2::new(world.x, -(world.y - FRACTAL_QUAD_Y)) / WORLD_UNITS_PER_COMPLEX_UNIT
+}
+
+// The world position of a complex plane coordinate, undoing `world_to_complex`.
+pub fn complex_to_world(complex: Vec2) -> Vec2 {
+    let world = complex * WORLD_UNITS_PER_COMPLEX_UNIT;
+    Vec2::new(world.x, FRACTAL_QUAD_Y - world.y)
+}
+
+// Marker for the mesh entity currently drawing the fractal material.
+#[derive(Component)]
+pub struct FractalQuad;
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub(crate) enum FractalType {
     Mandelbrot,
     Julia,
     BurningShip,
 }
 
+impl FractalType {
+    // Index of the fractal as used by the compute shaders.
+    pub fn shader_index(&self) -> u32 {
+        match self {
+            FractalType::Mandelbrot => 0,
+            FractalType::Julia => 1,
+            FractalType::BurningShip => 2,
+        }
+    }
+
+    // The CPU port of the fractal's fragment shader.
+    pub fn kernel(&self, julia_c: Vec2) -> Kernel {
+        match self {
+            FractalType::Mandelbrot => Kernel::Mandelbrot,
+            FractalType::Julia => Kernel::Julia {
+                c: julia_c.to_array(),
+            },
+            FractalType::BurningShip => Kernel::BurningShip,
+        }
+    }
+}
+
 impl Default for FractalType {
     fn default() -> Self {
         FractalType::Mandelbrot
@@ -27,8 +81,8 @@ impl Default for FractalType {
 }
 
 #[derive(Resource)]
-struct AnimationUpdateToggle {
-    active: bool,
+pub(crate) struct AnimationUpdateToggle {
+    pub active: bool,
 }
 
 impl Default for AnimationUpdateToggle {
@@ -37,15 +91,6 @@ impl Default for AnimationUpdateToggle {
     }
 }
 
-#[derive(Resource)]
-pub struct AnimationSpeed(pub f32);
-
-impl Default for AnimationSpeed {
-    fn default() -> Self {
-        AnimationSpeed(0.001)
-    }
-}
-
 #[derive(Default)]
 pub struct FractalControlPlugin;
 
@@ -53,55 +98,75 @@ impl Plugin for FractalControlPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<FractalType>();
         app.init_resource::<AnimationUpdateToggle>();
-        app.init_resource::<AnimationSpeed>();
         app.add_systems(FixedUpdate, uniform_update_system); // Update system for Mandelbrot material.
         app.add_systems(Update, fractal_toggle_system); // Update system for Mandelbrot material.
+        app.add_systems(Update, gamepad_julia_system);
         app.add_systems(Update, fractal_update_system);
     }
 }
 
 fn fractal_toggle_system(
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: ActionInput,
     mut fractal_type: ResMut<FractalType>,
     mut animation_toggle: ResMut<AnimationUpdateToggle>,
-    //mut music_toggle: ResMut<MusicUpdateToggle>,
+    mut progressive_settings: ResMut<ProgressiveSettings>,
+    mut music_toggle: ResMut<MusicUpdateToggle>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
+    if actions.just_pressed(Action::CycleFractal) {
         *fractal_type = match *fractal_type {
             FractalType::Mandelbrot => FractalType::Julia,
             FractalType::Julia => FractalType::BurningShip,
             FractalType::BurningShip => FractalType::Mandelbrot,
         };
     }
-    if keyboard_input.just_pressed(KeyCode::A) {
+    if actions.just_pressed(Action::ToggleAnimation) {
         animation_toggle.active = !animation_toggle.active;
     }
-    //if keyboard_input.just_pressed(KeyCode::M) {
-    //    music_toggle.active = !music_toggle.active;
-    //}
+    if actions.just_pressed(Action::CyclePalette) {
+        progressive_settings.palette = (progressive_settings.palette + 1) % PALETTES.len();
+    }
+    if actions.just_pressed(Action::ToggleMusic) {
+        music_toggle.active = !music_toggle.active;
+    }
 }
 
-// System to update the Mandelbrot material's color_scale based on time.
-fn uniform_update_system(
+// Move the Julia constant with the right gamepad stick. This pauses the animation, which would
+// otherwise overwrite the constant on its next update.
+fn gamepad_julia_system(
+    gamepad: GamepadAxes,
     time: Res<Time>,
+    fractal_type: Res<FractalType>,
+    mut julia_materials: ResMut<Assets<JuliaMaterial>>,
+    mut animation_toggle: ResMut<AnimationUpdateToggle>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let stick = gamepad.right_stick();
+    if stick == Vec2::ZERO || *fractal_type != FractalType::Julia {
+        return;
+    }
+    if animation_toggle.active {
+        animation_toggle.active = false;
+    }
+    redraw.send(RequestRedraw);
+    // Stick up moves the constant up on screen, which is towards negative imaginary values.
+    let step =
+        Vec2::new(stick.x, -stick.y) * gamepad.controls().julia_c_speed * time.delta_seconds();
+    for (_, material) in julia_materials.iter_mut() {
+        material.c += step;
+    }
+}
+
+// System to keep the Mandelbrot material's view uniforms in step with the camera while the
+// timeline plays. The timeline itself animates the parameters.
+fn uniform_update_system(
     mut materials: ResMut<Assets<MandelbrotMaterial>>,
-    mut julia_materials: ResMut<Assets<JuliaMaterial>>, // For Julia material
-    mut burning_ship_materials: ResMut<Assets<BurningShipMaterial>>, // For Julia material
     toggle: Res<AnimationUpdateToggle>,
-    animation_speed: ResMut<AnimationSpeed>,
     pancam_query: Query<&PanCamState>,
 ) {
     if !toggle.active {
         return;
     }
     for (_, material) in materials.iter_mut() {
-        let min_val = 0.05;
-        let max_val = 0.95;
-        let oscillation = (time.elapsed_seconds_f64() as f32 * animation_speed.0).sin();
-
-        let range = max_val - min_val;
-        material.color_scale = min_val + (range / 2.0) * (oscillation + 1.0);
-
         let pancam = pancam_query.get_single().unwrap();
         material.zoom = pancam.current_zoom;
 
@@ -118,35 +183,6 @@ fn uniform_update_system(
         material.offset = offset;
         material.global_offset = offset / pancam.current_zoom;
     }
-    for (_, material) in julia_materials.iter_mut() {
-        // Different frequencies and phase shifts for x and y components
-        let min_val = 0.05;
-        let max_val = 0.95;
-        let oscillation = (time.elapsed_seconds_f64() as f32 * animation_speed.0).sin();
-
-        let range = max_val - min_val;
-        material.color_scale = min_val + (range / 2.0) * (oscillation + 1.0);
-
-        // Restrict the range for c values
-        let max_c = 0.8;
-        let min_c = -0.8;
-
-        let c_range = max_c - min_c;
-        let cx_oscillation = 0.5 * (1.0 - (time.elapsed_seconds_f64() as f32 * 0.1 - 0.5).cos());
-        let cy_oscillation = 0.5 * (1.0 - (time.elapsed_seconds_f64() as f32 * 0.15 + 0.5).cos());
-
-        material.c.x = min_c + c_range * cx_oscillation;
-        material.c.y = min_c + c_range * cy_oscillation;
-    }
-    for (_, material) in burning_ship_materials.iter_mut() {
-        // Different frequencies and phase shifts for x and y components
-        let min_val = 0.00;
-        let max_val = 0.70;
-        let oscillation = (time.elapsed_seconds_f64() as f32 * animation_speed.0).sin();
-
-        let range = max_val - min_val;
-        material.color_scale = min_val + (range / 2.0) * (oscillation + 1.0);
-    }
 }
 
 use bevy::ecs::entity::Entities;
@@ -161,28 +197,37 @@ fn fractal_update_system(
     mut burning_ship_materials: ResMut<Assets<BurningShipMaterial>>, // For Julia material
     mut meshes: ResMut<Assets<Mesh>>, // For meshes
     fractal_type: Res<FractalType>,
+    progressive_settings: Res<ProgressiveSettings>,
     mut mandelbrot_entity: ResMut<MandelbrotEntity>,
     mut julia_entity: ResMut<JuliaEntity>,
     mut burning_ship_entity: ResMut<BurningShipEntity>,
 ) {
     if fractal_type.is_changed() {
         println!("Fractal Type Changed");
-        let colormap_texture_handle = asset_server.load("gradient.png");
+        let colormap_texture_handle = asset_server.load(
+            PALETTES
+                .get(progressive_settings.palette)
+                .copied()
+                .unwrap_or(PALETTES[0]),
+        );
         // Define uniform values for the Mandelbrot material.
         let mandelbrot_uniforms = MandelbrotUniforms {
             color_scale: 0.5,
             max_iterations: 5000.0,
+            periodicity_tolerance: DEFAULT_PERIODICITY_TOLERANCE,
         };
         let julia_uniforms = JuliaUniforms {
             color_scale: 0.5,
             max_iterations: 1000.0,
+            periodicity_tolerance: DEFAULT_PERIODICITY_TOLERANCE,
         };
         let burning_ship_uniforms = BurningShipUniforms {
             color_scale: 0.5,
             max_iterations: 1000.0,
+            periodicity_tolerance: DEFAULT_PERIODICITY_TOLERANCE,
         };
         let mesh = Mesh::from(shape::Quad {
-            size: Vec2::new(10000.0, 10000.0),
+            size: Vec2::new(FRACTAL_QUAD_SIZE, FRACTAL_QUAD_SIZE),
             flip: false,
         });
         let mandelbrot_mesh: Mesh2dHandle = Mesh2dHandle(meshes.add(mesh.clone()));
@@ -208,12 +253,15 @@ fn fractal_update_system(
                 );
                 mandelbrot_entity.0 = Some(
                     commands
-                        .spawn(MaterialMesh2dBundle {
-                            mesh: mandelbrot_mesh.clone(),
-                            material: mandelbrot_material_handle,
-                            transform: Transform::from_xyz(0.0, 0.5, 0.0),
-                            ..Default::default()
-                        })
+                        .spawn((
+                            MaterialMesh2dBundle {
+                                mesh: mandelbrot_mesh.clone(),
+                                material: mandelbrot_material_handle,
+                                transform: Transform::from_xyz(0.0, FRACTAL_QUAD_Y, 0.0),
+                                ..Default::default()
+                            },
+                            FractalQuad,
+                        ))
                         .id(),
                 );
                 println!("Spawned Mandelbrot");
@@ -238,12 +286,15 @@ fn fractal_update_system(
                 );
                 julia_entity.0 = Some(
                     commands
-                        .spawn(MaterialMesh2dBundle {
-                            mesh: mandelbrot_mesh.clone(),
-                            material: julia_material_handle,
-                            transform: Transform::from_xyz(0.0, 0.5, 0.0),
-                            ..Default::default()
-                        })
+                        .spawn((
+                            MaterialMesh2dBundle {
+                                mesh: mandelbrot_mesh.clone(),
+                                material: julia_material_handle,
+                                transform: Transform::from_xyz(0.0, FRACTAL_QUAD_Y, 0.0),
+                                ..Default::default()
+                            },
+                            FractalQuad,
+                        ))
                         .id(),
                 );
                 println!("Spawned Julia");
@@ -268,12 +319,15 @@ fn fractal_update_system(
                 );
                 burning_ship_entity.0 = Some(
                     commands
-                        .spawn(MaterialMesh2dBundle {
-                            mesh: mandelbrot_mesh.clone(),
-                            material: burning_ship_material_handle,
-                            transform: Transform::from_xyz(0.0, 0.5, 0.0),
-                            ..Default::default()
-                        })
+                        .spawn((
+                            MaterialMesh2dBundle {
+                                mesh: mandelbrot_mesh.clone(),
+                                material: burning_ship_material_handle,
+                                transform: Transform::from_xyz(0.0, FRACTAL_QUAD_Y, 0.0),
+                                ..Default::default()
+                            },
+                            FractalQuad,
+                        ))
                         .id(),
                 );
                 println!("Spawned Sierpinski Triangle");
@@ -0,0 +1,198 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+// Maps a raw axis magnitude to a control value: everything inside the dead zone is zero, the
+// rest is rescaled to 0..=1 and raised to `exponent`, so values above 1 give finer control
+// near the center.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AxisResponse {
+    pub dead_zone: f32,
+    pub exponent: f32,
+}
+
+impl AxisResponse {
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        if magnitude <= self.dead_zone {
+            return 0.0;
+        }
+        let scaled = ((magnitude - self.dead_zone) / (1.0 - self.dead_zone).max(f32::EPSILON))
+            .min(1.0)
+            .powf(self.exponent);
+        scaled * value.signum()
+    }
+
+    // Shape a stick radially, so diagonals respond like the main directions.
+    pub fn apply_stick(&self, stick: Vec2) -> Vec2 {
+        let length = stick.length();
+        if length == 0.0 {
+            return Vec2::ZERO;
+        }
+        stick / length * self.apply(length)
+    }
+}
+
+// Tuning for analog gamepad controls. Buttons go through the key bindings instead.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadControls {
+    pub stick: AxisResponse,
+    pub trigger: AxisResponse,
+    // Viewport heights panned per second at full left stick deflection.
+    pub pan_speed: f32,
+    // Natural-log change of the projection scale per second at full trigger.
+    pub zoom_speed: f32,
+    // Complex units the Julia constant moves per second at full right stick deflection.
+    pub julia_c_speed: f32,
+}
+
+impl Default for GamepadControls {
+    fn default() -> Self {
+        GamepadControls {
+            stick: AxisResponse {
+                dead_zone: 0.15,
+                exponent: 2.0,
+            },
+            trigger: AxisResponse {
+                dead_zone: 0.05,
+                exponent: 1.5,
+            },
+            pan_speed: 0.75,
+            zoom_speed: 1.5,
+            julia_c_speed: 0.2,
+        }
+    }
+}
+
+// Shaped analog input summed over every connected gamepad.
+#[derive(SystemParam)]
+pub struct GamepadAxes<'w> {
+    controls: Res<'w, GamepadControls>,
+    gamepads: Res<'w, Gamepads>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    button_axes: Res<'w, Axis<GamepadButton>>,
+}
+
+impl GamepadAxes<'_> {
+    pub fn controls(&self) -> &GamepadControls {
+        &self.controls
+    }
+
+    fn stick(&self, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+        self.gamepads
+            .iter()
+            .map(|gamepad| {
+                let axis = |axis_type| {
+                    self.axes
+                        .get(GamepadAxis::new(gamepad, axis_type))
+                        .unwrap_or(0.0)
+                };
+                self.controls.stick.apply_stick(Vec2::new(axis(x), axis(y)))
+            })
+            .sum::<Vec2>()
+            .clamp_length_max(1.0)
+    }
+
+    // Up is positive y.
+    pub fn left_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY)
+    }
+
+    pub fn right_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY)
+    }
+
+    // Right trigger minus left trigger: positive zooms in.
+    pub fn zoom(&self) -> f32 {
+        self.gamepads
+            .iter()
+            .map(|gamepad| {
+                let trigger = |button_type| {
+                    let value = self
+                        .button_axes
+                        .get(GamepadButton::new(gamepad, button_type))
+                        .unwrap_or(0.0);
+                    self.controls.trigger.apply(value)
+                };
+                trigger(GamepadButtonType::RightTrigger2) - trigger(GamepadButtonType::LeftTrigger2)
+            })
+            .sum::<f32>()
+            .clamp(-1.0, 1.0)
+    }
+}
+
+fn axis_response_ui(ui: &mut egui::Ui, name: &str, response: &mut AxisResponse) {
+    ui.horizontal(|ui| {
+        ui.label(format!("{name} Dead Zone:"));
+        ui.add(egui::Slider::new(&mut response.dead_zone, 0.0..=0.9));
+    });
+    ui.horizontal(|ui| {
+        ui.label(format!("{name} Curve:"));
+        ui.add(egui::Slider::new(&mut response.exponent, 0.5..=4.0));
+    });
+}
+
+// Sliders for the analog tuning, shown in the key bindings window.
+pub fn gamepad_controls_ui(ui: &mut egui::Ui, controls: &mut GamepadControls) {
+    axis_response_ui(ui, "Stick", &mut controls.stick);
+    axis_response_ui(ui, "Trigger", &mut controls.trigger);
+    ui.horizontal(|ui| {
+        ui.label("Pan Speed:");
+        ui.add(egui::Slider::new(&mut controls.pan_speed, 0.05..=5.0).logarithmic(true));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Zoom Speed:");
+        ui.add(egui::Slider::new(&mut controls.zoom_speed, 0.1..=10.0).logarithmic(true));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Julia C Speed:");
+        ui.add(egui::Slider::new(&mut controls.julia_c_speed, 0.01..=2.0).logarithmic(true));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: AxisResponse = AxisResponse {
+        dead_zone: 0.2,
+        exponent: 2.0,
+    };
+
+    #[test]
+    fn dead_zone_is_zero() {
+        assert_eq!(RESPONSE.apply(0.15), 0.0);
+        assert_eq!(RESPONSE.apply(-0.2), 0.0);
+    }
+
+    #[test]
+    fn response_is_rescaled_and_keeps_sign() {
+        assert_eq!(RESPONSE.apply(1.0), 1.0);
+        assert_eq!(RESPONSE.apply(-1.0), -1.0);
+        assert!((RESPONSE.apply(0.6) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stick_shaping_is_radial() {
+        let stick = RESPONSE.apply_stick(Vec2::new(0.6, 0.8));
+        assert!((stick.length() - 1.0).abs() < 1e-6);
+        assert!((stick.x / stick.y - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn controls_round_trip_through_ron() {
+        let controls = GamepadControls {
+            stick: RESPONSE,
+            pan_speed: 2.0,
+            ..default()
+        };
+        let text = ron::to_string(&controls).unwrap();
+        assert_eq!(ron::from_str::<GamepadControls>(&text).unwrap(), controls);
+        // Fields missing from the file keep their defaults.
+        let partial: GamepadControls = ron::from_str("(zoom_speed: 3.0)").unwrap();
+        assert_eq!(partial.zoom_speed, 3.0);
+        assert_eq!(partial.stick, GamepadControls::default().stick);
+    }
+}
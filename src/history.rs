@@ -0,0 +1,581 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy::utils::HashMap;
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use futures_lite::future;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, ActionInput};
+use crate::fractals::{
+    world_to_complex, AnimationUpdateToggle, FractalType, WORLD_UNITS_PER_COMPLEX_UNIT,
+};
+use crate::julia_path::JuliaPath;
+use crate::kernels::{render_rgba8, Colormap, KernelParams};
+use crate::materials::{ActiveFractalParams, FractalMaterials};
+use crate::pancam::{EguiWantsFocus, PanCamState, PanCamSystemSet};
+use crate::progressive::{ProgressiveSettings, PALETTES};
+use crate::timeline::Timeline;
+use crate::verify::load_colormap;
+
+// The navigation history, the timeline and the Julia path are kept in this file between runs.
+pub const SESSION_PATH: &str = "session.ron";
+// Seconds the view has to hold still before it is recorded.
+const SETTLE_TIME: f32 = 0.4;
+// The oldest entries are dropped beyond this many.
+const MAX_HISTORY: usize = 64;
+const THUMBNAIL_WIDTH: u32 = 96;
+const THUMBNAIL_HEIGHT: u32 = 64;
+// Thumbnails are tiny, so a lower iteration cap keeps deep views from tying up a worker.
+const THUMBNAIL_MAX_ITERATIONS: f32 = 2000.0;
+
+// A settled view: the fractal, where the camera was and the parameters it was drawn with.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub fractal: FractalType,
+    pub translation: Vec2,
+    // Projection scale of the camera.
+    pub scale: f32,
+    // Height of the visible area in world units.
+    pub view_height: f32,
+    pub color_scale: f32,
+    pub max_iterations: f32,
+    pub periodicity_tolerance: f32,
+    pub julia_c: Vec2,
+    // Index into PALETTES.
+    pub palette: usize,
+}
+
+impl HistoryEntry {
+    fn params(&self) -> ActiveFractalParams {
+        ActiveFractalParams {
+            fractal: self.fractal.shader_index(),
+            color_scale: self.color_scale,
+            max_iterations: self.max_iterations,
+            julia_c: self.julia_c,
+            periodicity_tolerance: self.periodicity_tolerance,
+        }
+    }
+
+    // Whether two entries show practically the same view, so only one of them is worth keeping.
+    fn same_view(&self, other: &HistoryEntry) -> bool {
+        self.fractal == other.fractal
+            && (self.scale / other.scale).ln().abs() < 0.05
+            && self.translation.distance(other.translation) < self.view_height * 0.02
+            && (self.fractal != FractalType::Julia || self.julia_c.distance(other.julia_c) < 1e-4)
+    }
+
+    fn label(&self, home_zoom: f32) -> String {
+        let magnification = home_zoom / self.scale.max(f32::MIN_POSITIVE);
+        format!("{:?}, {magnification:.3}x", self.fractal)
+    }
+}
+
+enum Thumbnail {
+    Missing,
+    Rendering(Task<Result<Vec<u8>, String>>),
+    Ready(egui::TextureHandle),
+    Failed,
+}
+
+struct Record {
+    entry: HistoryEntry,
+    thumbnail: Thumbnail,
+}
+
+// Settled views in the order they were visited, with back and forward like a browser.
+#[derive(Resource, Default)]
+pub struct NavigationHistory {
+    records: Vec<Record>,
+    // The entry the view is at. None until the first view of a run is recorded.
+    current: Option<usize>,
+    // Entry to animate to on the next update.
+    target: Option<HistoryEntry>,
+    // Entry whose parameters wait for its fractal's material to exist.
+    pending_parameters: Option<HistoryEntry>,
+    // The entries changed since the session was last saved.
+    dirty: bool,
+}
+
+impl NavigationHistory {
+    fn from_entries(entries: Vec<HistoryEntry>) -> Self {
+        NavigationHistory {
+            records: entries
+                .into_iter()
+                .map(|entry| Record {
+                    entry,
+                    thumbnail: Thumbnail::Missing,
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.records.iter().map(|record| &record.entry)
+    }
+
+    pub fn current(&self) -> Option<&HistoryEntry> {
+        self.current.map(|index| &self.records[index].entry)
+    }
+
+    // Record a settled view. Entries after the current one are dropped.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if let Some(current) = self.current {
+            self.records.truncate(current + 1);
+        }
+        self.records.push(Record {
+            entry,
+            thumbnail: Thumbnail::Missing,
+        });
+        if self.records.len() > MAX_HISTORY {
+            self.records.remove(0);
+        }
+        self.current = Some(self.records.len() - 1);
+        self.dirty = true;
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.current
+            .map_or(!self.records.is_empty(), |current| current > 0)
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.current
+            .is_some_and(|current| current + 1 < self.records.len())
+    }
+
+    // Before the first view of a run is recorded, back goes to the newest saved entry.
+    pub fn back(&mut self) {
+        if self.can_go_back() {
+            self.go_to(self.current.unwrap_or(self.records.len()) - 1);
+        }
+    }
+
+    pub fn forward(&mut self) {
+        if let Some(current) = self.current.filter(|_| self.can_go_forward()) {
+            self.go_to(current + 1);
+        }
+    }
+
+    pub fn go_to(&mut self, index: usize) {
+        if let Some(record) = self.records.get(index) {
+            self.current = Some(index);
+            self.target = Some(record.entry);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.current = None;
+        self.dirty = true;
+    }
+}
+
+// Everything kept between runs in SESSION_PATH.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Session {
+    history: Vec<HistoryEntry>,
+    timeline: Timeline,
+    julia_path: JuliaPath,
+}
+
+#[derive(Default)]
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        let session = load_session();
+        app.insert_resource(NavigationHistory::from_entries(session.history))
+            .insert_resource(session.timeline)
+            .insert_resource(session.julia_path)
+            .add_systems(
+                Update,
+                (
+                    // Like the camera controls, the bindings stay out of the way of egui.
+                    history_bindings_system
+                        .run_if(resource_equals(EguiWantsFocus(false)))
+                        .before(navigation_system),
+                    navigation_system.before(PanCamSystemSet),
+                    record_history_system.after(PanCamSystemSet),
+                    thumbnail_system,
+                    history_ui_system,
+                ),
+            )
+            // After the fractal quad has been respawned, so its new material gets the parameters.
+            .add_systems(PostUpdate, (restore_parameters_system, save_session_system));
+    }
+}
+
+fn load_session() -> Session {
+    let text = match std::fs::read_to_string(SESSION_PATH) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Session::default(),
+        Err(err) => {
+            error!("Could not read {SESSION_PATH}: {err}");
+            return Session::default();
+        }
+    };
+    ron::from_str(&text).unwrap_or_else(|err| {
+        error!("Could not parse {SESSION_PATH}, starting a new session: {err}");
+        Session::default()
+    })
+}
+
+fn save_session_system(
+    mut history: ResMut<NavigationHistory>,
+    mut timeline: ResMut<Timeline>,
+    mut julia_path: ResMut<JuliaPath>,
+) {
+    if !history.dirty && !timeline.dirty && !julia_path.dirty {
+        return;
+    }
+    history.dirty = false;
+    timeline.dirty = false;
+    julia_path.dirty = false;
+    let session = Session {
+        history: history.entries().copied().collect(),
+        timeline: timeline.clone(),
+        julia_path: julia_path.clone(),
+    };
+    let result = ron::ser::to_string_pretty(&session, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|text| std::fs::write(SESSION_PATH, text).map_err(|err| err.to_string()));
+    if let Err(err) = result {
+        error!("Could not save {SESSION_PATH}: {err}");
+    }
+}
+
+#[derive(Default)]
+struct SettleState {
+    // Camera translation and projection scale last frame.
+    last_view: Option<(Vec3, f32)>,
+    still_for: f32,
+    recorded: bool,
+}
+
+// Push the view once the camera has held still for SETTLE_TIME after moving.
+#[allow(clippy::too_many_arguments)]
+fn record_history_system(
+    mut history: ResMut<NavigationHistory>,
+    cameras: Query<(&PanCamState, &OrthographicProjection, &Transform)>,
+    fractal_type: Res<FractalType>,
+    materials: FractalMaterials,
+    progressive_settings: Res<ProgressiveSettings>,
+    time: Res<Time>,
+    mut settle: Local<SettleState>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let Ok((cam, proj, transform)) = cameras.get_single() else {
+        return;
+    };
+    let view = (transform.translation, proj.scale);
+    if !cam.initialized
+        || cam.first_zoom
+        || cam.is_zooming
+        || cam.velocity != Vec2::ZERO
+        || settle.last_view != Some(view)
+    {
+        *settle = SettleState {
+            last_view: Some(view),
+            ..default()
+        };
+        return;
+    }
+    if settle.recorded {
+        return;
+    }
+    settle.still_for += time.delta_seconds();
+    if settle.still_for < SETTLE_TIME {
+        // A reactive app might not update again on its own.
+        redraw.send(RequestRedraw);
+        return;
+    }
+    settle.recorded = true;
+
+    let Some(params) = materials.active(&fractal_type) else {
+        return;
+    };
+    let entry = HistoryEntry {
+        fractal: *fractal_type,
+        translation: transform.translation.truncate(),
+        scale: proj.scale,
+        view_height: proj.area.height(),
+        color_scale: params.color_scale,
+        max_iterations: params.max_iterations,
+        periodicity_tolerance: params.periodicity_tolerance,
+        julia_c: params.julia_c,
+        palette: progressive_settings.palette,
+    };
+    if !history
+        .current()
+        .is_some_and(|current| current.same_view(&entry))
+    {
+        history.push(entry);
+    }
+}
+
+// Go back and forward with the bindings.
+fn history_bindings_system(actions: ActionInput, mut history: ResMut<NavigationHistory>) {
+    if actions.just_pressed(Action::HistoryBack) {
+        history.back();
+    } else if actions.just_pressed(Action::HistoryForward) {
+        history.forward();
+    }
+}
+
+// Animate to the entry picked with the bindings or in the window.
+fn navigation_system(
+    mut history: ResMut<NavigationHistory>,
+    mut cameras: Query<(&mut PanCamState, &Transform)>,
+    mut fractal_type: ResMut<FractalType>,
+) {
+    let Some(entry) = history.target.take() else {
+        return;
+    };
+    for (mut cam, transform) in &mut cameras {
+        if cam.initialized {
+            cam.animate_to(
+                entry.translation.extend(transform.translation.z),
+                entry.scale,
+            );
+        }
+    }
+    if *fractal_type != entry.fractal {
+        *fractal_type = entry.fractal;
+    }
+    history.pending_parameters = Some(entry);
+}
+
+// Put an entry's parameters back once its fractal's material exists; switching fractals spawns a
+// fresh material with the defaults.
+fn restore_parameters_system(
+    mut history: ResMut<NavigationHistory>,
+    fractal_type: Res<FractalType>,
+    mut materials: FractalMaterials,
+    mut progressive_settings: ResMut<ProgressiveSettings>,
+    mut animation: ResMut<AnimationUpdateToggle>,
+) {
+    let Some(entry) = history.pending_parameters else {
+        return;
+    };
+    // The fractal was switched again before the material came up.
+    if *fractal_type != entry.fractal {
+        history.pending_parameters = None;
+        return;
+    }
+    if materials.active(&entry.fractal).is_none() {
+        return;
+    }
+    history.pending_parameters = None;
+    materials.restore(&entry.fractal, &entry.params());
+    if progressive_settings.palette != entry.palette {
+        progressive_settings.palette = entry.palette;
+    }
+    // The color animation would overwrite the restored coloring and Julia constant right away.
+    if animation.active {
+        animation.active = false;
+    }
+}
+
+// Decoded palettes shared by the thumbnail tasks, so each PNG is only loaded once.
+#[derive(Clone, Default)]
+struct ThumbnailPalettes(Arc<Mutex<HashMap<usize, Arc<Colormap>>>>);
+
+impl ThumbnailPalettes {
+    fn get(&self, palette: usize) -> Result<Arc<Colormap>, String> {
+        let mut palettes = self.0.lock().map_err(|err| err.to_string())?;
+        if let Some(colormap) = palettes.get(&palette) {
+            return Ok(colormap.clone());
+        }
+        let path = PALETTES.get(palette).copied().unwrap_or(PALETTES[0]);
+        let colormap = Arc::new(load_colormap(path)?);
+        palettes.insert(palette, colormap.clone());
+        Ok(colormap)
+    }
+}
+
+// Draw an entry on the CPU with the same kernels the shaders use.
+fn render_thumbnail(entry: &HistoryEntry, palettes: &ThumbnailPalettes) -> Result<Vec<u8>, String> {
+    let colormap = palettes.get(entry.palette)?;
+    let params = KernelParams {
+        color_scale: entry.color_scale,
+        max_iterations: entry.max_iterations.min(THUMBNAIL_MAX_ITERATIONS),
+        periodicity_tolerance: entry.periodicity_tolerance,
+    };
+    // The thumbnail spans the height of the view.
+    Ok(render_rgba8(
+        entry.fractal.kernel(entry.julia_c),
+        &params,
+        &colormap,
+        world_to_complex(entry.translation).to_array(),
+        entry.view_height / WORLD_UNITS_PER_COMPLEX_UNIT / THUMBNAIL_HEIGHT as f32,
+        THUMBNAIL_WIDTH,
+        THUMBNAIL_HEIGHT,
+    ))
+}
+
+// Render missing thumbnails in the background and upload the finished ones to egui.
+fn thumbnail_system(
+    mut history: ResMut<NavigationHistory>,
+    mut ctx: EguiContexts,
+    palettes: Local<ThumbnailPalettes>,
+) {
+    for record in &mut history.records {
+        match &mut record.thumbnail {
+            Thumbnail::Missing => {
+                let entry = record.entry;
+                let palettes = palettes.clone();
+                record.thumbnail = Thumbnail::Rendering(
+                    AsyncComputeTaskPool::get()
+                        .spawn(async move { render_thumbnail(&entry, &palettes) }),
+                );
+            }
+            Thumbnail::Rendering(task) => {
+                let Some(result) = block_on(future::poll_once(task)) else {
+                    continue;
+                };
+                record.thumbnail = match result {
+                    Ok(pixels) => Thumbnail::Ready(ctx.ctx_mut().load_texture(
+                        "history-thumbnail",
+                        egui::ColorImage::from_rgba_unmultiplied(
+                            [THUMBNAIL_WIDTH as usize, THUMBNAIL_HEIGHT as usize],
+                            &pixels,
+                        ),
+                        egui::TextureOptions::LINEAR,
+                    )),
+                    Err(err) => {
+                        error!("Could not render a history thumbnail: {err}");
+                        Thumbnail::Failed
+                    }
+                };
+            }
+            Thumbnail::Ready(_) | Thumbnail::Failed => {}
+        }
+    }
+}
+
+// Back, forward and a strip of thumbnails to jump to any earlier view.
+fn history_ui_system(
+    mut ctx: EguiContexts,
+    mut history: ResMut<NavigationHistory>,
+    cameras: Query<&PanCamState>,
+) {
+    let home_zoom = cameras.get_single().map_or(1.0, |cam| cam.home_zoom);
+    let size = egui::vec2(THUMBNAIL_WIDTH as f32, THUMBNAIL_HEIGHT as f32);
+    let (mut back, mut forward, mut clear, mut go_to) = (false, false, false, None);
+    egui::Window::new("History")
+        .default_open(false)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                back = ui
+                    .add_enabled(history.can_go_back(), egui::Button::new("Back"))
+                    .clicked();
+                forward = ui
+                    .add_enabled(history.can_go_forward(), egui::Button::new("Forward"))
+                    .clicked();
+                clear = ui.button("Clear").clicked();
+                ui.label(format!("{} views", history.records.len()));
+            });
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (index, record) in history.records.iter().enumerate() {
+                        let selected = history.current == Some(index);
+                        let response = match &record.thumbnail {
+                            Thumbnail::Ready(texture) => ui.add(
+                                egui::ImageButton::new(egui::load::SizedTexture::new(
+                                    texture.id(),
+                                    size,
+                                ))
+                                .selected(selected),
+                            ),
+                            _ => ui.add_sized(size, egui::SelectableLabel::new(selected, "...")),
+                        };
+                        if response
+                            .on_hover_text(record.entry.label(home_zoom))
+                            .clicked()
+                        {
+                            go_to = Some(index);
+                        }
+                    }
+                });
+            });
+        });
+    if back {
+        history.back();
+    } else if forward {
+        history.forward();
+    } else if let Some(index) = go_to {
+        history.go_to(index);
+    } else if clear {
+        history.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(x: f32) -> HistoryEntry {
+        HistoryEntry {
+            fractal: FractalType::Mandelbrot,
+            translation: Vec2::new(x, 0.0),
+            scale: 1.0,
+            view_height: 10.0,
+            color_scale: 0.5,
+            max_iterations: 1000.0,
+            periodicity_tolerance: 1e-5,
+            julia_c: Vec2::ZERO,
+            palette: 0,
+        }
+    }
+
+    fn positions(history: &NavigationHistory) -> Vec<f32> {
+        history.entries().map(|entry| entry.translation.x).collect()
+    }
+
+    #[test]
+    fn pushing_after_going_back_drops_forward_entries() {
+        let mut history = NavigationHistory::default();
+        for x in [1.0, 2.0, 3.0] {
+            history.push(entry(x));
+        }
+        history.back();
+        history.back();
+        assert_eq!(history.target.map(|entry| entry.translation.x), Some(1.0));
+        assert!(history.can_go_forward());
+        history.push(entry(4.0));
+        assert_eq!(positions(&history), vec![1.0, 4.0]);
+        assert!(!history.can_go_forward());
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut history = NavigationHistory::default();
+        for x in 0..MAX_HISTORY + 3 {
+            history.push(entry(x as f32));
+        }
+        assert_eq!(history.entries().count(), MAX_HISTORY);
+        assert_eq!(history.entries().next().unwrap().translation.x, 3.0);
+    }
+
+    #[test]
+    fn saved_history_starts_behind_the_new_run() {
+        let session = Session {
+            history: vec![entry(1.0), entry(2.0)],
+            ..default()
+        };
+        let text = ron::to_string(&session).unwrap();
+        let loaded: Session = ron::from_str(&text).unwrap();
+        let mut history = NavigationHistory::from_entries(loaded.history);
+        assert!(history.current().is_none());
+        history.back();
+        assert_eq!(
+            history.current().map(|entry| entry.translation.x),
+            Some(2.0)
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::fractals::{world_to_complex, FractalType, WORLD_UNITS_PER_COMPLEX_UNIT};
+use crate::kernels::{escape, smooth_escape, Kernel, KernelParams};
+use crate::materials::FractalMaterials;
+use crate::pancam::PanCamState;
+
+// Digits shown past the one that changes from pixel to pixel.
+const EXTRA_DIGITS: i32 = 1;
+// f32 coordinates carry no more than this, so deeper views just show noise past it.
+const MAX_DECIMALS: i32 = 10;
+
+#[derive(Default)]
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, hud_system);
+    }
+}
+
+// Decimals needed to tell neighbouring pixels apart when they are `spacing` apart.
+fn coordinate_decimals(spacing: f32) -> usize {
+    ((-spacing.log10()).ceil() as i32 + EXTRA_DIGITS).clamp(EXTRA_DIGITS, MAX_DECIMALS) as usize
+}
+
+// The cursor picks c for the Mandelbrot set and Burning Ship, and the starting point for Julia sets.
+fn point_label(fractal: FractalType) -> &'static str {
+    match fractal {
+        FractalType::Julia => "z0",
+        _ => "c",
+    }
+}
+
+// How the orbit of `point` ends. The smooth value comes from smooth_escape, since the Mandelbrot
+// kernel's own escape point is a box test's.
+fn orbit_summary(kernel: Kernel, point: [f32; 2], params: &KernelParams) -> String {
+    let orbit = escape(kernel, point, params);
+    if orbit.escaped {
+        let smooth =
+            smooth_escape(kernel, point, params.max_iterations).unwrap_or(orbit.iterations);
+        format!(
+            "escaped after {} (smooth {:.3}), |z| {:.4}",
+            orbit.iterations,
+            smooth,
+            orbit.magnitude()
+        )
+    } else if orbit.period > 0.0 {
+        format!(
+            "inside, period {}, |z| {:.4}",
+            orbit.period,
+            orbit.magnitude()
+        )
+    } else {
+        format!(
+            "bounded for {}, |z| {:.4}",
+            orbit.iterations,
+            orbit.magnitude()
+        )
+    }
+}
+
+// Corner readout of the point under the cursor and how deep the view is.
+fn hud_system(
+    mut ctx: EguiContexts,
+    cameras: Query<(
+        &Camera,
+        &GlobalTransform,
+        &OrthographicProjection,
+        &PanCamState,
+    )>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    fractal_type: Res<FractalType>,
+    materials: FractalMaterials,
+) {
+    let Ok((camera, camera_transform, proj, cam)) = cameras.get_single() else {
+        return;
+    };
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+    let spacing = proj.area.height() / viewport_size.y / WORLD_UNITS_PER_COMPLEX_UNIT;
+    let decimals = coordinate_decimals(spacing);
+
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+        .map(world_to_complex);
+    let params = materials.active(&fractal_type);
+    let label = point_label(*fractal_type);
+
+    egui::Area::new("hud")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+        .interactable(false)
+        .show(ctx.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let line = |ui: &mut egui::Ui, text: String| {
+                    ui.label(egui::RichText::new(text).monospace());
+                };
+                match cursor {
+                    Some(point) => {
+                        let sign = if point.y < 0.0 { '-' } else { '+' };
+                        line(
+                            ui,
+                            format!(
+                                "{label}  {:.*} {sign} {:.*}i",
+                                decimals,
+                                point.x,
+                                decimals,
+                                point.y.abs()
+                            ),
+                        );
+                        if let Some(params) = params {
+                            line(
+                                ui,
+                                orbit_summary(
+                                    fractal_type.kernel(params.julia_c),
+                                    point.to_array(),
+                                    &KernelParams {
+                                        color_scale: params.color_scale,
+                                        max_iterations: params.max_iterations,
+                                        periodicity_tolerance: params.periodicity_tolerance,
+                                    },
+                                ),
+                            );
+                        }
+                    }
+                    None => line(ui, format!("{label}  -")),
+                }
+                line(
+                    ui,
+                    format!(
+                        "magnification 10^{:.2}",
+                        cam.magnification(proj.scale).log10()
+                    ),
+                );
+                line(ui, format!("pixel spacing {spacing:.2e}"));
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimals_follow_pixel_spacing() {
+        assert_eq!(coordinate_decimals(0.004), 4);
+        assert_eq!(coordinate_decimals(2e-6), 7);
+        assert_eq!(coordinate_decimals(10.0), 1);
+        assert_eq!(coordinate_decimals(1e-20), 10);
+    }
+
+    #[test]
+    fn julia_points_are_labelled_z0() {
+        assert_eq!(point_label(FractalType::Mandelbrot), "c");
+        assert_eq!(point_label(FractalType::BurningShip), "c");
+        assert_eq!(point_label(FractalType::Julia), "z0");
+    }
+
+    #[test]
+    fn summary_describes_how_the_orbit_ends() {
+        let params = KernelParams {
+            color_scale: 0.0,
+            max_iterations: 100.0,
+            periodicity_tolerance: 1e-4,
+        };
+        // z2 = 1 + 3i leaves the box.
+        let escaped = orbit_summary(Kernel::Mandelbrot, [1.0, 1.0], &params);
+        assert!(escaped.starts_with("escaped after 1 (smooth "), "{escaped}");
+        assert!(
+            escaped.ends_with(&format!("|z| {:.4}", 10f32.sqrt())),
+            "{escaped}"
+        );
+        // z -> z^2 pulls 0.5 into the fixed point at 0.
+        let inside = orbit_summary(Kernel::Julia { c: [0.0, 0.0] }, [0.5, 0.0], &params);
+        assert!(inside.starts_with("inside, period 1"), "{inside}");
+        // The main cardioid is skipped without iterating.
+        let bounded = orbit_summary(Kernel::Mandelbrot, [0.0, 0.0], &params);
+        assert_eq!(bounded, "bounded for 100, |z| 0.0000");
+    }
+
+    #[test]
+    fn mandelbrot_smooth_value_is_continuous() {
+        // The orbits along this line leave the box one step later past Re(c) = -2, where the
+        // smooth value used to jump by more than one.
+        let max_iterations = 100.0;
+        let mut previous = None;
+        for i in 0..=400 {
+            let c = [-2.5 + i as f32 * 0.002, 0.3];
+            let smooth = smooth_escape(Kernel::Mandelbrot, c, max_iterations).unwrap();
+            if let Some(previous) = previous {
+                let difference: f32 = smooth - previous;
+                assert!(difference.abs() < 0.05, "jump of {difference} at {c:?}");
+            }
+            previous = Some(smooth);
+        }
+    }
+}
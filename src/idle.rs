@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::window::RequestRedraw;
+use bevy::winit::WinitSettings;
+
+use crate::export::ExportState;
+use crate::materials::{BurningShipMaterial, JuliaMaterial, MandelbrotMaterial};
+use crate::progressive::{ProgressiveSettings, ProgressiveView};
+
+// When enabled the app only redraws on input or when something on screen is still changing.
+#[derive(Resource, Clone, PartialEq, Default)]
+pub struct IdleMode {
+    pub enabled: bool,
+}
+
+#[derive(Default)]
+pub struct IdleModePlugin;
+
+impl Plugin for IdleModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<IdleMode>()
+            .add_systems(Update, (apply_idle_mode_system, idle_redraw_system));
+    }
+}
+
+// Switch winit between continuous and reactive updates when the setting changes.
+fn apply_idle_mode_system(idle_mode: Res<IdleMode>, mut winit_settings: ResMut<WinitSettings>) {
+    if !idle_mode.is_changed() {
+        return;
+    }
+    *winit_settings = if idle_mode.enabled {
+        WinitSettings::desktop_app()
+    } else {
+        WinitSettings::game()
+    };
+}
+
+// Request another frame while work is pending that input alone would not wake the app for.
+// Camera and color animations request their own frames.
+#[allow(clippy::too_many_arguments)]
+fn idle_redraw_system(
+    idle_mode: Res<IdleMode>,
+    progressive_settings: Res<ProgressiveSettings>,
+    progressive_view: Res<ProgressiveView>,
+    export_state: Res<ExportState>,
+    mut mandelbrot_events: EventReader<AssetEvent<MandelbrotMaterial>>,
+    mut julia_events: EventReader<AssetEvent<JuliaMaterial>>,
+    mut burning_ship_events: EventReader<AssetEvent<BurningShipMaterial>>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    // Drain the readers either way so stale events don't trigger a redraw later.
+    let materials_changed = mandelbrot_events.read().count()
+        + julia_events.read().count()
+        + burning_ship_events.read().count()
+        > 0;
+    if !idle_mode.enabled {
+        return;
+    }
+    let progressive_unfinished = progressive_view.enabled && !progressive_view.is_finished();
+    if materials_changed
+        || progressive_unfinished
+        || progressive_settings.is_changed()
+        || export_state.is_exporting()
+    {
+        redraw.send(RequestRedraw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::{edit_materials, prepare_mandelbrot_material, MandelbrotUniforms};
+
+    // Color scale a stand-in for the settings window writes every frame, changed or not.
+    #[derive(Resource)]
+    struct ColorScale(f32);
+
+    #[derive(Resource)]
+    struct MaterialHandle(#[allow(dead_code)] Handle<MandelbrotMaterial>);
+
+    fn spawn_material(mut commands: Commands, mut materials: ResMut<Assets<MandelbrotMaterial>>) {
+        let uniforms = MandelbrotUniforms {
+            color_scale: 0.5,
+            max_iterations: 100.0,
+            periodicity_tolerance: 0.0,
+        };
+        // Keep the strong handle alive or the material is freed right away.
+        commands.insert_resource(MaterialHandle(prepare_mandelbrot_material(
+            &uniforms,
+            Handle::default(),
+            &mut materials,
+        )));
+    }
+
+    fn apply_color_scale(
+        color_scale: Res<ColorScale>,
+        mut materials: ResMut<Assets<MandelbrotMaterial>>,
+    ) {
+        edit_materials(&mut materials, |material| {
+            material.color_scale = color_scale.0;
+        });
+    }
+
+    fn redraws_after_update(app: &mut App) -> usize {
+        app.update();
+        app.world
+            .resource_mut::<Events<RequestRedraw>>()
+            .drain()
+            .count()
+    }
+
+    #[test]
+    fn idle_app_without_input_stops_redrawing() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<MandelbrotMaterial>()
+            .init_asset::<JuliaMaterial>()
+            .init_asset::<BurningShipMaterial>()
+            .add_event::<RequestRedraw>()
+            .insert_resource(WinitSettings::game())
+            .insert_resource(IdleMode { enabled: true })
+            .insert_resource(ColorScale(0.5))
+            .init_resource::<ProgressiveSettings>()
+            .init_resource::<ProgressiveView>()
+            .init_resource::<ExportState>()
+            .add_plugins(IdleModePlugin)
+            .add_systems(Startup, spawn_material)
+            .add_systems(Update, apply_color_scale);
+
+        // Creating the material is worth a frame or two, then nothing is pending.
+        let settling: usize = (0..4).map(|_| redraws_after_update(&mut app)).sum();
+        assert!(settling > 0);
+        for _ in 0..10 {
+            assert_eq!(redraws_after_update(&mut app), 0);
+        }
+
+        // A real edit wakes the app once more.
+        app.world.resource_mut::<ColorScale>().0 = 0.75;
+        let woken: usize = (0..4).map(|_| redraws_after_update(&mut app)).sum();
+        assert!(woken > 0);
+        assert_eq!(redraws_after_update(&mut app), 0);
+    }
+}
@@ -0,0 +1,498 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use futures_lite::future;
+use serde::{Deserialize, Serialize};
+
+use crate::export::{ExportSettings, ExportState, SequenceView};
+use crate::fractals::{AnimationUpdateToggle, FractalType};
+use crate::kernels::{render_rgba8, Kernel, KernelParams};
+use crate::materials::{edit_materials, FractalMaterials, JuliaMaterial};
+use crate::pancam::PanCamState;
+use crate::progressive::{PinnedFrame, ProgressiveSettings, PALETTES};
+use crate::timeline::timeline_playback_system;
+use crate::verify::load_colormap;
+
+// Points the cardioid and circle outlines are made of.
+const OUTLINE_POINTS: usize = 256;
+// Freehand points closer together than this, in complex units, are dropped while drawing.
+const MIN_FREEHAND_SPACING: f32 = 0.005;
+// Side of the map the path is drawn on, in points, and of the image rendered for it, in pixels.
+const MAP_SIZE: u32 = 240;
+// The map shows the part of the plane holding the Mandelbrot set.
+const MAP_CENTER: [f32; 2] = [-0.5, 0.0];
+const MAP_SPAN: f32 = 3.0;
+const MAP_MAX_ITERATIONS: f32 = 200.0;
+// Loop exports use the Julia material's parameters, or these when it doesn't exist yet.
+const LOOP_EXPORT_PARAMS: KernelParams = KernelParams {
+    color_scale: 0.5,
+    max_iterations: 1000.0,
+    periodicity_tolerance: 1e-5,
+};
+
+// The closed curve the Julia constant travels around.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PathShape {
+    // Boundary of the main cardioid, c = e^(it) / 2 - e^(2it) / 4.
+    Cardioid,
+    Circle,
+    Freehand,
+}
+
+// A closed path in the parameter plane that drives the Julia constant at constant speed.
+// Saved in the session file with the view history.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JuliaPath {
+    // Move the Julia constant along the path while the animation plays.
+    pub enabled: bool,
+    pub shape: PathShape,
+    pub circle_center: Vec2,
+    pub circle_radius: f32,
+    // Points drawn by hand, in order. The path closes back to the first one.
+    pub freehand: Vec<Vec2>,
+    // Seconds for one trip around the path.
+    pub loop_seconds: f32,
+    // Frames in an exported loop.
+    pub loop_frames: u32,
+    // How far around the path c is, from 0 to 1.
+    #[serde(skip)]
+    pub phase: f32,
+    // Edited since the session was last saved.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl Default for JuliaPath {
+    fn default() -> Self {
+        JuliaPath {
+            enabled: true,
+            shape: PathShape::Cardioid,
+            // The boundary of the period 2 bulb.
+            circle_center: Vec2::new(-1.0, 0.0),
+            circle_radius: 0.25,
+            freehand: Vec::new(),
+            loop_seconds: 60.0,
+            loop_frames: 120,
+            phase: 0.0,
+            dirty: false,
+        }
+    }
+}
+
+impl JuliaPath {
+    // The path as a closed polyline, without repeating the first point at the end.
+    pub fn outline(&self) -> Vec<Vec2> {
+        match self.shape {
+            PathShape::Cardioid => (0..OUTLINE_POINTS)
+                .map(|i| {
+                    let t = i as f32 / OUTLINE_POINTS as f32 * TAU;
+                    Vec2::new(t.cos() / 2.0, t.sin() / 2.0)
+                        - Vec2::new((2.0 * t).cos() / 4.0, (2.0 * t).sin() / 4.0)
+                })
+                .collect(),
+            PathShape::Circle => (0..OUTLINE_POINTS)
+                .map(|i| {
+                    let t = i as f32 / OUTLINE_POINTS as f32 * TAU;
+                    self.circle_center + self.circle_radius * Vec2::new(t.cos(), t.sin())
+                })
+                .collect(),
+            PathShape::Freehand => self.freehand.clone(),
+        }
+    }
+
+    // The point `phase` of the way around the path by length, so c moves at constant speed.
+    pub fn point_at(&self, phase: f32) -> Option<Vec2> {
+        let outline = self.outline();
+        let first = *outline.first()?;
+        let segments = || {
+            outline
+                .iter()
+                .copied()
+                .zip(outline.iter().copied().skip(1).chain([first]))
+        };
+        let length: f32 = segments().map(|(a, b)| a.distance(b)).sum();
+        if length <= 0.0 {
+            return Some(first);
+        }
+        let mut remaining = phase.rem_euclid(1.0) * length;
+        for (a, b) in segments() {
+            let segment = a.distance(b);
+            if remaining <= segment && segment > 0.0 {
+                return Some(a.lerp(b, remaining / segment));
+            }
+            remaining -= segment;
+        }
+        Some(first)
+    }
+
+    // One Julia set per step around the path. The last frame stops a step short of the first, so
+    // the sequence plays back as a seamless loop.
+    fn loop_sequence(
+        &self,
+        params: KernelParams,
+        palette: usize,
+        view: SequenceView,
+    ) -> Vec<PinnedFrame> {
+        let frames = self.loop_frames.max(2);
+        (0..frames)
+            .filter_map(|frame| {
+                let c = self.point_at(frame as f32 / frames as f32)?;
+                Some(PinnedFrame {
+                    fractal: FractalType::Julia.shader_index(),
+                    center: view.center,
+                    pixel_step: view.pixel_step,
+                    julia_c: c,
+                    color_scale: params.color_scale,
+                    periodicity_tolerance: params.periodicity_tolerance,
+                    palette,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct JuliaPathPlugin;
+
+impl Plugin for JuliaPathPlugin {
+    fn build(&self, app: &mut App) {
+        // The history plugin loads the saved path with the session; this is the fallback.
+        app.init_resource::<JuliaPath>()
+            .init_resource::<PathMap>()
+            .add_systems(
+                Update,
+                (
+                    // After the timeline, so the path wins over keys on the Julia constant.
+                    julia_path_system.after(timeline_playback_system),
+                    path_map_render_system,
+                    julia_path_ui_system,
+                ),
+            );
+    }
+}
+
+// Move the Julia constant along the path while the animation plays. The materials are only
+// written while a Julia set is shown and c actually moved, so nothing else re-uploads them.
+fn julia_path_system(
+    time: Res<Time>,
+    mut path: ResMut<JuliaPath>,
+    animation: Res<AnimationUpdateToggle>,
+    fractal_type: Res<FractalType>,
+    mut julia_materials: ResMut<Assets<JuliaMaterial>>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    if !path.enabled || !animation.active {
+        return;
+    }
+    let step = time.delta_seconds() / path.loop_seconds.max(f32::EPSILON);
+    path.phase = (path.phase + step).rem_euclid(1.0);
+    if *fractal_type != FractalType::Julia {
+        return;
+    }
+    let Some(c) = path.point_at(path.phase) else {
+        return;
+    };
+    redraw.send(RequestRedraw);
+    edit_materials(&mut julia_materials, |material| material.c = c);
+}
+
+// The Mandelbrot set image the path is drawn over.
+#[derive(Resource, Default)]
+struct PathMap {
+    task: Option<Task<Result<Vec<u8>, String>>>,
+    texture: Option<egui::TextureHandle>,
+    started: bool,
+}
+
+// Render the map once, on the CPU.
+fn path_map_render_system(mut map: ResMut<PathMap>, mut ctx: EguiContexts) {
+    if !map.started {
+        map.started = true;
+        map.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            let colormap = load_colormap(PALETTES[0])?;
+            let params = KernelParams {
+                max_iterations: MAP_MAX_ITERATIONS,
+                ..LOOP_EXPORT_PARAMS
+            };
+            Ok(render_rgba8(
+                Kernel::Mandelbrot,
+                &params,
+                &colormap,
+                MAP_CENTER,
+                MAP_SPAN / MAP_SIZE as f32,
+                MAP_SIZE,
+                MAP_SIZE,
+            ))
+        }));
+    }
+    let Some(task) = map.task.as_mut() else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    map.task = None;
+    match result {
+        Ok(pixels) => {
+            let image = egui::ColorImage::from_rgba_unmultiplied(
+                [MAP_SIZE as usize, MAP_SIZE as usize],
+                &pixels,
+            );
+            map.texture = Some(ctx.ctx_mut().load_texture(
+                "julia_path_map",
+                image,
+                egui::TextureOptions::LINEAR,
+            ));
+        }
+        Err(err) => error!("Could not render the Julia path map: {err}"),
+    }
+}
+
+// The Julia Path window: pick or draw the path, preview where c is and export a loop.
+#[allow(clippy::too_many_arguments)]
+fn julia_path_ui_system(
+    mut ctx: EguiContexts,
+    mut path: ResMut<JuliaPath>,
+    map: Res<PathMap>,
+    materials: FractalMaterials,
+    mut export_state: ResMut<ExportState>,
+    export_settings: Res<ExportSettings>,
+    cameras: Query<(&Camera, &OrthographicProjection, &Transform), With<PanCamState>>,
+    progressive_settings: Res<ProgressiveSettings>,
+    // The path before the stroke being drawn, put back if the stroke is too short.
+    mut stroke_start: Local<Option<(PathShape, Vec<Vec2>)>>,
+) {
+    // Widgets edit a copy, so the path only counts as changed on real edits.
+    let mut edited = path.clone();
+    let current_c = materials
+        .active(&FractalType::Julia)
+        .map(|params| params.julia_c);
+    let mut start_export = false;
+
+    egui::Window::new("Julia Path")
+        .default_open(false)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.checkbox(&mut edited.enabled, "Move c along the path");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut edited.shape, PathShape::Cardioid, "Main cardioid");
+                ui.radio_value(&mut edited.shape, PathShape::Circle, "Circle");
+                ui.radio_value(&mut edited.shape, PathShape::Freehand, "Freehand");
+            });
+            if edited.shape == PathShape::Circle {
+                ui.horizontal(|ui| {
+                    ui.label("Center:");
+                    ui.add(egui::DragValue::new(&mut edited.circle_center.x).speed(0.005));
+                    ui.add(egui::DragValue::new(&mut edited.circle_center.y).speed(0.005));
+                    ui.label("Radius:");
+                    ui.add(
+                        egui::DragValue::new(&mut edited.circle_radius)
+                            .speed(0.002)
+                            .clamp_range(0.001..=2.0),
+                    );
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.label("Loop:");
+                ui.add(
+                    egui::DragValue::new(&mut edited.loop_seconds)
+                        .speed(0.5)
+                        .clamp_range(1.0..=600.0)
+                        .suffix(" s"),
+                );
+            });
+
+            let size = MAP_SIZE as f32;
+            let (rect, response) =
+                ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::drag());
+            let painter = ui.painter_at(rect);
+            match &map.texture {
+                Some(texture) => painter.image(
+                    texture.id(),
+                    rect,
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    egui::Color32::WHITE,
+                ),
+                None => painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20)),
+            };
+            // Rows run towards positive imaginary values, like the map render and the shaders.
+            let to_map = |c: Vec2| {
+                rect.center()
+                    + egui::vec2(c.x - MAP_CENTER[0], c.y - MAP_CENTER[1]) * (size / MAP_SPAN)
+            };
+            let to_plane = |point: egui::Pos2| {
+                let offset = (point - rect.center()) * (MAP_SPAN / size);
+                Vec2::new(MAP_CENTER[0] + offset.x, MAP_CENTER[1] + offset.y)
+            };
+
+            // Drawing on the map replaces the path with a freehand one.
+            if let Some(pointer) = response.interact_pointer_pos() {
+                if response.drag_started() {
+                    *stroke_start = Some((edited.shape, edited.freehand.clone()));
+                    edited.shape = PathShape::Freehand;
+                    edited.freehand.clear();
+                }
+                let point = to_plane(pointer);
+                let far_enough = edited
+                    .freehand
+                    .last()
+                    .map_or(true, |last| last.distance(point) >= MIN_FREEHAND_SPACING);
+                if response.dragged() && far_enough {
+                    edited.freehand.push(point);
+                }
+            }
+            if response.drag_released() {
+                if let Some((shape, freehand)) = stroke_start.take() {
+                    if edited.freehand.len() < 3 {
+                        edited.shape = shape;
+                        edited.freehand = freehand;
+                    }
+                }
+            }
+
+            let outline: Vec<egui::Pos2> = edited.outline().into_iter().map(to_map).collect();
+            if outline.len() >= 2 {
+                painter.add(egui::Shape::closed_line(
+                    outline,
+                    egui::Stroke::new(1.5, egui::Color32::WHITE),
+                ));
+            }
+            // Live preview of where c is now.
+            let marker = if edited.enabled {
+                edited.point_at(edited.phase)
+            } else {
+                current_c
+            };
+            if let Some(c) = marker {
+                painter.circle(
+                    to_map(c),
+                    4.0,
+                    egui::Color32::from_rgb(230, 80, 60),
+                    egui::Stroke::new(1.0, egui::Color32::WHITE),
+                );
+                ui.label(format!("c = {:.4} {:+.4}i", c.x, c.y));
+            }
+            ui.label("Drag on the map to draw a path.");
+
+            ui.separator();
+            match export_state.progress() {
+                Some((done, total)) => {
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                            .text(format!("Exporting image {done} of {total}")),
+                    );
+                }
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.label("Frames:");
+                        ui.add(egui::DragValue::new(&mut edited.loop_frames).clamp_range(2..=3600));
+                        start_export = ui
+                            .button("Export loop")
+                            .on_hover_text(
+                                "Render the Julia set at the current view for every step around \
+                                 the path, as numbered PNGs that loop seamlessly",
+                            )
+                            .clicked();
+                    });
+                }
+            }
+        });
+
+    if start_export {
+        let params = materials
+            .active(&FractalType::Julia)
+            .map_or(LOOP_EXPORT_PARAMS, |params| KernelParams {
+                color_scale: params.color_scale,
+                max_iterations: params.max_iterations,
+                periodicity_tolerance: params.periodicity_tolerance,
+            });
+        let palette = progressive_settings.palette;
+        match cameras.get_single() {
+            Ok((camera, proj, transform)) => {
+                if let Some(view) = SequenceView::of_camera(camera, proj, transform) {
+                    export_state.request_sequence(
+                        "julia-loop",
+                        edited.loop_sequence(params, palette, view),
+                        &export_settings,
+                    );
+                }
+            }
+            Err(err) => error!("Could not export a Julia loop: {err}"),
+        }
+    }
+    if edited.enabled != path.enabled
+        || edited.shape != path.shape
+        || edited.circle_center != path.circle_center
+        || edited.circle_radius != path.circle_radius
+        || edited.freehand != path.freehand
+        || edited.loop_seconds != path.loop_seconds
+        || edited.loop_frames != path.loop_frames
+    {
+        edited.dirty = true;
+        *path = edited;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle_path_moves_at_constant_speed() {
+        let path = JuliaPath {
+            shape: PathShape::Circle,
+            circle_center: Vec2::new(-1.0, 0.0),
+            circle_radius: 0.25,
+            ..default()
+        };
+        let quarter = path.point_at(0.25).unwrap();
+        assert!(quarter.distance(Vec2::new(-1.0, 0.25)) < 1e-3);
+        // Past the end wraps back around.
+        let wrapped = path.point_at(1.25).unwrap();
+        assert!(wrapped.distance(quarter) < 1e-5);
+    }
+
+    #[test]
+    fn freehand_path_closes_on_itself() {
+        let path = JuliaPath {
+            shape: PathShape::Freehand,
+            freehand: vec![Vec2::ZERO, Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)],
+            ..default()
+        };
+        // Sides of length 1, 1 and sqrt(2): halfway round is partway up the second side.
+        let halfway = path.point_at(0.5).unwrap();
+        let expected = Vec2::new(1.0, (2.0 + 2f32.sqrt()) / 2.0 - 1.0);
+        assert!(halfway.distance(expected) < 1e-5);
+        assert!(path.point_at(0.0).unwrap().distance(Vec2::ZERO) < 1e-6);
+        assert!(path.point_at(1.0).unwrap().distance(Vec2::ZERO) < 1e-6);
+    }
+
+    #[test]
+    fn loop_frames_pin_the_julia_set_at_the_view() {
+        let path = JuliaPath {
+            loop_frames: 4,
+            ..default()
+        };
+        let view = SequenceView {
+            center: Vec2::new(0.1, -0.2),
+            pixel_step: 0.004,
+        };
+        let frames = path.loop_sequence(LOOP_EXPORT_PARAMS, 2, view);
+        assert_eq!(frames.len(), 4);
+        for (index, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.fractal, FractalType::Julia.shader_index());
+            assert_eq!(
+                (frame.center, frame.pixel_step),
+                (view.center, view.pixel_step)
+            );
+            assert_eq!(frame.palette, 2);
+            let expected = path.point_at(index as f32 / 4.0).unwrap();
+            assert!(frame.julia_c.distance(expected) < 1e-6);
+        }
+        // The loop stops a step short of where it started.
+        assert!(frames[3].julia_c.distance(frames[0].julia_c) > 1e-3);
+    }
+}
@@ -0,0 +1,489 @@
+// CPU ports of the escape-time fragment shaders. They follow the WGSL line by line in f32 so the
+// output tracks what the GPU draws, and use nothing from the engine so they run without a GPU.
+// Nothing checks them against the shaders: the golden images in tests/golden are rendered from
+// these ports, so shader changes have to be carried over by hand.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kernel {
+    Mandelbrot,
+    Julia { c: [f32; 2] },
+    BurningShip,
+}
+
+// The uniforms every fragment shader shares.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KernelParams {
+    pub color_scale: f32,
+    pub max_iterations: f32,
+    pub periodicity_tolerance: f32,
+}
+
+// Colormap texture decoded to linear RGBA, sampled like the default linear, clamp-to-edge sampler.
+pub struct Colormap {
+    width: usize,
+    height: usize,
+    texels: Vec<[f32; 4]>,
+}
+
+impl Colormap {
+    // Build from sRGB encoded RGBA8 pixels, the way an Rgba8UnormSrgb texture is read.
+    pub fn from_srgba8(width: u32, height: u32, data: &[u8]) -> Self {
+        let texels = data
+            .chunks_exact(4)
+            .map(|texel| {
+                [
+                    srgb_to_linear(texel[0]),
+                    srgb_to_linear(texel[1]),
+                    srgb_to_linear(texel[2]),
+                    texel[3] as f32 / 255.0,
+                ]
+            })
+            .collect();
+        Colormap {
+            width: width.max(1) as usize,
+            height: height.max(1) as usize,
+            texels,
+        }
+    }
+
+    // Bilinear sample at normalized coordinates.
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let texel = |x: f32, y: f32| {
+            let x = (x.max(0.0) as usize).min(self.width - 1);
+            let y = (y.max(0.0) as usize).min(self.height - 1);
+            self.texels[y * self.width + x]
+        };
+        let top = mix(texel(x0, y0), texel(x0 + 1.0, y0), fx);
+        let bottom = mix(texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0), fx);
+        mix(top, bottom, fy)
+    }
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [0, 1, 2, 3].map(|i| a[i] * (1.0 - t) + b[i] * t)
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Encode a linear color channel for an sRGB render target.
+pub fn linear_to_srgb8(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let encoded = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+fn step(edge: f32, x: f32) -> f32 {
+    if x < edge {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// Interior color shared by the fragment shaders.
+fn interior_color(period: f32, colormap: &Colormap) -> [f32; 4] {
+    if period > 0.0 {
+        let period_color = colormap.sample((period * 0.1).fract(), 0.5);
+        [
+            period_color[0] * 0.2,
+            period_color[1] * 0.2,
+            period_color[2] * 0.2,
+            1.0,
+        ]
+    } else {
+        [0.0, 0.0, 0.0, 1.0]
+    }
+}
+
+// Brent-style periodicity detection state, mirroring the shaders.
+struct Periodicity {
+    saved_z: [f32; 2],
+    window: f32,
+    step: f32,
+    tolerance_squared: f32,
+}
+
+impl Periodicity {
+    fn new(z: [f32; 2], tolerance: f32) -> Self {
+        Periodicity {
+            saved_z: z,
+            window: 1.0,
+            step: 0.0,
+            tolerance_squared: tolerance * tolerance,
+        }
+    }
+
+    // Returns the period once the orbit comes back to the saved point.
+    fn check(&mut self, z: [f32; 2]) -> Option<f32> {
+        self.step += 1.0;
+        let drift = [z[0] - self.saved_z[0], z[1] - self.saved_z[1]];
+        if drift[0] * drift[0] + drift[1] * drift[1] < self.tolerance_squared {
+            return Some(self.step);
+        }
+        if self.step >= self.window {
+            self.saved_z = z;
+            self.step = 0.0;
+            self.window *= 2.0;
+        }
+        None
+    }
+}
+
+// Where an orbit ended up. `iterations` is the kernel's escape count, which is max_iterations for
+// points found to be inside; `z` is the last value computed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Escape {
+    pub iterations: f32,
+    pub period: f32,
+    pub z: [f32; 2],
+    pub escaped: bool,
+}
+
+impl Escape {
+    pub fn magnitude(&self) -> f32 {
+        (self.z[0] * self.z[0] + self.z[1] * self.z[1]).sqrt()
+    }
+
+    // Continuous iteration count, the way the Julia and Burning Ship shaders smooth it from the
+    // first point past radius 2. smooth_escape gives a smoother one.
+    pub fn smooth_iterations(&self) -> f32 {
+        if self.escaped {
+            smooth_count(self.iterations, self.z)
+        } else {
+            self.iterations
+        }
+    }
+}
+
+// Squared radius an escaped orbit is followed out to before smoothing its count.
+const SMOOTHING_BAILOUT_SQUARED: f32 = 65536.0;
+
+fn smooth_count(iterations: f32, z: [f32; 2]) -> f32 {
+    let radius_squared = z[0] * z[0] + z[1] * z[1];
+    iterations + 1.0 - radius_squared.ln().log2()
+}
+
+fn outside_radius(z: [f32; 2]) -> bool {
+    z[0] * z[0] + z[1] * z[1] > 4.0
+}
+
+// The escape test of a kernel's shader: mandelbrot_fragment.wgsl stops once either coordinate
+// leaves -2..2, the others once z leaves radius 2. iteration_stats.wgsl repeats it.
+fn has_escaped(kernel: Kernel, z: [f32; 2]) -> bool {
+    match kernel {
+        Kernel::Mandelbrot => z[0].abs() > 2.0 || z[1].abs() > 2.0,
+        Kernel::Julia { .. } | Kernel::BurningShip => outside_radius(z),
+    }
+}
+
+// Starting z and the constant added each step for a kernel iterating `position`.
+fn orbit_start(kernel: Kernel, position: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+    match kernel {
+        Kernel::Mandelbrot => ([0.0, 0.0], position),
+        Kernel::Julia { c } => (position, c),
+        Kernel::BurningShip => (position, position),
+    }
+}
+
+fn orbit_step(kernel: Kernel, z: [f32; 2], c: [f32; 2]) -> [f32; 2] {
+    let z = if kernel == Kernel::BurningShip {
+        [z[0].abs(), z[1].abs()]
+    } else {
+        z
+    };
+    [z[0] * z[0] - z[1] * z[1] + c[0], 2.0 * z[0] * z[1] + c[1]]
+}
+
+// Continuous escape count of `position`. The log adjustment only lines up from one iteration
+// count to the next when the orbit is followed well past escaping, so this keeps iterating an
+// escaped orbit out to SMOOTHING_BAILOUT_SQUARED rather than stopping at the kernel's own test,
+// which for the Mandelbrot set is a box and makes the value jump by about one near its corners.
+// None if the orbit stays within radius 2 for max_iterations steps.
+pub fn smooth_escape(kernel: Kernel, position: [f32; 2], max_iterations: f32) -> Option<f32> {
+    let (mut z, c) = orbit_start(kernel, position);
+    let mut iteration = 0.0f32;
+    loop {
+        let radius_squared = z[0] * z[0] + z[1] * z[1];
+        if radius_squared > SMOOTHING_BAILOUT_SQUARED {
+            return Some(smooth_count(iteration, z));
+        }
+        if !outside_radius(z) && iteration >= max_iterations {
+            return None;
+        }
+        z = orbit_step(kernel, z, c);
+        iteration += 1.0;
+    }
+}
+
+// Iterate the point `position` (the shaders' uv * 4.0 - 2.0) the way a kernel's shader does.
+pub fn escape(kernel: Kernel, position: [f32; 2], params: &KernelParams) -> Escape {
+    match kernel {
+        Kernel::Mandelbrot => escape_mandelbrot(position, params),
+        Kernel::Julia { c } => escape_julia(position, c, params),
+        Kernel::BurningShip => escape_burning_ship(position, params),
+    }
+}
+
+// The points z_0, z_1, ... a kernel visits from `position`, up to and including the first one
+// its shader counts as escaped, or `max_points` of them. No periodicity check, so cycles run to
+// the end.
+pub fn orbit(kernel: Kernel, position: [f32; 2], max_points: usize) -> Vec<[f32; 2]> {
+    let (mut z, c) = orbit_start(kernel, position);
+    let mut points = vec![z];
+    while points.len() < max_points && !has_escaped(kernel, z) {
+        z = orbit_step(kernel, z, c);
+        points.push(z);
+    }
+    points
+}
+
+// Color of the point `position` (the shaders' uv * 4.0 - 2.0) for a kernel.
+pub fn shade(
+    kernel: Kernel,
+    position: [f32; 2],
+    params: &KernelParams,
+    colormap: &Colormap,
+) -> [f32; 4] {
+    match kernel {
+        Kernel::Mandelbrot => shade_mandelbrot(position, params, colormap),
+        Kernel::Julia { c } => shade_julia(position, c, params, colormap),
+        Kernel::BurningShip => shade_burning_ship(position, params, colormap),
+    }
+}
+
+// Shade a width x height grid of points `pixel_step` apart around `center` into sRGB encoded RGBA8
+// pixels. Rows run towards positive imaginary values, the way the shaders' uv runs down the quad.
+pub fn render_rgba8(
+    kernel: Kernel,
+    params: &KernelParams,
+    colormap: &Colormap,
+    center: [f32; 2],
+    pixel_step: f32,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let position = [
+                center[0] + (x as f32 - width as f32 / 2.0 + 0.5) * pixel_step,
+                center[1] + (y as f32 - height as f32 / 2.0 + 0.5) * pixel_step,
+            ];
+            let color = shade(kernel, position, params, colormap);
+            pixels.extend([
+                linear_to_srgb8(color[0]),
+                linear_to_srgb8(color[1]),
+                linear_to_srgb8(color[2]),
+                (color[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+            ]);
+        }
+    }
+    pixels
+}
+
+// mandelbrot_fragment.wgsl
+fn escape_mandelbrot(c: [f32; 2], params: &KernelParams) -> Escape {
+    let max_iterations = params.max_iterations;
+    let mut z = [0.0f32, 0.0];
+    let mut iteration = 0.0f32;
+    let mut periodicity = Periodicity::new(z, params.periodicity_tolerance);
+    let mut period = 0.0f32;
+
+    let q = (c[0] - 0.25) * (c[0] - 0.25) + c[1] * c[1];
+    if q * (q + (c[0] - 0.25)) < 0.25 * c[1] * c[1]
+        || (c[0] + 1.0) * (c[0] + 1.0) + c[1] * c[1] < 0.0625
+    {
+        iteration = max_iterations;
+    } else {
+        while iteration < max_iterations {
+            let x = (z[0] * z[0] - z[1] * z[1]) + c[0];
+            let y = (2.0 * z[0] * z[1]) + c[1];
+            if has_escaped(Kernel::Mandelbrot, [x, y]) {
+                return Escape {
+                    iterations: iteration,
+                    period,
+                    z: [x, y],
+                    escaped: true,
+                };
+            }
+            z = [x, y];
+            iteration += 1.0;
+            if let Some(found) = periodicity.check(z) {
+                period = found;
+                iteration = max_iterations;
+                break;
+            }
+        }
+    }
+    Escape {
+        iterations: iteration,
+        period,
+        z,
+        escaped: false,
+    }
+}
+
+fn shade_mandelbrot(c: [f32; 2], params: &KernelParams, colormap: &Colormap) -> [f32; 4] {
+    let Escape {
+        iterations: iteration,
+        period,
+        ..
+    } = escape_mandelbrot(c, params);
+    let basic_color = iteration / params.max_iterations;
+    let color = basic_color.powf(0.3) * (1.0 - params.color_scale) + params.color_scale;
+    let condition = step(0.99, color);
+    let colormap_color = colormap.sample(color, 0.5);
+    mix(colormap_color, interior_color(period, colormap), condition)
+}
+
+// julia_fragment.wgsl
+fn escape_julia(start: [f32; 2], c: [f32; 2], params: &KernelParams) -> Escape {
+    let max_iterations = params.max_iterations;
+    let mut z = start;
+    let mut iteration = 0.0f32;
+    let mut periodicity = Periodicity::new(z, params.periodicity_tolerance);
+    let mut period = 0.0f32;
+
+    while iteration < max_iterations {
+        if has_escaped(Kernel::Julia { c }, z) {
+            return Escape {
+                iterations: iteration,
+                period,
+                z,
+                escaped: true,
+            };
+        }
+        let x_squared = z[0] * z[0];
+        let y_squared = z[1] * z[1];
+        let two_xy = 2.0 * z[0] * z[1];
+        z = [x_squared - y_squared + c[0], two_xy + c[1]];
+        iteration += 1.0;
+        if let Some(found) = periodicity.check(z) {
+            period = found;
+            iteration = max_iterations;
+            break;
+        }
+    }
+    Escape {
+        iterations: iteration,
+        period,
+        z,
+        escaped: false,
+    }
+}
+
+fn shade_julia(
+    start: [f32; 2],
+    c: [f32; 2],
+    params: &KernelParams,
+    colormap: &Colormap,
+) -> [f32; 4] {
+    let orbit = escape_julia(start, c, params);
+    let basic_color = orbit.smooth_iterations().max(0.0) / params.max_iterations;
+    let color = basic_color.powf(0.1) * (1.0 - params.color_scale) + params.color_scale;
+    let condition = step(0.99, color);
+    let colormap_color = colormap.sample(color, 0.5);
+    mix(
+        colormap_color,
+        interior_color(orbit.period, colormap),
+        condition,
+    )
+}
+
+// burning_ship_fragment.wgsl
+fn escape_burning_ship(c: [f32; 2], params: &KernelParams) -> Escape {
+    let max_iterations = params.max_iterations;
+    let mut z = c;
+    let mut iteration = 0.0f32;
+    let mut periodicity = Periodicity::new(z, params.periodicity_tolerance);
+    let mut period = 0.0f32;
+
+    while iteration < max_iterations {
+        z = [z[0].abs(), z[1].abs()];
+        if has_escaped(Kernel::BurningShip, z) {
+            break;
+        }
+        let x_squared = z[0] * z[0];
+        let y_squared = z[1] * z[1];
+        let two_xy = 2.0 * z[0] * z[1];
+        z = [x_squared - y_squared + c[0], two_xy + c[1]];
+        iteration += 1.0;
+        if let Some(found) = periodicity.check(z) {
+            period = found;
+            iteration = max_iterations;
+            break;
+        }
+    }
+    Escape {
+        iterations: iteration,
+        period,
+        z,
+        escaped: iteration < max_iterations,
+    }
+}
+
+fn shade_burning_ship(c: [f32; 2], params: &KernelParams, colormap: &Colormap) -> [f32; 4] {
+    let orbit = escape_burning_ship(c, params);
+    let basic_color = orbit.smooth_iterations() / params.max_iterations;
+    let color = basic_color.powf(0.1) * (1.0 - params.color_scale) + params.color_scale;
+    let condition = step(0.99, color);
+    let colormap_color = colormap.sample(color, 0.5);
+    mix(
+        colormap_color,
+        interior_color(orbit.period, colormap),
+        condition,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orbit_ends_where_the_kernel_escapes() {
+        let params = KernelParams {
+            color_scale: 0.0,
+            max_iterations: 100.0,
+            periodicity_tolerance: 0.0,
+        };
+        // 1.5 + 1.5i is outside radius 2 but inside the Mandelbrot shader's box, so the orbit
+        // only stops at the next point.
+        assert_eq!(
+            orbit(Kernel::Mandelbrot, [1.5, 1.5], 100),
+            vec![[0.0, 0.0], [1.5, 1.5], [1.5, 6.0]]
+        );
+        for (kernel, position) in [
+            (Kernel::Mandelbrot, [1.5, 1.5]),
+            (Kernel::Mandelbrot, [-0.75, 0.2]),
+            (Kernel::Julia { c: [-0.8, 0.156] }, [1.2, 0.9]),
+            (Kernel::BurningShip, [-1.7, -0.1]),
+        ] {
+            let escape = escape(kernel, position, &params);
+            assert!(escape.escaped, "{kernel:?} {position:?}");
+            let last = *orbit(kernel, position, 1000).last().unwrap();
+            // The Burning Ship kernel reports z after folding it into the first quadrant.
+            assert_eq!(
+                last.map(f32::abs),
+                escape.z.map(f32::abs),
+                "{kernel:?} {position:?}"
+            );
+        }
+        // Points that never escape run to the limit.
+        assert_eq!(orbit(Kernel::Mandelbrot, [-1.0, 0.0], 50).len(), 50);
+    }
+}
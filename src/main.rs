@@ -8,9 +8,46 @@ use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::sprite::Material2dPlugin;
 use bevy_egui::EguiPlugin;
 
+mod audio;
+use crate::audio::MusicPlugin;
+
+mod auto_iterations;
+use crate::auto_iterations::AutoIterationsPlugin;
+
+mod bindings;
+use crate::bindings::BindingsPlugin;
+
+mod buddhabrot;
+use crate::buddhabrot::BuddhabrotPlugin;
+
+mod export;
+use crate::export::ExportPlugin;
+
 mod fractals;
 use crate::fractals::FractalControlPlugin;
 
+mod gamepad;
+
+mod history;
+use crate::history::HistoryPlugin;
+
+mod hud;
+use crate::hud::HudPlugin;
+
+mod idle;
+use crate::idle::IdleModePlugin;
+
+mod julia_path;
+use crate::julia_path::JuliaPathPlugin;
+
+mod kernels;
+
+mod minimap;
+use crate::minimap::MinimapPlugin;
+
+mod orbit;
+use crate::orbit::OrbitPlugin;
+
 mod pancam;
 use crate::pancam::{PanCamConfig, PanCamPlugin, PanCamState};
 
@@ -23,11 +60,28 @@ use crate::materials::{BurningShipEntity, BurningShipMaterial};
 
 mod prelude;
 
+mod progressive;
+use crate::progressive::ProgressiveRenderPlugin;
+
+mod readback;
+
+mod timeline;
+use crate::timeline::TimelinePlugin;
+
 mod ui;
 use crate::ui::UIPlugin;
 
+mod verify;
+
 // The main function to initialize and run the Bevy app.
 fn main() {
+    // `verify` checks the CPU kernel ports against golden images rendered by those same ports, not
+    // the shaders, and exits without opening a window.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("verify") {
+        std::process::exit(verify::run(args));
+    }
+
     // Initializing the Bevy app and adding various plugins.
     let _app = App::new()
         // Uncomment to set a custom clear color for the renderer.
@@ -42,7 +96,20 @@ fn main() {
         .add_plugins(FrameTimeDiagnosticsPlugin::default()) // Diagnostics for frame time.
         .add_plugins(PanCamPlugin::default()) // Custom camera control plugin.
         .add_plugins(UIPlugin)
+        .add_plugins(BindingsPlugin) // Configurable controls, rebinding window and F1 overlay.
+        .add_plugins(HistoryPlugin) // Back and forward through settled views, kept in session.ron.
+        .add_plugins(MinimapPlugin) // Corner overview that recenters the camera.
+        .add_plugins(HudPlugin) // Coordinate, orbit and magnification readout for the cursor.
+        .add_plugins(OrbitPlugin) // Hold O to trace and plot the orbit under the cursor.
         .add_plugins(FractalControlPlugin)
+        .add_plugins(TimelinePlugin) // Keyframed parameter and camera animation.
+        .add_plugins(JuliaPathPlugin) // Julia constant moving around a closed path, with loop export.
+        .add_plugins(MusicPlugin) // Audio-reactive parameters driven by a local music file.
+        .add_plugins(ProgressiveRenderPlugin) // Compute path that spreads iterations across frames.
+        .add_plugins(ExportPlugin)
+        .add_plugins(AutoIterationsPlugin) // Picks max_iterations from zoom depth and readback stats.
+        .add_plugins(IdleModePlugin) // Optional reactive redraws to save power.
+        .add_plugins(BuddhabrotPlugin) // CPU orbit density renderer.
         .add_systems(Startup, setup) // Setup function called at startup.
         .add_plugins(Material2dPlugin::<MandelbrotMaterial>::default()) // Plugin for 2D materials.
         .add_plugins(Material2dPlugin::<JuliaMaterial>::default()) // Plugin for 2D materials.
@@ -56,7 +123,6 @@ fn setup(mut commands: Commands) {
     commands.spawn((
         Camera2dBundle::default(),
         PanCamConfig {
-            grab_buttons: vec![MouseButton::Left, MouseButton::Middle],
             enabled: true,
             zoom_to_cursor: true,
             min_scale: 0.00012,
@@ -74,6 +140,7 @@ fn setup(mut commands: Commands) {
         },
         PanCamState {
             current_zoom: 0.5,
+            home_zoom: 7.0,
             target_zoom: 7.0,
             is_zooming: true,
             target_translation: None,
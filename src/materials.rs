@@ -1,8 +1,11 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::reflect::{TypePath, TypeUuid};
 use bevy::render::render_resource::{AsBindGroup, ShaderRef};
 use bevy::sprite::Material2d;
 
+use crate::fractals::FractalType;
+
 #[derive(Resource)]
 pub struct MandelbrotEntity(pub Option<Entity>);
 impl Default for MandelbrotEntity {
@@ -15,10 +18,11 @@ impl Default for MandelbrotEntity {
 pub struct MandelbrotUniforms {
     pub color_scale: f32,
     pub max_iterations: f32,
+    pub periodicity_tolerance: f32,
 }
 
 // Mandelbrot material definition. It holds parameters and texture for the Mandelbrot fractal.
-#[derive(Component, Debug, Clone, AsBindGroup, TypeUuid, TypePath, Asset)]
+#[derive(Component, Debug, Clone, PartialEq, AsBindGroup, TypeUuid, TypePath, Asset)]
 #[uuid = "148ef22b-c53e-4bc2-982c-bb2b102e38f8"]
 pub struct MandelbrotMaterial {
     #[uniform(0)]
@@ -31,6 +35,9 @@ pub struct MandelbrotMaterial {
     pub offset: Vec2,
     #[uniform(6)]
     pub global_offset: Vec2,
+    // Distance under which a repeating orbit point counts as a cycle; 0 disables the check.
+    #[uniform(7)]
+    pub periodicity_tolerance: f32,
     #[texture(4)]
     #[sampler(5)]
     colormap_texture: Handle<Image>,
@@ -54,6 +61,7 @@ pub fn prepare_mandelbrot_material(
         zoom: 4.5,
         offset: Vec2 { x: 0.0, y: 0.0 },
         global_offset: Vec2 { x: 0.0, y: 0.0 } / 4.5,
+        periodicity_tolerance: uniforms.periodicity_tolerance,
         colormap_texture: colormap_texture_handle,
     };
     materials.add(material)
@@ -72,10 +80,11 @@ impl Default for JuliaEntity {
 pub struct JuliaUniforms {
     pub color_scale: f32,
     pub max_iterations: f32,
+    pub periodicity_tolerance: f32,
 }
 
 // Julia material definition. It holds parameters and texture for the Julia fractal.
-#[derive(Component, Debug, Clone, AsBindGroup, TypeUuid, TypePath, Asset)]
+#[derive(Component, Debug, Clone, PartialEq, AsBindGroup, TypeUuid, TypePath, Asset)]
 #[uuid = "258ef34b-d54f-4bc3-993b-bc3e203a48f9"]
 pub struct JuliaMaterial {
     #[uniform(0)]
@@ -84,6 +93,8 @@ pub struct JuliaMaterial {
     pub max_iterations: f32,
     #[uniform(2)]
     pub c: Vec2, // Julia constant
+    #[uniform(7)]
+    pub periodicity_tolerance: f32,
     #[texture(4)]
     #[sampler(5)]
     colormap_texture: Handle<Image>,
@@ -104,6 +115,7 @@ pub fn prepare_julia_material(
         color_scale: uniforms.color_scale,
         max_iterations: uniforms.max_iterations,
         c: Vec2 { x: 0.3, y: 0.8 },
+        periodicity_tolerance: uniforms.periodicity_tolerance,
         colormap_texture: colormap_texture_handle,
     };
     materials.add(material)
@@ -122,16 +134,19 @@ impl Default for BurningShipEntity {
 pub struct BurningShipUniforms {
     pub color_scale: f32,
     pub max_iterations: f32,
+    pub periodicity_tolerance: f32,
 }
 
 // Sierpinski Triangle material definition.
-#[derive(Component, Debug, Clone, AsBindGroup, TypeUuid, TypePath, Asset)]
+#[derive(Component, Debug, Clone, PartialEq, AsBindGroup, TypeUuid, TypePath, Asset)]
 #[uuid = "0e17159a-ca90-4cd1-a40e-ab12c9455c11"]
 pub struct BurningShipMaterial {
     #[uniform(0)]
     pub color_scale: f32,
     #[uniform(1)]
     pub max_iterations: f32,
+    #[uniform(7)]
+    pub periodicity_tolerance: f32,
     #[texture(4)]
     #[sampler(5)]
     colormap_texture: Handle<Image>,
@@ -152,7 +167,136 @@ pub fn prepare_burning_ship_material(
     let material = BurningShipMaterial {
         color_scale: uniforms.color_scale,
         max_iterations: uniforms.max_iterations,
+        periodicity_tolerance: uniforms.periodicity_tolerance,
         colormap_texture: colormap_texture_handle,
     };
     materials.add(material)
 }
+
+// Parameters of whichever fractal material is currently on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActiveFractalParams {
+    // Index of the fractal as used by the compute shaders.
+    pub fractal: u32,
+    pub color_scale: f32,
+    pub max_iterations: f32,
+    pub julia_c: Vec2,
+    pub periodicity_tolerance: f32,
+}
+
+// All fractal material assets, for systems that work on whichever one is active.
+#[derive(SystemParam)]
+pub struct FractalMaterials<'w> {
+    pub mandelbrot: ResMut<'w, Assets<MandelbrotMaterial>>,
+    pub julia: ResMut<'w, Assets<JuliaMaterial>>,
+    pub burning_ship: ResMut<'w, Assets<BurningShipMaterial>>,
+}
+
+impl<'w> FractalMaterials<'w> {
+    pub fn active(&self, fractal_type: &FractalType) -> Option<ActiveFractalParams> {
+        let fractal = fractal_type.shader_index();
+        match fractal_type {
+            FractalType::Mandelbrot => {
+                self.mandelbrot
+                    .iter()
+                    .next()
+                    .map(|(_, m)| ActiveFractalParams {
+                        fractal,
+                        color_scale: m.color_scale,
+                        max_iterations: m.max_iterations,
+                        julia_c: Vec2::ZERO,
+                        periodicity_tolerance: m.periodicity_tolerance,
+                    })
+            }
+            FractalType::Julia => self.julia.iter().next().map(|(_, m)| ActiveFractalParams {
+                fractal,
+                color_scale: m.color_scale,
+                max_iterations: m.max_iterations,
+                julia_c: m.c,
+                periodicity_tolerance: m.periodicity_tolerance,
+            }),
+            FractalType::BurningShip => {
+                self.burning_ship
+                    .iter()
+                    .next()
+                    .map(|(_, m)| ActiveFractalParams {
+                        fractal,
+                        color_scale: m.color_scale,
+                        max_iterations: m.max_iterations,
+                        julia_c: Vec2::ZERO,
+                        periodicity_tolerance: m.periodicity_tolerance,
+                    })
+            }
+        }
+    }
+
+    // Put the parameters of a previously active fractal back on its materials. Only materials
+    // that differ are written, so calling it every frame costs no re-uploads.
+    pub fn restore(&mut self, fractal_type: &FractalType, params: &ActiveFractalParams) {
+        match fractal_type {
+            FractalType::Mandelbrot => edit_materials(&mut self.mandelbrot, |material| {
+                material.color_scale = params.color_scale;
+                material.max_iterations = params.max_iterations;
+                material.periodicity_tolerance = params.periodicity_tolerance;
+            }),
+            FractalType::Julia => edit_materials(&mut self.julia, |material| {
+                material.color_scale = params.color_scale;
+                material.max_iterations = params.max_iterations;
+                material.periodicity_tolerance = params.periodicity_tolerance;
+                material.c = params.julia_c;
+            }),
+            FractalType::BurningShip => edit_materials(&mut self.burning_ship, |material| {
+                material.color_scale = params.color_scale;
+                material.max_iterations = params.max_iterations;
+                material.periodicity_tolerance = params.periodicity_tolerance;
+            }),
+        }
+    }
+
+    // Draw every fractal material with the given colormap.
+    pub fn set_colormap(&mut self, colormap: &Handle<Image>) {
+        edit_materials(&mut self.mandelbrot, |material| {
+            material.colormap_texture = colormap.clone();
+        });
+        edit_materials(&mut self.julia, |material| {
+            material.colormap_texture = colormap.clone();
+        });
+        edit_materials(&mut self.burning_ship, |material| {
+            material.colormap_texture = colormap.clone();
+        });
+    }
+
+    // Set the iteration cap on every fractal material.
+    pub fn set_max_iterations(&mut self, max_iterations: f32) {
+        edit_materials(&mut self.mandelbrot, |material| {
+            material.max_iterations = max_iterations;
+        });
+        edit_materials(&mut self.julia, |material| {
+            material.max_iterations = max_iterations;
+        });
+        edit_materials(&mut self.burning_ship, |material| {
+            material.max_iterations = max_iterations;
+        });
+    }
+}
+
+// Apply an edit to every material of one kind, writing back only those it actually changes.
+// Mutable access marks a material modified, which re-uploads it and wakes idle mode.
+pub fn edit_materials<M: Asset + Clone + PartialEq>(
+    materials: &mut Assets<M>,
+    mut edit: impl FnMut(&mut M),
+) {
+    let ids: Vec<_> = materials.ids().collect();
+    for id in ids {
+        let Some(material) = materials.get(id) else {
+            continue;
+        };
+        let mut edited = material.clone();
+        edit(&mut edited);
+        if edited != *material {
+            if let Some(material) = materials.get_mut(id) {
+                *material = edited;
+            }
+        }
+    }
+}
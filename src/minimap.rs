@@ -0,0 +1,317 @@
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy_egui::{egui, EguiContexts};
+use futures_lite::future;
+
+use crate::bindings::{Action, ActionInput};
+use crate::fractals::{FractalType, FRACTAL_QUAD_SIZE, FRACTAL_QUAD_Y};
+use crate::kernels::{render_rgba8, KernelParams};
+use crate::materials::FractalMaterials;
+use crate::pancam::PanCamState;
+use crate::progressive::{ProgressiveSettings, PALETTES};
+use crate::verify::load_colormap;
+
+// Side of the minimap in points, and of the image rendered for it in pixels.
+const MINIMAP_SIZE: u32 = 160;
+// The overview is small, so a low iteration cap is plenty.
+const MINIMAP_MAX_ITERATIONS: f32 = 300.0;
+// Shortest time between re-renders while the Julia constant keeps changing, in seconds.
+const MINIMAP_REFRESH_INTERVAL: f32 = 0.5;
+// A view smaller than this many points on the minimap is shown as a marker instead.
+const MIN_VIEW_RECT_SIZE: f32 = 6.0;
+
+// What the overview image depends on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct OverviewKey {
+    fractal: FractalType,
+    julia_c: Vec2,
+    palette: usize,
+}
+
+#[derive(Resource)]
+pub struct MinimapSettings {
+    pub visible: bool,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        MinimapSettings { visible: true }
+    }
+}
+
+// The overview image and the render producing the next one.
+#[derive(Resource, Default)]
+struct Overview {
+    // Key of the image shown, or being rendered.
+    key: Option<OverviewKey>,
+    task: Option<Task<Result<Vec<u8>, String>>>,
+    texture: Option<egui::TextureHandle>,
+    // Seconds since startup when the last render began.
+    started_at: f32,
+}
+
+#[derive(Default)]
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Overview>()
+            .init_resource::<MinimapSettings>()
+            .add_systems(
+                Update,
+                (
+                    minimap_toggle_system,
+                    overview_render_system,
+                    minimap_system,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn minimap_toggle_system(actions: ActionInput, mut settings: ResMut<MinimapSettings>) {
+    if actions.just_pressed(Action::ToggleMinimap) {
+        settings.visible = !settings.visible;
+    }
+}
+
+// The minimap shows the fractal quad, with y flipped to screen space.
+fn to_minimap(rect: egui::Rect, world: Vec2) -> egui::Pos2 {
+    let u = world.x / FRACTAL_QUAD_SIZE + 0.5;
+    let v = 0.5 - (world.y - FRACTAL_QUAD_Y) / FRACTAL_QUAD_SIZE;
+    rect.min + egui::vec2(u * rect.width(), v * rect.height())
+}
+
+fn to_world(rect: egui::Rect, point: egui::Pos2) -> Vec2 {
+    let offset = point - rect.min;
+    Vec2::new(
+        (offset.x / rect.width() - 0.5) * FRACTAL_QUAD_SIZE,
+        (0.5 - offset.y / rect.height()) * FRACTAL_QUAD_SIZE + FRACTAL_QUAD_Y,
+    )
+}
+
+// Render the whole quad on the CPU whenever the fractal, palette or Julia constant changes.
+fn overview_render_system(
+    mut overview: ResMut<Overview>,
+    mut ctx: EguiContexts,
+    settings: Res<MinimapSettings>,
+    fractal_type: Res<FractalType>,
+    materials: FractalMaterials,
+    progressive_settings: Res<ProgressiveSettings>,
+    time: Res<Time>,
+) {
+    if let Some(task) = overview.task.as_mut() {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return;
+        };
+        overview.task = None;
+        match result {
+            Ok(pixels) => {
+                let image = egui::ColorImage::from_rgba_unmultiplied(
+                    [MINIMAP_SIZE as usize, MINIMAP_SIZE as usize],
+                    &pixels,
+                );
+                match overview.texture.as_mut() {
+                    Some(texture) => texture.set(image, egui::TextureOptions::LINEAR),
+                    None => {
+                        overview.texture = Some(ctx.ctx_mut().load_texture(
+                            "minimap",
+                            image,
+                            egui::TextureOptions::LINEAR,
+                        ))
+                    }
+                }
+            }
+            Err(err) => error!("Could not render the minimap: {err}"),
+        }
+    }
+
+    // A hidden minimap catches up once it is shown again.
+    if !settings.visible {
+        return;
+    }
+    let Some(params) = materials.active(&fractal_type) else {
+        return;
+    };
+    let key = OverviewKey {
+        fractal: *fractal_type,
+        julia_c: params.julia_c,
+        palette: progressive_settings.palette,
+    };
+    let now = time.elapsed_seconds();
+    let stale = overview.key.map_or(true, |shown| {
+        shown.fractal != key.fractal
+            || shown.palette != key.palette
+            || (shown.julia_c != key.julia_c
+                && now - overview.started_at >= MINIMAP_REFRESH_INTERVAL)
+    });
+    if !stale {
+        return;
+    }
+    overview.key = Some(key);
+    overview.started_at = now;
+    let params = KernelParams {
+        color_scale: params.color_scale,
+        max_iterations: params.max_iterations.min(MINIMAP_MAX_ITERATIONS),
+        periodicity_tolerance: params.periodicity_tolerance,
+    };
+    overview.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let colormap = load_colormap(PALETTES.get(key.palette).copied().unwrap_or(PALETTES[0]))?;
+        // The quad spans -2..2 on both axes.
+        Ok(render_rgba8(
+            key.fractal.kernel(key.julia_c),
+            &params,
+            &colormap,
+            [0.0, 0.0],
+            4.0 / MINIMAP_SIZE as f32,
+            MINIMAP_SIZE,
+            MINIMAP_SIZE,
+        ))
+    }));
+}
+
+// Corner overview of the whole fractal with the camera's view outlined. Clicking it eases the
+// camera there and dragging moves the view along.
+fn minimap_system(
+    mut ctx: EguiContexts,
+    overview: Res<Overview>,
+    mut settings: ResMut<MinimapSettings>,
+    mut cameras: Query<(&mut PanCamState, &OrthographicProjection, &mut Transform)>,
+    // Offset from the pointer to the view center in world units while dragging.
+    mut grab: Local<Option<Vec2>>,
+) {
+    let Ok((mut cam, proj, mut transform)) = cameras.get_single_mut() else {
+        return;
+    };
+    if !settings.visible {
+        *grab = None;
+        return;
+    }
+    let (mut jump, mut drag) = (None, None);
+    let mut visible = true;
+    egui::Window::new("Minimap")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .resizable(false)
+        .open(&mut visible)
+        .show(ctx.ctx_mut(), |ui| {
+            let size = MINIMAP_SIZE as f32;
+            let (rect, response) =
+                ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click_and_drag());
+            let painter = ui.painter_at(rect);
+            match &overview.texture {
+                Some(texture) => painter.image(
+                    texture.id(),
+                    rect,
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    egui::Color32::WHITE,
+                ),
+                None => painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20)),
+            };
+
+            let to_minimap = |world: Vec2| to_minimap(rect, world);
+            let to_world = |point: egui::Pos2| to_world(rect, point);
+
+            let center = transform.translation.truncate();
+            let half = proj.area.size() / 2.0;
+            let corners: Vec<egui::Pos2> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .into_iter()
+                .map(|(x, y)| {
+                    let corner = transform.rotation * Vec3::new(x * half.x, y * half.y, 0.0);
+                    to_minimap(center + corner.truncate())
+                })
+                .collect();
+            let bounds = egui::Rect::from_points(&corners);
+            let stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+            if bounds.width().max(bounds.height()) >= MIN_VIEW_RECT_SIZE {
+                painter.add(egui::Shape::closed_line(corners, stroke));
+            } else {
+                // Too deep for a rectangle: mark the spot and say how deep.
+                let marker = to_minimap(center);
+                painter.circle_stroke(marker, 4.0, stroke);
+                painter.line_segment(
+                    [marker - egui::vec2(7.0, 0.0), marker + egui::vec2(7.0, 0.0)],
+                    stroke,
+                );
+                painter.line_segment(
+                    [marker - egui::vec2(0.0, 7.0), marker + egui::vec2(0.0, 7.0)],
+                    stroke,
+                );
+                let align = if marker.x > rect.center().x {
+                    egui::Align2::RIGHT_BOTTOM
+                } else {
+                    egui::Align2::LEFT_BOTTOM
+                };
+                let label_offset = if marker.x > rect.center().x {
+                    -8.0
+                } else {
+                    8.0
+                };
+                painter.text(
+                    marker + egui::vec2(label_offset, -6.0),
+                    align,
+                    format!("{:.2e}x", cam.magnification(proj.scale)),
+                    egui::FontId::proportional(12.0),
+                    egui::Color32::WHITE,
+                );
+            }
+
+            if let Some(pointer) = response.interact_pointer_pos() {
+                if response.drag_started() {
+                    // Grabbing the view rectangle keeps it under the pointer; anywhere else
+                    // centers the view on the pointer.
+                    let grabbed = bounds.expand(4.0).contains(pointer);
+                    *grab = Some(if grabbed {
+                        center - to_world(pointer)
+                    } else {
+                        Vec2::ZERO
+                    });
+                }
+                if response.dragged() {
+                    drag = grab.map(|offset| to_world(pointer) + offset);
+                }
+                if response.clicked() {
+                    jump = Some(to_world(pointer));
+                }
+            }
+            if response.drag_released() {
+                *grab = None;
+            }
+        });
+    // The window's close button hides it like the binding does.
+    if !visible {
+        settings.visible = false;
+    }
+
+    if let Some(position) = drag {
+        // Follow the pointer directly and drop any animation that would pull the view back.
+        transform.translation = position.extend(transform.translation.z);
+        cam.target_translation = None;
+        cam.target_zoom = proj.scale;
+        cam.is_zooming = false;
+        cam.target_locked = false;
+        cam.stop_glide();
+    } else if let Some(position) = jump {
+        cam.animate_to(position.extend(transform.translation.z), proj.scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimap_and_world_positions_round_trip() {
+        let rect = egui::Rect::from_min_size(egui::pos2(40.0, 25.0), egui::vec2(160.0, 160.0));
+        for world in [
+            Vec2::new(0.0, FRACTAL_QUAD_Y),
+            Vec2::new(-0.3, 0.2) * FRACTAL_QUAD_SIZE,
+            Vec2::new(0.45, -0.1) * FRACTAL_QUAD_SIZE + Vec2::Y * FRACTAL_QUAD_Y,
+        ] {
+            let back = to_world(rect, to_minimap(rect, world));
+            assert!((back - world).length() < FRACTAL_QUAD_SIZE * 1e-5);
+        }
+        // The quad's top left corner is the minimap's.
+        let corner = Vec2::new(-0.5, 0.5) * FRACTAL_QUAD_SIZE + Vec2::Y * FRACTAL_QUAD_Y;
+        assert!((to_minimap(rect, corner) - rect.min).length() < 1e-3);
+    }
+}
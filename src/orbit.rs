@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use egui_plot::{Line, Plot, PlotPoints};
+
+use crate::bindings::{Action, ActionInput};
+use crate::fractals::{complex_to_world, world_to_complex, FractalType};
+use crate::kernels::{orbit, Kernel};
+use crate::materials::FractalMaterials;
+
+// Longest orbit traced, whatever max_iterations is set to.
+const MAX_ORBIT_POINTS: usize = 1000;
+// Height of each plot in the orbit window, in points.
+const PLOT_HEIGHT: f32 = 120.0;
+
+// How orbits are traced.
+#[derive(Resource)]
+pub struct OrbitSettings {
+    // On the Mandelbrot set, trace the critical orbit z_0 = 0 of the hovered c, the one the shader
+    // iterates. Off traces the hovered point as z_0 under the Julia constant instead.
+    pub critical_orbit: bool,
+}
+
+impl Default for OrbitSettings {
+    fn default() -> Self {
+        OrbitSettings {
+            critical_orbit: true,
+        }
+    }
+}
+
+// The orbit being shown, in complex plane coordinates.
+#[derive(Resource, Default)]
+struct TracedOrbit {
+    start: Option<Vec2>,
+    points: Vec<Vec2>,
+}
+
+#[derive(Default)]
+pub struct OrbitPlugin;
+
+impl Plugin for OrbitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OrbitSettings>()
+            .init_resource::<TracedOrbit>()
+            .add_systems(Update, (trace_orbit_system, orbit_window_system).chain());
+
+        // Without gizmos the orbit still shows in the plots.
+        if app.is_plugin_added::<bevy::gizmos::GizmoPlugin>() {
+            app.add_systems(PostUpdate, draw_orbit_system);
+        }
+    }
+}
+
+// While the binding is held, trace the orbit of the point under the cursor. The pointer moving
+// onto the orbit window keeps the last point, so the window can be used without losing it.
+#[allow(clippy::too_many_arguments)]
+fn trace_orbit_system(
+    actions: ActionInput,
+    mut traced: ResMut<TracedOrbit>,
+    settings: Res<OrbitSettings>,
+    mut ctx: EguiContexts,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    fractal_type: Res<FractalType>,
+    materials: FractalMaterials,
+) {
+    if !actions.pressed(Action::ShowOrbit) {
+        *traced = TracedOrbit::default();
+        return;
+    }
+    if !ctx.ctx_mut().is_pointer_over_area() {
+        let cursor = windows.get_single().ok().and_then(Window::cursor_position);
+        if let (Some(cursor), Ok((camera, camera_transform))) = (cursor, cameras.get_single()) {
+            if let Some(world) = camera.viewport_to_world_2d(camera_transform, cursor) {
+                traced.start = Some(world_to_complex(world));
+            }
+        }
+    }
+    let (Some(start), Some(params)) = (traced.start, materials.active(&fractal_type)) else {
+        traced.points.clear();
+        return;
+    };
+    let kernel = match *fractal_type {
+        FractalType::Mandelbrot if !settings.critical_orbit => Kernel::Julia {
+            c: params.julia_c.to_array(),
+        },
+        _ => fractal_type.kernel(params.julia_c),
+    };
+    let max_points = (params.max_iterations as usize + 1).clamp(2, MAX_ORBIT_POINTS);
+    traced.points = orbit(kernel, start.to_array(), max_points)
+        .into_iter()
+        .map(Vec2::from)
+        .collect();
+}
+
+// Draw the orbit over the fractal, with a ring around its first point.
+fn draw_orbit_system(
+    traced: Res<TracedOrbit>,
+    cameras: Query<&OrthographicProjection>,
+    mut gizmos: Gizmos,
+) {
+    let Some(first) = traced.points.first() else {
+        return;
+    };
+    let points: Vec<Vec2> = traced
+        .points
+        .iter()
+        .copied()
+        .map(complex_to_world)
+        .collect();
+    let ring_radius = cameras.get_single().map_or(1.0, |proj| proj.scale) * 5.0;
+    gizmos.circle_2d(complex_to_world(*first), ring_radius, Color::ORANGE);
+    gizmos.linestrip_2d(points, Color::YELLOW);
+}
+
+// Plot |z_n| and arg(z_n) against n while an orbit is shown.
+fn orbit_window_system(
+    mut ctx: EguiContexts,
+    traced: Res<TracedOrbit>,
+    mut settings: ResMut<OrbitSettings>,
+    fractal_type: Res<FractalType>,
+) {
+    let Some(start) = traced.start else {
+        return;
+    };
+    if traced.points.is_empty() {
+        return;
+    }
+    egui::Window::new("Orbit")
+        .default_pos(egui::pos2(10.0, 120.0))
+        .resizable(false)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.label(format!("From {:.6} {:+.6}i", start.x, start.y));
+            let last = traced.points[traced.points.len() - 1];
+            if last.length_squared() > 4.0 {
+                ui.label(format!("Escapes at n = {}", traced.points.len() - 1));
+            } else {
+                ui.label(format!("Bounded for {} steps", traced.points.len() - 1));
+            }
+            if *fractal_type == FractalType::Mandelbrot {
+                ui.checkbox(&mut settings.critical_orbit, "Critical orbit (z0 = 0)")
+                    .on_hover_text(
+                        "Off traces the hovered point as z0 under the Julia constant instead",
+                    );
+            }
+
+            let magnitudes: PlotPoints = traced
+                .points
+                .iter()
+                .enumerate()
+                .map(|(n, z)| [n as f64, z.length() as f64])
+                .collect();
+            let arguments: PlotPoints = traced
+                .points
+                .iter()
+                .enumerate()
+                .map(|(n, z)| [n as f64, z.y.atan2(z.x) as f64])
+                .collect();
+            ui.label("|z_n|");
+            Plot::new("orbit_magnitude")
+                .height(PLOT_HEIGHT)
+                .width(2.0 * PLOT_HEIGHT)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| plot_ui.line(Line::new(magnitudes)));
+            ui.label("arg(z_n)");
+            Plot::new("orbit_argument")
+                .height(PLOT_HEIGHT)
+                .width(2.0 * PLOT_HEIGHT)
+                .allow_scroll(false)
+                .include_y(-std::f64::consts::PI)
+                .include_y(std::f64::consts::PI)
+                .show(ui, |plot_ui| plot_ui.line(Line::new(arguments)));
+        });
+}
@@ -4,12 +4,40 @@
 use bevy::{
     input::keyboard::KeyCode,
     input::mouse::{MouseScrollUnit, MouseWheel},
+    input::touch::{TouchInput, Touches},
+    input::touchpad::{TouchpadMagnify, TouchpadRotate},
     math::vec2,
     prelude::*,
-    render::camera::CameraProjection,
-    window::PrimaryWindow,
+    render::camera::{CameraProjection, NormalizedRenderTarget},
+    utils::HashMap,
+    window::{PrimaryWindow, RequestRedraw},
 };
 
+use std::f32::consts::{PI, TAU};
+
+use crate::bindings::{
+    Action, ActionInput, BindingCapture, Bindings, DoubleClicks, EguiWantsKeyboard,
+};
+use crate::gamepad::{GamepadAxes, GamepadControls};
+
+// Longest frame delta the zoom animation advances by in one step, in seconds.
+const MAX_INTERPOLATION_DELTA: f32 = 1.0 / 30.0;
+// Keyboard navigation speeds once fully accelerated: viewport heights panned per second, and
+// natural-log units of magnification per second.
+const KEY_PAN_SPEED: f32 = 0.8;
+const KEY_ZOOM_SPEED: f32 = 1.5;
+// Time constant of the ease in when a navigation key is pressed and the ease out on release.
+const KEY_ACCELERATION_TIME: f32 = 0.25;
+// Weight of the newest frame in the drag and gesture velocity estimates.
+const GESTURE_VELOCITY_SMOOTHING: f32 = 0.4;
+// The glide stops once it pans less than this many viewport heights per second, and zooms and
+// rotates slower than this many natural-log units or radians per second.
+const GLIDE_STOP_SPEED: f32 = 0.01;
+// Boxes narrower or shorter than this many logical pixels are taken as a stray click, not a zoom.
+const MIN_BOX_ZOOM_SIZE: f32 = 4.0;
+// Magnification step of the click zoom bindings.
+const CLICK_ZOOM_FACTOR: f32 = 2.0;
+
 /// Plugin that adds the necessary systems for `PanCamConfig` and `PanCamState` components to work
 #[derive(Default)]
 pub struct PanCamPlugin;
@@ -27,7 +55,24 @@ impl Plugin for PanCamPlugin {
                 camera_zoom
                     .before(apply_constraints_system)
                     .before(zoom_interpolation_system),
+                keyboard_navigation_system
+                    .after(camera_zoom)
+                    .before(zoom_interpolation_system),
+                click_zoom_system
+                    .after(camera_zoom)
+                    .before(zoom_interpolation_system)
+                    .before(camera_movement),
                 zoom_interpolation_system.before(apply_constraints_system),
+                gamepad_camera_system
+                    .after(zoom_interpolation_system)
+                    .before(apply_constraints_system),
+                touch_gesture_system
+                    .after(zoom_interpolation_system)
+                    .before(apply_constraints_system),
+                glide_system
+                    .after(camera_movement)
+                    .after(touch_gesture_system)
+                    .before(apply_constraints_system),
                 apply_constraints_system,
             )
                 .in_set(PanCamSystemSet),
@@ -35,6 +80,30 @@ impl Plugin for PanCamPlugin {
         .register_type::<PanCamConfig>()
         .register_type::<PanCamState>();
 
+        // Apps built without gizmos still get box zoom, just without the outline.
+        if app.is_plugin_added::<bevy::gizmos::GizmoPlugin>() {
+            app.add_systems(PostUpdate, draw_box_zoom_system);
+        }
+
+        // The systems only need these to exist, so headless apps without input or windows work.
+        app.add_event::<MouseWheel>()
+            .add_event::<TouchInput>()
+            .add_event::<TouchpadMagnify>()
+            .add_event::<TouchpadRotate>()
+            .add_event::<RequestRedraw>()
+            .init_resource::<Touches>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<Axis<GamepadButton>>()
+            .init_resource::<Gamepads>()
+            .init_resource::<Bindings>()
+            .init_resource::<BindingCapture>()
+            .init_resource::<DoubleClicks>()
+            .init_resource::<EguiWantsKeyboard>()
+            .init_resource::<GamepadControls>();
+
         {
             app.init_resource::<EguiWantsFocus>()
                 .add_systems(PostUpdate, check_egui_wants_focus)
@@ -47,7 +116,7 @@ impl Plugin for PanCamPlugin {
 }
 
 #[derive(Resource, Deref, DerefMut, PartialEq, Eq, Default)]
-struct EguiWantsFocus(bool);
+pub struct EguiWantsFocus(pub bool);
 
 // todo: make run condition when Bevy supports mutable resources in them
 fn check_egui_wants_focus(
@@ -64,20 +133,38 @@ fn check_egui_wants_focus(
     wants_focus.set_if_neq(EguiWantsFocus(new_wants_focus));
 }
 
+// The window a camera renders to, or None for image targets and cameras whose window is gone.
+fn camera_window(camera: &Camera, primary_window: Option<Entity>) -> Option<Entity> {
+    match camera.target.normalize(primary_window)? {
+        NormalizedRenderTarget::Window(window_ref) => Some(window_ref.entity()),
+        _ => None,
+    }
+}
+
+// Cursor position relative to the top left corner of the camera's viewport, in logical pixels.
+// None when the cursor is outside the viewport or its window.
+fn viewport_cursor_position(camera: &Camera, window: &Window) -> Option<Vec2> {
+    let viewport = camera.logical_viewport_rect()?;
+    let cursor = window.cursor_position()?;
+    viewport.contains(cursor).then(|| cursor - viewport.min)
+}
+
+// Turn an offset in view space (x right, y up) into world space, following the camera's rotation.
+fn view_to_world(transform: &Transform, offset: Vec2) -> Vec2 {
+    (transform.rotation * offset.extend(0.0)).truncate()
+}
+
 // System that applies constraints on the camera's position and zoom based on defined bounds.
 fn apply_constraints_system(
     mut query: Query<(
+        &Camera,
         &PanCamConfig,
         &mut PanCamState,
         &mut OrthographicProjection,
         &mut Transform,
     )>,
-    primary_window: Query<&Window, With<PrimaryWindow>>,
 ) {
-    let window = primary_window.single();
-    let window_size = Vec2::new(window.width(), window.height());
-
-    for (cam_conf, mut cam, mut proj, mut pos) in query.iter_mut() {
+    for (camera, cam_conf, mut cam, mut proj, mut pos) in query.iter_mut() {
         let scale_constrained = BVec2::new(
             cam_conf.min_x.is_some() && cam_conf.max_x.is_some(),
             cam_conf.min_y.is_some() && cam_conf.max_y.is_some(),
@@ -88,7 +175,12 @@ fn apply_constraints_system(
             cam_conf.max_y.unwrap_or(f32::INFINITY) - cam_conf.min_y.unwrap_or(-f32::INFINITY),
         );
 
-        let max_safe_scale = max_scale_within_bounds(bounds_size, &proj, window_size);
+        // The viewport size is unknown until the camera's render target has been resolved.
+        let max_safe_scale = camera
+            .logical_viewport_size()
+            .map_or(Vec2::splat(f32::INFINITY), |viewport_size| {
+                max_scale_within_bounds(bounds_size, &proj, viewport_size)
+            });
 
         let max_scale = cam_conf
             .max_scale
@@ -161,14 +253,36 @@ fn zoom_interpolation_system(
         &mut Transform,
     )>,
     time: Res<Time>,
+    mut redraw: EventWriter<RequestRedraw>,
 ) {
+    // The first frame after an idle sleep can have a long delta; cap it so the zoom doesn't jump.
+    let delta = time.delta_seconds().min(MAX_INTERPOLATION_DELTA);
     for (cam_conf, mut cam, mut proj, mut transform) in query.iter_mut() {
-        let interpolation_factor = cam_conf.animation_scale * time.delta_seconds();
+        let interpolation_factor = cam_conf.animation_scale * delta;
+        // The rotation eases on its own, since the zoom can arrive first.
+        if let Some(target_rotation) = cam.target_rotation {
+            redraw.send(RequestRedraw);
+            if ease_rotation(&mut transform, target_rotation, interpolation_factor) {
+                cam.target_rotation = None;
+            }
+        }
         if cam.is_zooming {
+            // Keep frames coming while the animation runs, even in reactive update modes.
+            redraw.send(RequestRedraw);
             let zoom_difference = cam.target_zoom - proj.scale;
 
             // Reset zooming flag if close to target values
-            if zoom_difference.abs() <= 0.1
+            if cam.target_locked {
+                // Navigation targets can lie deep in, so judge closeness relative to the view.
+                if zoom_difference.abs() <= cam.target_zoom * 1e-3
+                    && cam.target_translation.map_or(true, |target| {
+                        (target - transform.translation).length() <= proj.scale * 0.5
+                    })
+                {
+                    cam.is_zooming = false;
+                    cam.target_locked = false;
+                }
+            } else if zoom_difference.abs() <= 0.1
                 && (cam.target_translation.is_none()
                     || (cam.target_translation.unwrap() - transform.translation).length() <= 0.01)
             {
@@ -214,43 +328,560 @@ fn zoom_interpolation_system(
     }
 }
 
+// Turn the camera `factor` of the way to `target`. Returns true once it is there.
+fn ease_rotation(transform: &mut Transform, target: Quat, factor: f32) -> bool {
+    if factor >= 1.0 || transform.rotation.angle_between(target) <= 1e-3 {
+        transform.rotation = target;
+        return true;
+    }
+    transform.rotation = transform.rotation.slerp(target, factor);
+    false
+}
+
+// Pan with the arrow keys and zoom around the viewport center with the zoom keys, easing in and
+// out. Home eases back to the initial view. Both go through the targets `zoom_interpolation_system`
+// follows.
+fn keyboard_navigation_system(
+    actions: ActionInput,
+    time: Res<Time>,
+    mut query: Query<(
+        &PanCamConfig,
+        &mut PanCamState,
+        &OrthographicProjection,
+        &Transform,
+    )>,
+    mut motion: Local<Vec3>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let held = |action| if actions.pressed(action) { 1.0 } else { 0.0 };
+    // Pan x, pan y and zoom, eased towards the keys held.
+    let input = Vec3::new(
+        held(Action::PanRight) - held(Action::PanLeft),
+        held(Action::PanUp) - held(Action::PanDown),
+        held(Action::ZoomIn) - held(Action::ZoomOut),
+    );
+    let delta = time.delta_seconds().min(MAX_INTERPOLATION_DELTA);
+    *motion = motion.lerp(input, 1.0 - (-delta / KEY_ACCELERATION_TIME).exp());
+    if input == Vec3::ZERO && motion.length() < 1e-3 {
+        *motion = Vec3::ZERO;
+    }
+    let reset = actions.just_pressed(Action::ResetView);
+    if *motion == Vec3::ZERO && !reset {
+        return;
+    }
+    redraw.send(RequestRedraw);
+
+    for (cam_conf, mut cam, proj, transform) in query.iter_mut() {
+        // Leave the intro zoom alone.
+        if !cam_conf.enabled || !cam.initialized {
+            continue;
+        }
+        if reset {
+            cam.target_zoom = cam.home_zoom;
+            cam.target_translation = Some(cam.home_translation);
+            cam.target_rotation = Some(Quat::IDENTITY);
+        } else {
+            // The interpolation closes `animation_scale` of the gap per second, so aiming this far
+            // ahead moves the view at the wanted speed. The visible area, and so the speed, follows
+            // the projection scale.
+            let lead = 1.0 / cam_conf.animation_scale.max(f32::EPSILON);
+            let pan = view_to_world(
+                transform,
+                motion.truncate() * proj.area.height() * KEY_PAN_SPEED * lead,
+            );
+            cam.target_translation = Some(transform.translation + pan.extend(0.0));
+            cam.target_zoom = proj.scale * (-motion.z * KEY_ZOOM_SPEED * lead).exp();
+        }
+        cam.stop_glide();
+        cam.is_zooming = true;
+        cam.first_zoom = false;
+        cam.target_locked = true;
+    }
+}
+
+// Animate to projection scale `scale` so that the world point under `from` ends up under `to`.
+// Both are offsets from the viewport center in logical pixels with y up.
+fn zoom_to(
+    cam: &mut PanCamState,
+    transform: &Transform,
+    proj: &OrthographicProjection,
+    viewport_size: Vec2,
+    from: Vec2,
+    to: Vec2,
+    scale: f32,
+) {
+    let world_per_pixel = proj.area.size() / viewport_size;
+    let anchor =
+        transform.translation.truncate() + view_to_world(transform, from * world_per_pixel);
+    let offset = view_to_world(transform, to * world_per_pixel * scale / proj.scale);
+    cam.animate_to((anchor - offset).extend(transform.translation.z), scale);
+}
+
+// Drag out a box with the box zoom binding to fit it to the viewport, keeping the viewport's
+// aspect ratio, or zoom 2x in or out around the cursor with the click zoom bindings.
+fn click_zoom_system(
+    actions: ActionInput,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<&Window>,
+    mut query: Query<(
+        Entity,
+        &Camera,
+        &PanCamConfig,
+        &mut PanCamState,
+        &OrthographicProjection,
+        &Transform,
+    )>,
+    mut box_anchors: Local<HashMap<Entity, Vec2>>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let primary_window = primary_window.get_single().ok();
+    let box_held = actions.pressed(Action::BoxZoom);
+    let box_started = actions.just_pressed(Action::BoxZoom);
+    let click_factor = if actions.just_pressed(Action::ClickZoomIn) {
+        CLICK_ZOOM_FACTOR
+    } else if actions.just_pressed(Action::ClickZoomOut) {
+        1.0 / CLICK_ZOOM_FACTOR
+    } else {
+        1.0
+    };
+
+    for (entity, camera, cam_conf, mut cam, proj, transform) in &mut query {
+        // Leave the intro zoom alone.
+        if !cam_conf.enabled || !cam.initialized {
+            box_anchors.remove(&entity);
+            cam.selection = None;
+            continue;
+        }
+        let Some(viewport_size) = camera.logical_viewport_size() else {
+            continue;
+        };
+        let cursor = camera_window(camera, primary_window)
+            .and_then(|window| windows.get(window).ok())
+            .and_then(|window| viewport_cursor_position(camera, window))
+            .map(|cursor| {
+                let offset = cursor - viewport_size / 2.0;
+                Vec2::new(offset.x, -offset.y)
+            });
+
+        if box_held {
+            match (box_anchors.get(&entity).copied(), cursor) {
+                (None, Some(cursor)) if box_started => {
+                    box_anchors.insert(entity, cursor);
+                    cam.selection = Some(Rect::from_corners(cursor, cursor));
+                }
+                (Some(anchor), Some(cursor)) => {
+                    cam.selection = Some(Rect::from_corners(anchor, cursor));
+                    redraw.send(RequestRedraw);
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if box_anchors.remove(&entity).is_some() {
+            if let Some(selection) = cam.selection.take() {
+                let size = selection.size();
+                if size.min_element() >= MIN_BOX_ZOOM_SIZE {
+                    // The box's longer side relative to the viewport decides, so all of it stays
+                    // in view.
+                    let fit = (size / viewport_size).max_element();
+                    let scale = (proj.scale * fit).max(cam_conf.min_scale);
+                    zoom_to(
+                        &mut cam,
+                        transform,
+                        proj,
+                        viewport_size,
+                        selection.center(),
+                        Vec2::ZERO,
+                        scale,
+                    );
+                }
+            }
+            continue;
+        }
+
+        if let Some(cursor) = cursor.filter(|_| click_factor != 1.0) {
+            // Quick repeated clicks build on the zoom still animating.
+            let base_scale = if cam.is_zooming && cam.target_locked {
+                cam.target_zoom
+            } else {
+                proj.scale
+            };
+            let scale = (base_scale / click_factor).max(cam_conf.min_scale);
+            zoom_to(
+                &mut cam,
+                transform,
+                proj,
+                viewport_size,
+                cursor,
+                cursor,
+                scale,
+            );
+        }
+    }
+}
+
+// Outline the box being dragged out for a box zoom.
+fn draw_box_zoom_system(
+    query: Query<(&Camera, &PanCamState, &OrthographicProjection, &Transform)>,
+    mut gizmos: Gizmos,
+) {
+    for (camera, cam, proj, transform) in &query {
+        let (Some(selection), Some(viewport_size)) =
+            (cam.selection, camera.logical_viewport_size())
+        else {
+            continue;
+        };
+        let world_per_pixel = proj.area.size() / viewport_size;
+        let center = transform.translation.truncate()
+            + view_to_world(transform, selection.center() * world_per_pixel);
+        let angle = transform.rotation.to_euler(EulerRot::XYZ).2;
+        gizmos.rect_2d(
+            center,
+            angle,
+            selection.size() * world_per_pixel,
+            Color::WHITE,
+        );
+    }
+}
+
+// Pan with the left stick and zoom around the viewport center with the triggers.
+fn gamepad_camera_system(
+    gamepad: GamepadAxes,
+    time: Res<Time>,
+    mut query: Query<(
+        &PanCamConfig,
+        &mut PanCamState,
+        &mut OrthographicProjection,
+        &mut Transform,
+    )>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let stick = gamepad.left_stick();
+    let zoom = gamepad.zoom();
+    if stick == Vec2::ZERO && zoom == 0.0 {
+        return;
+    }
+    // Sticks and triggers don't wake a reactive app, so keep frames coming while they are held.
+    redraw.send(RequestRedraw);
+    let delta = time.delta_seconds().min(MAX_INTERPOLATION_DELTA);
+    let controls = gamepad.controls();
+
+    for (cam_conf, mut cam, mut proj, mut transform) in query.iter_mut() {
+        // Leave the intro zoom alone.
+        if !cam_conf.enabled || !cam.initialized {
+            continue;
+        }
+
+        // The visible area grows with the projection scale, so panning speed follows it.
+        let pan = view_to_world(
+            &transform,
+            stick * proj.area.height() * controls.pan_speed * delta,
+        )
+        .extend(0.0);
+        transform.translation += pan;
+        if let Some(target_translation) = cam.target_translation.as_mut() {
+            *target_translation += pan;
+        }
+
+        if zoom != 0.0 {
+            proj.scale *= (-zoom * controls.zoom_speed * delta).exp();
+            // Take over from any wheel zoom still animating so the two don't fight.
+            cam.target_zoom = proj.scale;
+            cam.target_translation = None;
+            cam.is_zooming = false;
+            cam.first_zoom = false;
+        }
+    }
+}
+
+// Signed angle from `from` to `to`, counterclockwise positive.
+fn angle_between(from: Vec2, to: Vec2) -> f32 {
+    let angle = to.y.atan2(to.x) - from.y.atan2(from.x);
+    if angle > PI {
+        angle - TAU
+    } else if angle < -PI {
+        angle + TAU
+    } else {
+        angle
+    }
+}
+
+// Zoom by `zoom_factor` (above 1 zooms in) and turn the content by `rotation` radians so that the
+// world point under `from` ends up under `to`. Both are offsets from the viewport center in logical
+// pixels with y up.
+fn apply_gesture(
+    transform: &mut Transform,
+    proj: &mut OrthographicProjection,
+    viewport_size: Vec2,
+    from: Vec2,
+    to: Vec2,
+    zoom_factor: f32,
+    rotation: f32,
+) {
+    let world_per_pixel = proj.area.size() / viewport_size;
+    let anchor =
+        transform.translation.truncate() + view_to_world(transform, from * world_per_pixel);
+    proj.scale /= zoom_factor;
+    // Turning the camera one way turns the content the other.
+    transform.rotate_z(-rotation);
+    let offset = view_to_world(transform, to * world_per_pixel / zoom_factor);
+    transform.translation = (anchor - offset).extend(transform.translation.z);
+}
+
+// Touchscreen and trackpad gestures: one finger pans, two fingers pan, pinch-zoom around their
+// centroid and rotate when the camera allows it. Trackpad magnify and rotate gestures anchor at the
+// cursor. The gesture's velocity is left for `glide_system` to carry on with.
+#[allow(clippy::too_many_arguments)]
+fn touch_gesture_system(
+    touches: Res<Touches>,
+    mut magnify_events: EventReader<TouchpadMagnify>,
+    mut rotate_events: EventReader<TouchpadRotate>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<&Window>,
+    time: Res<Time>,
+    // Where each finger was when this system last ran. Touch::previous_position only goes back to
+    // the last touch event, and several can arrive in one frame.
+    mut last_positions: Local<HashMap<u64, Vec2>>,
+    mut query: Query<(
+        &Camera,
+        &PanCamConfig,
+        &mut PanCamState,
+        &mut OrthographicProjection,
+        &mut Transform,
+    )>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let primary_window = primary_window.get_single().ok();
+    let previous_positions = std::mem::replace(
+        &mut *last_positions,
+        touches
+            .iter()
+            .map(|touch| (touch.id(), touch.position()))
+            .collect(),
+    );
+    let magnify = magnify_events
+        .read()
+        .fold(1.0, |factor, event| factor * (1.0 + event.0).max(0.1));
+    let trackpad_rotation: f32 = rotate_events.read().map(|event| event.0.to_radians()).sum();
+    // Follow the two oldest fingers, so a third one doesn't make the gesture jump.
+    let mut fingers: Vec<_> = touches.iter().collect();
+    fingers.sort_by_key(|touch| touch.id());
+    fingers.truncate(2);
+
+    for (camera, cam_conf, mut cam, mut proj, mut transform) in query.iter_mut() {
+        // Leave the intro zoom alone.
+        if !cam_conf.enabled || !cam.initialized {
+            continue;
+        }
+        let Some(window) = camera_window(camera, primary_window).and_then(|e| windows.get(e).ok())
+        else {
+            continue;
+        };
+        let (Some(viewport), Some(viewport_size)) = (
+            camera.logical_viewport_rect(),
+            camera.logical_viewport_size(),
+        ) else {
+            continue;
+        };
+        let to_view = |position: Vec2| {
+            let offset = position - viewport.center();
+            Vec2::new(offset.x, -offset.y)
+        };
+
+        // Touch events don't say which window they belong to, so they go to the focused one.
+        let centroid = |positions: &[Vec2]| positions.iter().sum::<Vec2>() / positions.len() as f32;
+        let current: Vec<Vec2> = fingers.iter().map(|touch| touch.position()).collect();
+        let gesture =
+            if window.focused && !current.is_empty() && viewport.contains(centroid(&current)) {
+                // A finger that just landed hasn't moved yet.
+                let previous: Vec<Vec2> = fingers
+                    .iter()
+                    .map(|touch| {
+                        let position = previous_positions.get(&touch.id());
+                        to_view(position.copied().unwrap_or(touch.position()))
+                    })
+                    .collect();
+                let current: Vec<Vec2> = current.into_iter().map(to_view).collect();
+                let (mut zoom_factor, mut rotation) = (1.0, 0.0);
+                if let ([previous_a, previous_b], [current_a, current_b]) =
+                    (previous.as_slice(), current.as_slice())
+                {
+                    let (previous_span, current_span) =
+                        (*previous_b - *previous_a, *current_b - *current_a);
+                    // Fingers that start on the same spot have no meaningful span yet.
+                    if previous_span.length() > 1.0 && current_span.length() > 1.0 {
+                        zoom_factor = current_span.length() / previous_span.length();
+                        rotation = angle_between(previous_span, current_span);
+                    }
+                }
+                Some((
+                    centroid(&previous),
+                    centroid(&current),
+                    zoom_factor,
+                    rotation,
+                ))
+            } else if magnify != 1.0 || trackpad_rotation != 0.0 {
+                window
+                    .cursor_position()
+                    .filter(|cursor| viewport.contains(*cursor))
+                    .map(|cursor| {
+                        let anchor = to_view(cursor);
+                        (anchor, anchor, magnify, trackpad_rotation)
+                    })
+            } else {
+                None
+            };
+
+        if let Some((from, to, zoom_factor, rotation)) = gesture {
+            let rotation = if cam_conf.rotation_enabled {
+                rotation
+            } else {
+                0.0
+            };
+            let previous_translation = transform.translation;
+            apply_gesture(
+                &mut transform,
+                &mut proj,
+                viewport_size,
+                from,
+                to,
+                zoom_factor,
+                rotation,
+            );
+            // Take over from any wheel zoom still animating so the two don't fight.
+            cam.target_zoom = proj.scale;
+            cam.target_translation = None;
+            cam.target_rotation = None;
+            cam.is_zooming = false;
+            cam.first_zoom = false;
+            cam.dragging = true;
+
+            let delta = time.delta_seconds();
+            if delta > 0.0 {
+                let velocity = (transform.translation - previous_translation).truncate() / delta;
+                cam.velocity = cam.velocity.lerp(velocity, GESTURE_VELOCITY_SMOOTHING);
+                cam.zoom_velocity +=
+                    (zoom_factor.ln() / delta - cam.zoom_velocity) * GESTURE_VELOCITY_SMOOTHING;
+                cam.rotation_velocity +=
+                    (rotation / delta - cam.rotation_velocity) * GESTURE_VELOCITY_SMOOTHING;
+            }
+            redraw.send(RequestRedraw);
+        }
+    }
+}
+
+// Keep the view moving with the velocity a mouse drag or gesture ended with, slowing down with the
+// camera's friction. A glide that runs into the bounds bounces back or stops there.
+fn glide_system(
+    time: Res<Time>,
+    mut query: Query<(
+        &PanCamConfig,
+        &mut PanCamState,
+        &mut OrthographicProjection,
+        &mut Transform,
+    )>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let delta = time.delta_seconds().min(MAX_INTERPOLATION_DELTA);
+    for (cam_conf, mut cam, mut proj, mut transform) in query.iter_mut() {
+        // Whatever is dragging the view this frame owns it.
+        let dragging = std::mem::take(&mut cam.dragging);
+        if dragging
+            || !cam_conf.enabled
+            || (cam.velocity == Vec2::ZERO
+                && cam.zoom_velocity == 0.0
+                && cam.rotation_velocity == 0.0)
+        {
+            continue;
+        }
+
+        let max_speed = cam_conf.max_glide_velocity * proj.area.height();
+        let decay = (-cam_conf.glide_friction * delta).exp();
+        cam.velocity = cam.velocity.clamp_length_max(max_speed) * decay;
+        cam.zoom_velocity *= decay;
+        cam.rotation_velocity *= decay;
+
+        transform.translation += (cam.velocity * delta).extend(0.0);
+        proj.scale /= (cam.zoom_velocity * delta).exp();
+        transform.rotate_z(-cam.rotation_velocity * delta);
+        cam.target_zoom = proj.scale;
+
+        let half_of_viewport = proj.area.size() / 2.;
+        for (axis, min, max) in [
+            (0, cam_conf.min_x, cam_conf.max_x),
+            (1, cam_conf.min_y, cam_conf.max_y),
+        ] {
+            let low = min.map_or(f32::NEG_INFINITY, |min| min + half_of_viewport[axis]);
+            let high = max.map_or(f32::INFINITY, |max| max - half_of_viewport[axis]);
+            let position = transform.translation[axis];
+            if (position < low && cam.velocity[axis] < 0.0)
+                || (position > high && cam.velocity[axis] > 0.0)
+            {
+                cam.velocity[axis] *= -cam_conf.edge_bounce;
+            }
+            transform.translation[axis] = position.max(low).min(high);
+        }
+
+        if cam.velocity.length() < GLIDE_STOP_SPEED * proj.area.height()
+            && cam.zoom_velocity.abs() < GLIDE_STOP_SPEED
+            && cam.rotation_velocity.abs() < GLIDE_STOP_SPEED
+        {
+            cam.stop_glide();
+        }
+        redraw.send(RequestRedraw);
+    }
+}
+
 // Handle camera zooming based on mouse wheel events and target zoom levels.
 fn camera_zoom(
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: ActionInput,
     mut query: Query<(
+        &Camera,
         &PanCamConfig,
         &mut PanCamState,
         &mut OrthographicProjection,
         &mut Transform,
     )>,
     mut scroll_events: EventReader<MouseWheel>,
-    primary_window: Query<&Window, With<PrimaryWindow>>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<&Window>,
 ) {
-    let window = primary_window.single();
-    let window_size = Vec2::new(window.width(), window.height());
-    let mouse_normalized_screen_pos = window
-        .cursor_position()
-        .map(|cursor_pos| (cursor_pos / window_size) * 2. - Vec2::ONE)
-        .map(|p| Vec2::new(p.x, -p.y));
-
-    for (cam_conf, mut cam, mut proj, pos) in &mut query {
+    let primary_window = primary_window.get_single().ok();
+    let scroll_events: Vec<MouseWheel> = scroll_events.read().cloned().collect();
+
+    for (camera, cam_conf, mut cam, mut proj, pos) in &mut query {
+        let window_entity = camera_window(camera, primary_window);
+        let window = window_entity.and_then(|entity| windows.get(entity).ok());
+        let cursor_position = window.and_then(|window| viewport_cursor_position(camera, window));
+        // A cursor over another viewport of the same window belongs to that viewport's camera.
+        let cursor_elsewhere =
+            cursor_position.is_none() && window.is_some_and(|w| w.cursor_position().is_some());
+
+        let mouse_normalized_screen_pos = cursor_position
+            .zip(camera.logical_viewport_size())
+            .map(|(cursor_pos, viewport_size)| (cursor_pos / viewport_size) * 2. - Vec2::ONE)
+            .map(|p| Vec2::new(p.x, -p.y));
+
         let pixels_per_line = cam_conf.pixels_per_line;
         let base_zoom_multiplier = cam_conf.base_zoom_multiplier;
 
-        // Check if left shift is pressed
-        let shift_multiplier = if keyboard_input.pressed(KeyCode::ShiftLeft) {
+        // Check if the zoom boost binding is held
+        let shift_multiplier = if actions.pressed(Action::ZoomBoost) {
             cam_conf.shift_multiplier_shifted
         } else {
             cam_conf.shift_multiplier_normal
         };
 
-        let mut scroll = scroll_events
-            .iter()
-            .map(|ev| match ev.unit {
-                MouseScrollUnit::Pixel => ev.y,
-                MouseScrollUnit::Line => ev.y * pixels_per_line,
-            })
-            .sum::<f32>();
+        let mut scroll = actions.zoom_scroll(
+            scroll_events
+                .iter()
+                .filter(|ev| Some(ev.window) == window_entity && !cursor_elsewhere)
+                .map(|ev| match ev.unit {
+                    MouseScrollUnit::Pixel => ev.y,
+                    MouseScrollUnit::Line => ev.y * pixels_per_line,
+                })
+                .sum::<f32>(),
+        );
 
         //println!("Current scale: {:?}", proj.scale);
         //println!("Target scale: {:?}", cam.target_zoom);
@@ -271,10 +902,17 @@ fn camera_zoom(
             cam.first_zoom = true;
             cam.is_zooming = true; // Ensure the camera starts zooming on initialization
             cam.initialized = true;
+            cam.home_translation = pos.translation;
         }
 
-        if scroll != 0.0 || cam.is_zooming {
+        // Locked targets come from keyboard or animated navigation and are eased to unchanged,
+        // until the wheel takes over.
+        if scroll != 0.0 || (cam.is_zooming && !cam.target_locked) {
             if cam_conf.enabled {
+                cam.target_locked = false;
+                if scroll != 0.0 {
+                    cam.stop_glide();
+                }
                 // Compute dynamic zoom factor based on the current scale
 
                 // Adjust the zoom multiplier with the dynamic factor
@@ -288,21 +926,27 @@ fn camera_zoom(
                 if let Some(mouse_normalized_screen_pos) = mouse_normalized_screen_pos {
                     let proj_size = proj.area.max / old_scale;
                     let mouse_world_pos = pos.translation.truncate()
-                        + mouse_normalized_screen_pos * proj_size * old_scale;
+                        + view_to_world(&pos, mouse_normalized_screen_pos * proj_size * old_scale);
 
                     cam.target_translation = Some(
                         (mouse_world_pos
-                            - mouse_normalized_screen_pos * proj_size * cam.target_zoom)
-                            .extend(pos.translation.z),
+                            - view_to_world(
+                                &pos,
+                                mouse_normalized_screen_pos * proj_size * cam.target_zoom,
+                            ))
+                        .extend(pos.translation.z),
                     );
                 }
 
                 if let Some(mouse_normalized_screen_pos) = mouse_normalized_screen_pos {
                     let proj_size = proj.area.max / old_scale;
                     let mouse_world_pos_before = pos.translation.truncate()
-                        + mouse_normalized_screen_pos * proj_size * old_scale;
+                        + view_to_world(&pos, mouse_normalized_screen_pos * proj_size * old_scale);
                     let mouse_world_pos_after = pos.translation.truncate()
-                        + mouse_normalized_screen_pos * proj_size * cam.target_zoom;
+                        + view_to_world(
+                            &pos,
+                            mouse_normalized_screen_pos * proj_size * cam.target_zoom,
+                        );
                     cam.delta_zoom_translation =
                         Some((mouse_world_pos_before - mouse_world_pos_after).extend(0.0));
                 } else {
@@ -321,51 +965,69 @@ fn camera_zoom(
 fn max_scale_within_bounds(
     bounds_size: Vec2,
     proj: &OrthographicProjection,
-    window_size: Vec2, //viewport?
+    viewport_size: Vec2,
 ) -> Vec2 {
     let mut p = proj.clone();
     p.scale = 1.;
-    p.update(window_size.x, window_size.y);
+    p.update(viewport_size.x, viewport_size.y);
     let base_world_size = p.area.size();
     bounds_size / base_world_size
 }
 
 // Handle camera movement based on mouse drag events.
 fn camera_movement(
-    primary_window: Query<&Window, With<PrimaryWindow>>,
-    mouse_buttons: Res<Input<MouseButton>>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<&Window>,
+    actions: ActionInput,
+    time: Res<Time>,
     mut query: Query<(
+        Entity,
+        &Camera,
         &PanCamConfig,
         &mut PanCamState,
         &mut Transform,
         &OrthographicProjection,
     )>,
-    mut last_pos: Local<Option<Vec2>>,
+    mut last_positions: Local<HashMap<Entity, Vec2>>,
 ) {
-    let window = primary_window.single();
-    let window_size = Vec2::new(window.width(), window.height());
+    let primary_window = primary_window.get_single().ok();
 
-    // Use position instead of MouseMotion, otherwise we don't get acceleration movement
-    let current_pos = match window.cursor_position() {
-        Some(c) => Vec2::new(c.x, -c.y),
-        None => return,
-    };
-    let delta_device_pixels = current_pos - last_pos.unwrap_or(current_pos);
+    for (entity, camera, cam_conf, mut cam, mut transform, projection) in &mut query {
+        // Use position instead of MouseMotion, otherwise we don't get acceleration movement
+        let cursor_position = camera_window(camera, primary_window)
+            .and_then(|window| windows.get(window).ok())
+            .and_then(|window| viewport_cursor_position(camera, window));
+        let (Some(cursor_position), Some(viewport_size)) =
+            (cursor_position, camera.logical_viewport_size())
+        else {
+            last_positions.remove(&entity);
+            continue;
+        };
+        let current_pos = Vec2::new(cursor_position.x, -cursor_position.y);
+        let last_pos = last_positions.insert(entity, current_pos);
+        let delta_device_pixels = current_pos - last_pos.unwrap_or(current_pos);
 
-    for (cam_conf, cam, mut transform, projection) in &mut query {
-        if cam_conf.enabled
-            && cam_conf
-                .grab_buttons
-                .iter()
-                .any(|btn| mouse_buttons.pressed(*btn))
-        {
+        // The box zoom drag shares its button with panning by default, so it wins while active.
+        if cam_conf.enabled && cam.selection.is_none() && actions.pressed(Action::Pan) {
             let proj_size = projection.area.size();
-            let world_units_per_device_pixel = proj_size / window_size;
+            let world_units_per_device_pixel = proj_size / viewport_size;
             let delta_world = delta_device_pixels * world_units_per_device_pixel;
 
+            // A new drag catches the view, cancelling any glide.
+            if actions.just_pressed(Action::Pan) {
+                cam.stop_glide();
+            }
+            cam.dragging = true;
             if !cam.is_zooming {
                 // Handle panning
-                transform.translation -= delta_world.extend(0.0);
+                let pan = -view_to_world(&transform, delta_world);
+                transform.translation += pan.extend(0.0);
+
+                // Track the drag velocity, so the view glides on once the button is released.
+                let delta = time.delta_seconds();
+                if delta > 0.0 {
+                    cam.velocity = cam.velocity.lerp(pan / delta, GESTURE_VELOCITY_SMOOTHING);
+                }
             }
 
             // Apply boundary constraints
@@ -389,14 +1051,12 @@ fn camera_movement(
             }
         }
     }
-    *last_pos = Some(current_pos);
 }
 
 /// A component for user-facing configurations of panning camera controls.
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 pub struct PanCamConfig {
-    pub grab_buttons: Vec<MouseButton>,
     pub enabled: bool,
     pub zoom_to_cursor: bool,
     pub min_scale: f32,
@@ -410,12 +1070,19 @@ pub struct PanCamConfig {
     pub shift_multiplier_normal: f32,
     pub shift_multiplier_shifted: f32,
     pub animation_scale: f32,
+    /// Let two-finger and trackpad rotate gestures turn the view.
+    pub rotation_enabled: bool,
+    /// Decay rate per second of the glide after a drag or gesture; higher stops sooner.
+    pub glide_friction: f32,
+    /// Fastest glide, in viewport heights per second.
+    pub max_glide_velocity: f32,
+    /// Share of its speed a glide keeps when it bounces off a bound; 0 stops it at the edge.
+    pub edge_bounce: f32,
 }
 
 impl Default for PanCamConfig {
     fn default() -> Self {
         Self {
-            grab_buttons: vec![MouseButton::Left, MouseButton::Right, MouseButton::Middle],
             enabled: true,
             zoom_to_cursor: true,
             min_scale: 0.00001,
@@ -429,6 +1096,10 @@ impl Default for PanCamConfig {
             shift_multiplier_normal: 10.0,
             shift_multiplier_shifted: 30.0,
             animation_scale: 3.0,
+            rotation_enabled: false,
+            glide_friction: 4.0,
+            max_glide_velocity: 5.0,
+            edge_bounce: 0.3,
         }
     }
 }
@@ -438,24 +1109,77 @@ impl Default for PanCamConfig {
 #[reflect(Component)]
 pub struct PanCamState {
     pub current_zoom: f32,
+    /// Projection scale of the initial view, used as the 1x reference for magnification.
+    pub home_zoom: f32,
     pub target_zoom: f32,
     pub is_zooming: bool,
     pub target_translation: Option<Vec3>,
+    /// Rotation the camera is easing to, e.g. back upright on reset.
+    pub target_rotation: Option<Quat>,
     pub delta_zoom_translation: Option<Vec3>,
     pub first_zoom: bool,
     pub initialized: bool,
+    /// Translation of the initial view, restored by the reset view binding.
+    pub home_translation: Vec3,
+    /// The targets were set by keyboard or animated navigation and are eased to as they are,
+    /// instead of being recomputed from the scroll wheel every frame.
+    pub target_locked: bool,
+    /// Glide velocity left over from a drag or gesture, in world units per second.
+    pub velocity: Vec2,
+    /// Glide zoom speed in natural-log units of magnification per second.
+    pub zoom_velocity: f32,
+    /// Glide rotation of the content in radians per second, counterclockwise positive.
+    pub rotation_velocity: f32,
+    /// Box being dragged out for a box zoom, as offsets from the viewport center in logical
+    /// pixels with y up.
+    pub selection: Option<Rect>,
+    /// A drag or gesture moved the view this frame, so it doesn't glide.
+    pub dragging: bool,
+}
+
+impl PanCamState {
+    /// How many times a view with projection scale `scale` is magnified relative to the initial view.
+    pub fn magnification(&self, scale: f32) -> f32 {
+        self.home_zoom / scale.max(f32::MIN_POSITIVE)
+    }
+
+    /// Ease the camera to `translation` and projection scale `scale`, cancelling any glide.
+    pub fn animate_to(&mut self, translation: Vec3, scale: f32) {
+        self.target_zoom = scale;
+        self.target_translation = Some(translation);
+        self.stop_glide();
+        self.is_zooming = true;
+        self.first_zoom = false;
+        self.target_locked = true;
+    }
+
+    /// Cancel any glide left over from a drag or gesture.
+    pub fn stop_glide(&mut self) {
+        self.velocity = Vec2::ZERO;
+        self.zoom_velocity = 0.0;
+        self.rotation_velocity = 0.0;
+    }
 }
 
 impl Default for PanCamState {
     fn default() -> Self {
         Self {
             current_zoom: 1.0,
+            home_zoom: 1.0,
             target_zoom: 1.0,
             is_zooming: false,
             target_translation: None,
+            target_rotation: None,
             delta_zoom_translation: None,
             first_zoom: false,
             initialized: false,
+            home_translation: Vec3::ZERO,
+            target_locked: false,
+            velocity: Vec2::ZERO,
+            zoom_velocity: 0.0,
+            rotation_velocity: 0.0,
+            selection: None,
+            dragging: false,
         }
     }
 }
@@ -466,6 +1190,7 @@ mod tests {
     use std::f32::INFINITY;
 
     use bevy::prelude::OrthographicProjection;
+    use bevy::render::camera::RenderTarget;
 
     use super::*;
 
@@ -476,6 +1201,141 @@ mod tests {
         proj
     }
 
+    #[test]
+    fn window_cameras_follow_the_primary_window() {
+        let primary = Entity::from_raw(7);
+        let camera = Camera::default();
+        assert_eq!(camera_window(&camera, Some(primary)), Some(primary));
+        assert_eq!(camera_window(&camera, None), None);
+    }
+
+    #[test]
+    fn image_cameras_have_no_window() {
+        let camera = Camera {
+            target: RenderTarget::Image(Handle::default()),
+            ..default()
+        };
+        assert_eq!(camera_window(&camera, Some(Entity::from_raw(7))), None);
+    }
+
+    #[test]
+    fn plugin_runs_without_windows() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, PanCamPlugin));
+        let camera = app
+            .world
+            .spawn((
+                Camera2dBundle::default(),
+                PanCamConfig::default(),
+                PanCamState {
+                    target_zoom: 2.0,
+                    is_zooming: true,
+                    ..default()
+                },
+            ))
+            .id();
+        for _ in 0..3 {
+            app.update();
+        }
+        let scale = app
+            .world
+            .get::<OrthographicProjection>(camera)
+            .unwrap()
+            .scale;
+        assert!(scale.is_finite() && scale > 0.0);
+    }
+
+    #[test]
+    fn glide_bounces_off_bounds() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, PanCamPlugin));
+        let camera = app
+            .world
+            .spawn((
+                Camera2dBundle {
+                    transform: Transform::from_xyz(-100., 0., 0.),
+                    ..default()
+                },
+                PanCamConfig {
+                    min_x: Some(-10.),
+                    edge_bounce: 0.5,
+                    ..default()
+                },
+                PanCamState {
+                    initialized: true,
+                    velocity: vec2(-50., 0.),
+                    ..default()
+                },
+            ))
+            .id();
+        app.update();
+        let cam = app.world.get::<PanCamState>(camera).unwrap();
+        assert!(cam.velocity.x > 0.0);
+    }
+
+    #[test]
+    fn click_zoom_keeps_the_point_under_the_cursor() {
+        let viewport_size = vec2(200., 100.);
+        let proj = mock_proj(viewport_size);
+        let transform = Transform::from_xyz(10., 20., 0.);
+        let mut cam = PanCamState::default();
+        let cursor = vec2(50., -25.);
+        zoom_to(
+            &mut cam,
+            &transform,
+            &proj,
+            viewport_size,
+            cursor,
+            cursor,
+            0.5,
+        );
+        let target = cam.target_translation.unwrap().truncate();
+        assert!((target + cursor * 0.5 - vec2(60., -5.)).length() < 1e-4);
+        assert_eq!(cam.target_zoom, 0.5);
+        assert!(cam.is_zooming && cam.target_locked);
+    }
+
+    #[test]
+    fn rotation_eases_back_upright() {
+        let mut transform = Transform::from_rotation(Quat::from_rotation_z(1.0));
+        assert!(!ease_rotation(&mut transform, Quat::IDENTITY, 0.5));
+        assert!((transform.rotation.angle_between(Quat::IDENTITY) - 0.5).abs() < 1e-4);
+        let mut steps = 0;
+        while !ease_rotation(&mut transform, Quat::IDENTITY, 0.5) {
+            steps += 1;
+        }
+        assert!(steps > 3);
+        assert_eq!(transform.rotation, Quat::IDENTITY);
+    }
+
+    #[test]
+    fn angle_between_is_signed_and_wraps() {
+        assert!((angle_between(Vec2::X, Vec2::Y) - PI / 2.0).abs() < 1e-6);
+        assert!((angle_between(Vec2::Y, Vec2::X) + PI / 2.0).abs() < 1e-6);
+        // Across the negative x axis the short way round is still 20 degrees.
+        let from = Vec2::from_angle(170f32.to_radians());
+        let to = Vec2::from_angle(-170f32.to_radians());
+        assert!((angle_between(from, to) - 20f32.to_radians()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn gesture_keeps_the_point_under_the_fingers() {
+        let viewport_size = vec2(200., 100.);
+        let mut proj = mock_proj(viewport_size);
+        let mut transform = Transform::from_xyz(10., 20., 0.);
+        let (from, to) = (vec2(30., 10.), vec2(-20., 5.));
+        let world_per_pixel = proj.area.size() / viewport_size;
+        let anchor = transform.translation.truncate() + from * world_per_pixel;
+
+        apply_gesture(&mut transform, &mut proj, viewport_size, from, to, 2.0, 0.5);
+        assert_eq!(proj.scale, 0.5);
+        let (axis, angle) = transform.rotation.to_axis_angle();
+        assert!((axis.z * angle + 0.5).abs() < 1e-5);
+        let under_to = transform.translation.truncate()
+            + view_to_world(&transform, to * world_per_pixel / 2.0);
+        assert!((under_to - anchor).length() < 1e-4);
+    }
+
     #[test]
     fn bounds_matching_window_width_have_max_scale_1() {
         let window_size = vec2(100., 100.);
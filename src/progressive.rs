@@ -0,0 +1,1260 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::math::DVec2;
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_graph::{self, RenderGraph};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::window::PrimaryWindow;
+
+use crate::bindings::{Action, ActionInput};
+use crate::fractals::{
+    complex_to_world, world_to_complex, FractalQuad, FractalType, WORLD_UNITS_PER_COMPLEX_UNIT,
+};
+use crate::materials::FractalMaterials;
+use crate::pancam::{PanCamState, PanCamSystemSet};
+use crate::readback::Readback;
+
+const WORKGROUP_SIZE: u32 = 8;
+const PROGRESSIVE_NODE: &str = "progressive_fractal";
+// One u32 counter of busy pixels.
+const BUSY_PIXELS_SIZE: u64 = 4;
+// Resolution scales are rounded to multiples of 1 / RESOLUTION_STEPS.
+const RESOLUTION_STEPS: f32 = 8.0;
+// Origins, pixel steps and Julia constants closer than this fraction of a pixel, and tolerances
+// within this relative difference, count as unchanged, so float noise doesn't restart the image.
+const GEOMETRY_TOLERANCE: f32 = 1e-4;
+// How long the Julia constant has to hold still before the image restarts for it.
+pub const JULIA_SETTLE_SECONDS: f32 = 0.25;
+
+// Colormaps the compute renderer can pick from, relative to the assets directory.
+pub const PALETTES: [&str; 5] = [
+    "gradient.png",
+    "gradient2.png",
+    "gradient3.png",
+    "gradient4.png",
+    "gradient6.png",
+];
+
+// User-facing settings for the progressive renderer.
+#[derive(Resource, Clone, PartialEq)]
+pub struct ProgressiveSettings {
+    pub enabled: bool,
+    // Iterations every unresolved pixel may advance per frame.
+    pub iterations_per_frame: u32,
+    // Iteration cap; stored as an integer on the GPU so it can go well past f32 precision.
+    pub max_iterations: u32,
+    // Accumulate jittered subpixel samples while the camera is still.
+    pub temporal_accumulation: bool,
+    // Number of subpixel samples a pixel accumulates before it stops refining.
+    pub samples_per_pixel: u32,
+    // Keep the current coloring and Julia constant instead of following the materials, e.g. during exports.
+    pub freeze_parameters: bool,
+    // Index into PALETTES. Switching palettes, like changing the color scale, only reruns the
+    // color pass and keeps every sample taken so far.
+    pub palette: usize,
+    // Lower the resolution while the camera moves to hold target_frame_time.
+    pub dynamic_resolution: bool,
+    // Frame time in milliseconds the dynamic resolution aims for.
+    pub target_frame_time: f32,
+    // Smallest fraction of the window resolution rendered while moving.
+    pub min_resolution_scale: f32,
+    // Render this frame at full resolution instead of following the camera and the materials,
+    // e.g. while a sequence export steps through its frames.
+    pub pinned: Option<PinnedFrame>,
+}
+
+// A view and fractal parameters for the progressive renderer to draw regardless of the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PinnedFrame {
+    // Index of the fractal as used by the compute shaders.
+    pub fractal: u32,
+    // Complex coordinate of the view center.
+    pub center: Vec2,
+    // Complex distance between neighbouring physical pixels.
+    pub pixel_step: f32,
+    pub julia_c: Vec2,
+    pub color_scale: f32,
+    pub periodicity_tolerance: f32,
+    // Index into PALETTES.
+    pub palette: usize,
+}
+
+impl Default for ProgressiveSettings {
+    fn default() -> Self {
+        ProgressiveSettings {
+            enabled: false,
+            iterations_per_frame: 250,
+            max_iterations: 100_000,
+            temporal_accumulation: true,
+            samples_per_pixel: 16,
+            freeze_parameters: false,
+            palette: 0,
+            dynamic_resolution: true,
+            target_frame_time: 1000.0 / 60.0,
+            min_resolution_scale: 0.25,
+            pinned: None,
+        }
+    }
+}
+
+// Uniform block shared with progressive_compute.wgsl; field order must match the shader.
+#[derive(Clone, Copy, Default, Debug, PartialEq, ShaderType)]
+pub struct ProgressiveParams {
+    // Complex coordinate of the center of pixel (0, 0).
+    pub origin: Vec2,
+    // Complex distance between neighbouring pixels.
+    pub pixel_step: Vec2,
+    pub julia_c: Vec2,
+    pub size: UVec2,
+    pub fractal: u32,
+    pub max_iterations: u32,
+    pub iteration_budget: u32,
+    pub color_scale: f32,
+    pub periodicity_tolerance: f32,
+    pub sample_cap: u32,
+    // Non-zero while pixels should keep taking new subpixel samples.
+    pub accumulate: u32,
+    // Mapping of the previously rendered frame, for the preview shown while pixels resolve.
+    pub previous_origin: Vec2,
+    pub previous_pixel_step: Vec2,
+    pub previous_size: UVec2,
+    // Pixel p of this frame shows what pixel p + reuse_offset showed last frame.
+    pub reuse_offset: IVec2,
+    // One of the REUSE_* constants; how much of the previous frame a reset may keep.
+    pub reuse: u32,
+}
+
+// Start from scratch.
+const REUSE_NONE: u32 = 0;
+// Show the previous output rescaled to the new view until pixels resolve.
+const REUSE_PREVIEW: u32 = 1;
+// The view moved by whole pixels; copy the state of the overlapping region over.
+const REUSE_PIXELS: u32 = 2;
+
+impl ProgressiveParams {
+    // Whether two parameter sets produce the same orbits, i.e. only coloring differs.
+    pub fn same_geometry(&self, other: &ProgressiveParams) -> bool {
+        self.same_view(other) && self.same_julia_c(other)
+    }
+
+    // Whether everything but the Julia constant produces the same orbits.
+    fn same_view(&self, other: &ProgressiveParams) -> bool {
+        let tolerance = self.pixel_tolerance(other);
+        (self.origin - other.origin).abs().max_element() <= tolerance
+            && (self.pixel_step - other.pixel_step).abs().max_element() <= tolerance
+            && self.size == other.size
+            && self.fractal == other.fractal
+            && self.max_iterations == other.max_iterations
+            && (self.periodicity_tolerance - other.periodicity_tolerance).abs()
+                <= self
+                    .periodicity_tolerance
+                    .abs()
+                    .max(other.periodicity_tolerance.abs())
+                    * GEOMETRY_TOLERANCE
+    }
+
+    fn same_julia_c(&self, other: &ProgressiveParams) -> bool {
+        (self.julia_c - other.julia_c).abs().max_element() <= self.pixel_tolerance(other)
+    }
+
+    // Complex distance below which two positions count as the same.
+    fn pixel_tolerance(&self, other: &ProgressiveParams) -> f32 {
+        self.pixel_step
+            .abs()
+            .max(other.pixel_step.abs())
+            .max_element()
+            * GEOMETRY_TOLERANCE
+    }
+
+    // Whether jittered samples beyond the cached center sample are being taken.
+    fn takes_extra_samples(&self) -> bool {
+        self.accumulate != 0 && self.sample_cap > 1
+    }
+}
+
+// Everything the render world needs to advance the progressive render for this frame.
+#[derive(Resource, Clone, Default, ExtractResource)]
+pub struct ProgressiveView {
+    pub enabled: bool,
+    pub params: ProgressiveParams,
+    // Bumped whenever the geometry changes; the render node resets the state when it sees a new value.
+    pub generation: u32,
+    // Frames dispatched since the state was last reset or pixels were last asked for more samples.
+    pub frames_since_reset: u32,
+    // Fraction of the window resolution currently rendered.
+    pub resolution_scale: f32,
+    // Pixel grid coordinate of the view center; the origin is snapped to it so pans move whole pixels.
+    pub anchor: DVec2,
+    pub orbit_state: Handle<Image>,
+    pub progress_state: Handle<Image>,
+    pub accumulation: Handle<Image>,
+    // Cached result of the center sample of every pixel, read by the color pass.
+    pub iteration_data: Handle<Image>,
+    pub output: Handle<Image>,
+    pub colormap: Handle<Image>,
+    // State and output of the frame before the last reset, which the reset pass reuses.
+    pub previous: PreviousFrame,
+    // Bumped to have the render world copy the output back; the copy arrives in CapturedFrames.
+    pub capture: u32,
+    // Bumped whenever pixels are given new work; busy pixel counts read back from the GPU are
+    // matched against it.
+    pub epoch: u32,
+    // The GPU reported that no pixel is busy anymore in the current epoch.
+    pub resolved: bool,
+    // The pinned frame the current parameters were set up for, if any.
+    pub pinned: Option<PinnedFrame>,
+}
+
+#[derive(Clone, Default)]
+pub struct PreviousFrame {
+    pub orbit_state: Handle<Image>,
+    pub progress_state: Handle<Image>,
+    pub accumulation: Handle<Image>,
+    pub iteration_data: Handle<Image>,
+    pub output: Handle<Image>,
+}
+
+impl ProgressiveView {
+    // Upper bound on the frames needed until every pixel has all of its samples. Used for the
+    // progress estimate, and as a fallback until the GPU reports the image resolved.
+    pub fn frames_to_finish(&self) -> u32 {
+        let budget = self.params.iteration_budget.max(1);
+        let frames_per_sample = (self.params.max_iterations + budget - 1) / budget + 1;
+        let samples = if self.params.accumulate != 0 {
+            self.params.sample_cap.max(1)
+        } else {
+            1
+        };
+        frames_per_sample.saturating_mul(samples)
+    }
+
+    // Fraction of the work done towards a fully resolved image.
+    pub fn progress(&self) -> f32 {
+        if self.resolved {
+            return 1.0;
+        }
+        (self.frames_since_reset as f32 / self.frames_to_finish() as f32).min(1.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.resolved || self.frames_since_reset >= self.frames_to_finish()
+    }
+
+    // Start counting towards a resolved image again after pixels were given new work.
+    fn restart(&mut self) {
+        self.frames_since_reset = 0;
+        self.epoch = self.epoch.wrapping_add(1);
+        self.resolved = false;
+    }
+
+    // Make the current textures the previous frame for a reset that reuses them. The reset pass
+    // rewrites every rendered pixel of the other set, so swapping stands in for a copy.
+    fn swap_previous(&mut self) {
+        let previous = &mut self.previous;
+        std::mem::swap(&mut self.orbit_state, &mut previous.orbit_state);
+        std::mem::swap(&mut self.progress_state, &mut previous.progress_state);
+        std::mem::swap(&mut self.accumulation, &mut previous.accumulation);
+        std::mem::swap(&mut self.iteration_data, &mut previous.iteration_data);
+        std::mem::swap(&mut self.output, &mut previous.output);
+    }
+
+    // Ask for a copy of the output as it is after this frame. Returns the id the copy is
+    // delivered under.
+    pub fn request_capture(&mut self) -> u32 {
+        self.capture = self.capture.wrapping_add(1);
+        self.capture
+    }
+}
+
+// The progressive output copied back for an export, as linear RGBA16F texels.
+pub struct CapturedFrame {
+    pub id: u32,
+    pub size: UVec2,
+    // Rows are padded to the texture copy alignment.
+    pub bytes_per_row: u32,
+    pub data: Vec<u8>,
+}
+
+impl CapturedFrame {
+    // Tightly packed sRGB pixels, ready to be written to an image file.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.size.x as usize * self.size.y as usize * 4);
+        for row in self.data.chunks_exact(self.bytes_per_row as usize) {
+            for texel in row[..self.size.x as usize * 8].chunks_exact(8) {
+                let [r, g, b, a]: [f32; 4] = std::array::from_fn(|channel| {
+                    half_to_f32(u16::from_le_bytes([
+                        texel[2 * channel],
+                        texel[2 * channel + 1],
+                    ]))
+                });
+                let srgb = Color::rgba_linear(r, g, b, a).as_rgba_f32();
+                pixels.extend(srgb.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8));
+            }
+        }
+        pixels
+    }
+}
+
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32 / 1024.0;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-14),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa) * 2f32.powi(exponent - 15),
+    }
+}
+
+// Latest busy pixel count read back from the GPU, with the epoch it was counted in.
+#[derive(Resource, Clone, Default)]
+struct LatestBusyPixels(Arc<Mutex<Option<(u32, u32)>>>);
+
+// Captures handed from the render world to the main world.
+#[derive(Resource, Clone, Default)]
+pub struct CapturedFrames(Arc<Mutex<Option<CapturedFrame>>>);
+
+impl CapturedFrames {
+    // The capture requested under id, once it has arrived.
+    pub fn take(&self, id: u32) -> Option<CapturedFrame> {
+        let mut frame = self.0.lock().ok()?;
+        frame.as_ref().filter(|frame| frame.id == id)?;
+        frame.take()
+    }
+}
+
+// Loaded handles for every entry of PALETTES.
+#[derive(Resource, Default)]
+struct PaletteHandles(Vec<Handle<Image>>);
+
+// Marker for the sprite that shows the progressive output in front of the camera.
+#[derive(Component)]
+pub struct ProgressiveDisplay;
+
+#[derive(Default)]
+pub struct ProgressiveRenderPlugin;
+
+impl Plugin for ProgressiveRenderPlugin {
+    fn build(&self, app: &mut App) {
+        let captured = CapturedFrames::default();
+        let busy_pixels = LatestBusyPixels::default();
+        app.init_resource::<ProgressiveSettings>()
+            .init_resource::<ProgressiveView>()
+            .insert_resource(captured.clone())
+            .insert_resource(busy_pixels.clone())
+            .init_resource::<PaletteHandles>()
+            .add_plugins(ExtractResourcePlugin::<ProgressiveView>::default())
+            .add_systems(Startup, setup_progressive_display)
+            .add_systems(
+                Update,
+                (
+                    progressive_view_system.after(PanCamSystemSet),
+                    material_palette_system,
+                ),
+            );
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(captured)
+            .insert_resource(busy_pixels)
+            .init_resource::<ProgressiveCapture>()
+            .add_systems(
+                Render,
+                (
+                    prepare_progressive_bind_group.in_set(RenderSet::PrepareBindGroups),
+                    (read_progressive_capture, read_busy_pixels).in_set(RenderSet::Cleanup),
+                ),
+            );
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(PROGRESSIVE_NODE, ProgressiveNode::default());
+        render_graph.add_node_edge(
+            PROGRESSIVE_NODE,
+            bevy::render::main_graph::node::CAMERA_DRIVER,
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<ProgressivePipeline>();
+        render_app.init_resource::<ProgressiveUniform>();
+        render_app.init_resource::<ProgressiveBusyPixels>();
+    }
+}
+
+fn setup_progressive_display(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut view: ResMut<ProgressiveView>,
+    mut palettes: ResMut<PaletteHandles>,
+) {
+    palettes.0 = PALETTES
+        .iter()
+        .map(|path| asset_server.load(*path))
+        .collect();
+    view.colormap = palettes.0[0].clone();
+    commands.spawn((
+        SpriteBundle {
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ProgressiveDisplay,
+    ));
+}
+
+// Create a GPU-writable image used for per-pixel state or output.
+fn storage_image(size: UVec2, format: TextureFormat, fill: &[u8]) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        fill,
+        format,
+    );
+    image.texture_descriptor.usage = TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::STORAGE_BINDING
+        | TextureUsages::TEXTURE_BINDING;
+    image
+}
+
+// The fragment shader quad follows the palette too, so it works on either renderer. The fractal
+// materials are rebuilt when the fractal changes, hence checking every frame.
+fn material_palette_system(
+    settings: Res<ProgressiveSettings>,
+    palettes: Res<PaletteHandles>,
+    mut materials: FractalMaterials,
+) {
+    if let Some(colormap) = palettes.0.get(settings.palette) {
+        materials.set_colormap(colormap);
+    }
+}
+
+// System to track the view, resize the state textures and keep the display sprite glued to the camera.
+#[allow(clippy::too_many_arguments)]
+fn progressive_view_system(
+    settings: Res<ProgressiveSettings>,
+    mut view: ResMut<ProgressiveView>,
+    palettes: Res<PaletteHandles>,
+    mut images: ResMut<Assets<Image>>,
+    fractal_type: Res<FractalType>,
+    materials: FractalMaterials,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    input: ActionInput,
+    busy_pixels: Res<LatestBusyPixels>,
+    diagnostics: Res<DiagnosticsStore>,
+    mut adaptive_scale: Local<f32>,
+    time: Res<Time>,
+    // The Julia constant last seen and when it last changed.
+    mut julia_motion: Local<(Vec2, f32)>,
+    camera_query: Query<(&Transform, &OrthographicProjection, &PanCamState)>,
+    mut display_query: Query<
+        (
+            &mut Transform,
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut Visibility,
+        ),
+        (With<ProgressiveDisplay>, Without<PanCamState>),
+    >,
+    mut quad_query: Query<&mut Visibility, (With<FractalQuad>, Without<ProgressiveDisplay>)>,
+) {
+    let Ok((mut display_transform, mut sprite, mut texture, mut display_visibility)) =
+        display_query.get_single_mut()
+    else {
+        return;
+    };
+    if !settings.enabled {
+        view.enabled = false;
+        display_visibility.set_if_neq(Visibility::Hidden);
+        for mut visibility in &mut quad_query {
+            visibility.set_if_neq(Visibility::Inherited);
+        }
+        return;
+    }
+    let (Ok(window), Ok((camera_transform, projection, pancam))) =
+        (primary_window.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    // Textures always match the window; lower resolutions only use part of them.
+    let size = UVec2::new(window.physical_width(), window.physical_height()).max(UVec2::ONE);
+    if images
+        .get(&view.output)
+        .map_or(true, |image| image.size() != size)
+    {
+        view.orbit_state = images.add(storage_image(size, TextureFormat::Rgba32Float, &[0; 16]));
+        view.progress_state = images.add(storage_image(size, TextureFormat::Rgba32Uint, &[0; 16]));
+        view.accumulation = images.add(storage_image(size, TextureFormat::Rgba32Float, &[0; 16]));
+        view.iteration_data = images.add(storage_image(size, TextureFormat::Rgba32Float, &[0; 16]));
+        view.output = images.add(storage_image(
+            size,
+            TextureFormat::Rgba16Float,
+            &[0, 0, 0, 0, 0, 0, 0, 60],
+        ));
+        view.previous = PreviousFrame {
+            orbit_state: images.add(storage_image(size, TextureFormat::Rgba32Float, &[0; 16])),
+            progress_state: images.add(storage_image(size, TextureFormat::Rgba32Uint, &[0; 16])),
+            accumulation: images.add(storage_image(size, TextureFormat::Rgba32Float, &[0; 16])),
+            iteration_data: images.add(storage_image(size, TextureFormat::Rgba32Float, &[0; 16])),
+            output: images.add(storage_image(
+                size,
+                TextureFormat::Rgba16Float,
+                &[0, 0, 0, 0, 0, 0, 0, 60],
+            )),
+        };
+        // Nothing rendered at this size yet, so there is nothing to carry over.
+        view.enabled = false;
+    }
+
+    let latest_busy = busy_pixels
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut latest| latest.take());
+    if latest_busy == Some((view.epoch, 0)) {
+        view.resolved = true;
+    }
+
+    let (color_scale, julia_c, periodicity_tolerance, fractal) = match settings.pinned {
+        Some(pinned) => (
+            pinned.color_scale,
+            pinned.julia_c,
+            pinned.periodicity_tolerance,
+            pinned.fractal,
+        ),
+        None => {
+            let Some(active) = materials.active(&fractal_type) else {
+                return;
+            };
+            (
+                active.color_scale,
+                active.julia_c,
+                active.periodicity_tolerance,
+                active.fractal,
+            )
+        }
+    };
+    let (color_scale, julia_c) =
+        if settings.freeze_parameters && settings.pinned.is_none() && view.enabled {
+            (view.params.color_scale, view.params.julia_c)
+        } else {
+            (color_scale, julia_c)
+        };
+
+    // Only refine with extra samples while the camera is at rest. A pinned frame doesn't move.
+    let camera_still =
+        settings.pinned.is_some() || (!pancam.is_zooming && !input.pressed(Action::Pan));
+
+    if *adaptive_scale <= 0.0 {
+        *adaptive_scale = 1.0;
+    }
+    let min_scale = settings.min_resolution_scale.clamp(0.05, 1.0);
+    let resolution_scale = if settings.dynamic_resolution && !camera_still {
+        if let Some(frame_time) = diagnostics
+            .get(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            .and_then(|frame_time| frame_time.smoothed())
+        {
+            // Cost goes with the pixel count, i.e. the square of the scale; step gently so
+            // the resolution does not oscillate.
+            let ratio = settings.target_frame_time as f64 / frame_time.max(0.1);
+            *adaptive_scale =
+                (*adaptive_scale * (ratio.sqrt() as f32).clamp(0.8, 1.25)).clamp(min_scale, 1.0);
+        }
+        // Quantize so small fluctuations do not reset the image every frame.
+        ((*adaptive_scale * RESOLUTION_STEPS).round() / RESOLUTION_STEPS).clamp(min_scale, 1.0)
+    } else {
+        1.0
+    };
+    let texture_size = size;
+    let size = (texture_size.as_vec2() * resolution_scale)
+        .ceil()
+        .as_uvec2()
+        .clamp(UVec2::ONE, texture_size);
+
+    // Complex units covered by one rendered pixel.
+    let (spacing, center) = match settings.pinned {
+        Some(pinned) => (pinned.pixel_step, pinned.center),
+        None => (
+            projection.scale / window.scale_factor() as f32 / WORLD_UNITS_PER_COMPLEX_UNIT,
+            world_to_complex(camera_transform.translation.truncate()),
+        ),
+    };
+    let pixel_step = spacing * texture_size.as_vec2() / size.as_vec2();
+    // Snap the view to the pixel grid so a pan shifts the image by whole pixels.
+    let anchor = (center.as_dvec2() / pixel_step.as_dvec2()).round();
+    let mut params = ProgressiveParams {
+        origin: (anchor - (size.as_dvec2() / 2.0 - 0.5)).as_vec2() * pixel_step,
+        pixel_step,
+        julia_c,
+        size,
+        fractal,
+        max_iterations: settings.max_iterations.max(1),
+        iteration_budget: settings.iterations_per_frame.max(1),
+        color_scale,
+        periodicity_tolerance,
+        sample_cap: settings.samples_per_pixel.max(1),
+        accumulate: (settings.temporal_accumulation && camera_still) as u32,
+        ..default()
+    };
+
+    let palette = settings
+        .pinned
+        .map_or(settings.palette, |pinned| pinned.palette);
+    let colormap = palettes
+        .0
+        .get(palette)
+        .cloned()
+        .unwrap_or_else(|| view.colormap.clone());
+
+    // Restarting every frame while only the Julia constant moves, e.g. along its path, would never
+    // resolve anything. Keep the current state and let the fragment shader quad show the moving
+    // constant, then restart once it has held still for a moment.
+    let now = time.elapsed_seconds();
+    if julia_c != julia_motion.0 {
+        *julia_motion = (julia_c, now);
+    }
+    // A pinned frame is drawn as asked, however often its constant changes.
+    let julia_settling = view.enabled
+        && settings.pinned.is_none()
+        && view.params.same_view(&params)
+        && !view.params.same_julia_c(&params)
+        && now - julia_motion.1 < JULIA_SETTLE_SECONDS;
+    if julia_settling {
+        params.julia_c = view.params.julia_c;
+    }
+
+    // Keep the reuse settings of the last reset for as long as the geometry holds.
+    params.previous_origin = view.params.previous_origin;
+    params.previous_pixel_step = view.params.previous_pixel_step;
+    params.previous_size = view.params.previous_size;
+    params.reuse_offset = view.params.reuse_offset;
+    params.reuse = view.params.reuse;
+    if !view.enabled || !view.params.same_geometry(&params) {
+        params.previous_origin = view.params.origin;
+        params.previous_pixel_step = view.params.pixel_step;
+        params.previous_size = view.params.size;
+        let shift = anchor - view.anchor;
+        let shifted_view = ProgressiveParams {
+            origin: view.params.origin,
+            ..params
+        };
+        params.reuse = if !view.enabled
+            || params.fractal != view.params.fractal
+            || !params.same_julia_c(&view.params)
+        {
+            REUSE_NONE
+        } else if shifted_view.same_view(&view.params) && shift.abs().cmplt(size.as_dvec2()).all() {
+            params.reuse_offset = shift.as_ivec2();
+            REUSE_PIXELS
+        } else {
+            REUSE_PREVIEW
+        };
+        if params.reuse != REUSE_NONE {
+            view.swap_previous();
+        }
+        view.generation = view.generation.wrapping_add(1);
+        view.restart();
+    } else if params.takes_extra_samples()
+        && (!view.params.takes_extra_samples() || view.params.sample_cap != params.sample_cap)
+    {
+        // The camera came to rest or the sample count was raised after the image had resolved;
+        // pixels pick up sampling where they stopped. Recoloring never needs new samples.
+        view.restart();
+    }
+    view.enabled = true;
+    view.params = params;
+    view.anchor = anchor;
+    view.resolution_scale = resolution_scale;
+    view.colormap = colormap;
+    view.pinned = settings.pinned;
+    view.frames_since_reset = view.frames_since_reset.saturating_add(1);
+
+    // Cover the window in front of the fractal quad, centered where the snapped pixel grid puts the
+    // image rather than on the camera, so pixels don't swim by a fraction of a pixel while panning.
+    let image_center = (anchor * pixel_step.as_dvec2()).as_vec2();
+    display_transform.translation = complex_to_world(image_center).extend(1.0);
+    display_transform.scale = Vec3::new(projection.scale, projection.scale, 1.0);
+    sprite.custom_size = Some(Vec2::new(window.width(), window.height()));
+    // Stretch the rendered part of the texture over the whole window.
+    sprite.rect = Some(Rect::new(0.0, 0.0, size.x as f32, size.y as f32));
+    if *texture != view.output {
+        *texture = view.output.clone();
+    }
+    let (quad_visibility, display) = if julia_settling {
+        (Visibility::Inherited, Visibility::Hidden)
+    } else {
+        (Visibility::Hidden, Visibility::Inherited)
+    };
+    for mut visibility in &mut quad_query {
+        visibility.set_if_neq(quad_visibility);
+    }
+    display_visibility.set_if_neq(display);
+}
+
+#[derive(Resource, Default)]
+struct ProgressiveUniform(UniformBuffer<ProgressiveParams>);
+
+#[derive(Resource)]
+struct ProgressiveBindGroup(BindGroup);
+
+fn prepare_progressive_bind_group(
+    mut commands: Commands,
+    pipeline: Res<ProgressivePipeline>,
+    gpu_images: Res<RenderAssets<Image>>,
+    view: Res<ProgressiveView>,
+    busy_pixels: Res<ProgressiveBusyPixels>,
+    mut uniform: ResMut<ProgressiveUniform>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    commands.remove_resource::<ProgressiveBindGroup>();
+    if !view.enabled {
+        return;
+    }
+    let (
+        Some(orbit_state),
+        Some(progress_state),
+        Some(accumulation),
+        Some(iteration_data),
+        Some(output),
+        Some(colormap),
+    ) = (
+        gpu_images.get(&view.orbit_state),
+        gpu_images.get(&view.progress_state),
+        gpu_images.get(&view.accumulation),
+        gpu_images.get(&view.iteration_data),
+        gpu_images.get(&view.output),
+        gpu_images.get(&view.colormap),
+    )
+    else {
+        return;
+    };
+    let (
+        Some(previous_orbit_state),
+        Some(previous_progress_state),
+        Some(previous_accumulation),
+        Some(previous_iteration_data),
+        Some(previous_output),
+    ) = (
+        gpu_images.get(&view.previous.orbit_state),
+        gpu_images.get(&view.previous.progress_state),
+        gpu_images.get(&view.previous.accumulation),
+        gpu_images.get(&view.previous.iteration_data),
+        gpu_images.get(&view.previous.output),
+    )
+    else {
+        return;
+    };
+
+    uniform.0.set(view.params);
+    uniform.0.write_buffer(&render_device, &render_queue);
+    let Some(params) = uniform.0.binding() else {
+        return;
+    };
+
+    let bind_group = render_device.create_bind_group(
+        "progressive_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((
+            params,
+            &orbit_state.texture_view,
+            &progress_state.texture_view,
+            &output.texture_view,
+            &colormap.texture_view,
+            &colormap.sampler,
+            &accumulation.texture_view,
+            &iteration_data.texture_view,
+            &previous_orbit_state.texture_view,
+            &previous_progress_state.texture_view,
+            &previous_accumulation.texture_view,
+            &previous_iteration_data.texture_view,
+            &previous_output.texture_view,
+            busy_pixels.counter.as_entire_binding(),
+        )),
+    );
+    commands.insert_resource(ProgressiveBindGroup(bind_group));
+}
+
+fn sampled_texture_entry(binding: u32, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Texture {
+            sample_type,
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn storage_texture_entry(
+    binding: u32,
+    access: StorageTextureAccess,
+    format: TextureFormat,
+) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::StorageTexture {
+            access,
+            format,
+            view_dimension: TextureViewDimension::D2,
+        },
+        count: None,
+    }
+}
+
+#[derive(Resource)]
+struct ProgressivePipeline {
+    layout: BindGroupLayout,
+    reset_pipeline: CachedComputePipelineId,
+    iterate_pipeline: CachedComputePipelineId,
+    colorize_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for ProgressivePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("progressive_bind_group_layout"),
+                    entries: &[
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: Some(ProgressiveParams::min_size()),
+                            },
+                            count: None,
+                        },
+                        storage_texture_entry(
+                            1,
+                            StorageTextureAccess::ReadWrite,
+                            TextureFormat::Rgba32Float,
+                        ),
+                        storage_texture_entry(
+                            2,
+                            StorageTextureAccess::ReadWrite,
+                            TextureFormat::Rgba32Uint,
+                        ),
+                        storage_texture_entry(
+                            3,
+                            StorageTextureAccess::WriteOnly,
+                            TextureFormat::Rgba16Float,
+                        ),
+                        sampled_texture_entry(4, TextureSampleType::Float { filterable: true }),
+                        BindGroupLayoutEntry {
+                            binding: 5,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                        storage_texture_entry(
+                            6,
+                            StorageTextureAccess::ReadWrite,
+                            TextureFormat::Rgba32Float,
+                        ),
+                        storage_texture_entry(
+                            7,
+                            StorageTextureAccess::ReadWrite,
+                            TextureFormat::Rgba32Float,
+                        ),
+                        sampled_texture_entry(8, TextureSampleType::Float { filterable: false }),
+                        sampled_texture_entry(9, TextureSampleType::Uint),
+                        sampled_texture_entry(10, TextureSampleType::Float { filterable: false }),
+                        sampled_texture_entry(11, TextureSampleType::Float { filterable: false }),
+                        sampled_texture_entry(12, TextureSampleType::Float { filterable: true }),
+                        BindGroupLayoutEntry {
+                            binding: 13,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: BufferSize::new(BUSY_PIXELS_SIZE),
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let shader = world
+            .resource::<AssetServer>()
+            .load("shaders/progressive_compute.wgsl");
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let queue = |entry_point: &'static str| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(Cow::from(entry_point)),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: vec![],
+                entry_point: Cow::from(entry_point),
+            })
+        };
+        let reset_pipeline = queue("reset");
+        let iterate_pipeline = queue("iterate");
+        let colorize_pipeline = queue("colorize");
+
+        ProgressivePipeline {
+            layout,
+            reset_pipeline,
+            iterate_pipeline,
+            colorize_pipeline,
+        }
+    }
+}
+
+// Counter the iterate pass adds every busy pixel to, and its readback. The count tells the main
+// world when the image has resolved, which is usually long before frames_to_finish.
+#[derive(Resource)]
+struct ProgressiveBusyPixels {
+    counter: Buffer,
+    readback: Readback,
+    // Epoch of the count being copied or mapped.
+    in_flight: Option<u32>,
+}
+
+impl FromWorld for ProgressiveBusyPixels {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        ProgressiveBusyPixels {
+            counter: render_device.create_buffer(&BufferDescriptor {
+                label: Some("progressive_busy_pixels"),
+                size: BUSY_PIXELS_SIZE,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            readback: Readback::new(
+                render_device,
+                "progressive_busy_pixels_readback",
+                BUSY_PIXELS_SIZE,
+            ),
+            in_flight: None,
+        }
+    }
+}
+
+// Hand busy pixel counts to the main world.
+fn read_busy_pixels(
+    mut busy_pixels: ResMut<ProgressiveBusyPixels>,
+    render_device: Res<RenderDevice>,
+    latest: Res<LatestBusyPixels>,
+) {
+    let Some(data) = busy_pixels.readback.poll(&render_device) else {
+        return;
+    };
+    let (Some(epoch), Ok(count)) = (busy_pixels.in_flight.take(), data[..4].try_into()) else {
+        return;
+    };
+    if let Ok(mut latest) = latest.0.lock() {
+        *latest = Some((epoch, u32::from_le_bytes(count)));
+    }
+}
+
+// Render world side of output captures.
+#[derive(Resource, Default)]
+struct ProgressiveCapture {
+    readback: Option<Readback>,
+    // Id, size and row pitch of the capture being copied or mapped.
+    in_flight: Option<(u32, UVec2, u32)>,
+    // Last capture id served, so every request is copied once.
+    served: u32,
+}
+
+impl ProgressiveCapture {
+    // Claim the readback buffer for a copy of the output this frame, if a capture is wanted.
+    fn begin(&mut self, render_device: &RenderDevice, id: u32, size: UVec2) -> bool {
+        if id == self.served || self.readback.as_ref().is_some_and(|r| !r.is_free()) {
+            return false;
+        }
+        let bytes_per_row = RenderDevice::align_copy_bytes_per_row(size.x as usize * 8) as u32;
+        let buffer_size = bytes_per_row as u64 * size.y as u64;
+        if self
+            .readback
+            .as_ref()
+            .map_or(true, |readback| readback.buffer().size() != buffer_size)
+        {
+            self.readback = Some(Readback::new(
+                render_device,
+                "progressive_capture",
+                buffer_size,
+            ));
+        }
+        self.served = id;
+        self.in_flight = Some((id, size, bytes_per_row));
+        self.readback.as_mut().is_some_and(Readback::begin_copy)
+    }
+}
+
+// Hand finished output copies to the main world.
+fn read_progressive_capture(
+    mut capture: ResMut<ProgressiveCapture>,
+    render_device: Res<RenderDevice>,
+    captured: Res<CapturedFrames>,
+) {
+    let Some(readback) = capture.readback.as_mut() else {
+        return;
+    };
+    let data = readback.poll(&render_device);
+    if !readback.is_free() {
+        return;
+    }
+    let Some((id, size, bytes_per_row)) = capture.in_flight.take() else {
+        return;
+    };
+    match data {
+        Some(data) => {
+            if let Ok(mut frame) = captured.0.lock() {
+                *frame = Some(CapturedFrame {
+                    id,
+                    size,
+                    bytes_per_row,
+                    data,
+                });
+            }
+        }
+        // The map failed; copy again next frame.
+        None => capture.served = id.wrapping_sub(1),
+    }
+}
+
+// Render graph node dispatching the reset, iterate and color passes ahead of the cameras.
+#[derive(Default)]
+struct ProgressiveNode {
+    ready: bool,
+    generation: Option<u32>,
+    reset: bool,
+    // False once every pixel has resolved, leaving only the color pass to run.
+    iterate: bool,
+    // Count the pixels the iterate pass leaves busy.
+    count_busy: bool,
+    // Copy the output into the capture readback after the color pass.
+    capture: bool,
+}
+
+impl render_graph::Node for ProgressiveNode {
+    fn update(&mut self, world: &mut World) {
+        let pipeline = world.resource::<ProgressivePipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let view = world.resource::<ProgressiveView>();
+
+        self.ready = view.enabled
+            && world.contains_resource::<ProgressiveBindGroup>()
+            && [
+                pipeline.reset_pipeline,
+                pipeline.iterate_pipeline,
+                pipeline.colorize_pipeline,
+            ]
+            .into_iter()
+            .all(|id| {
+                matches!(
+                    pipeline_cache.get_compute_pipeline_state(id),
+                    CachedPipelineState::Ok(_)
+                )
+            });
+        self.count_busy = false;
+        self.capture = false;
+        if !self.ready {
+            return;
+        }
+        self.reset = self.generation != Some(view.generation);
+        self.generation = Some(view.generation);
+        self.iterate = self.reset || !view.is_finished();
+
+        let (epoch, capture, size) = (view.epoch, view.capture, view.params.size);
+        let mut busy_pixels = world.resource_mut::<ProgressiveBusyPixels>();
+        self.count_busy = self.iterate && busy_pixels.readback.begin_copy();
+        if self.count_busy {
+            busy_pixels.in_flight = Some(epoch);
+        }
+        let render_device = world.resource::<RenderDevice>().clone();
+        self.capture =
+            world
+                .resource_mut::<ProgressiveCapture>()
+                .begin(&render_device, capture, size);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        if !self.ready {
+            return Ok(());
+        }
+        let view = world.resource::<ProgressiveView>();
+        let bind_group = &world.resource::<ProgressiveBindGroup>().0;
+        let pipeline = world.resource::<ProgressivePipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let (Some(reset_pipeline), Some(iterate_pipeline), Some(colorize_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(pipeline.reset_pipeline),
+            pipeline_cache.get_compute_pipeline(pipeline.iterate_pipeline),
+            pipeline_cache.get_compute_pipeline(pipeline.colorize_pipeline),
+        ) else {
+            return Ok(());
+        };
+
+        let busy_pixels = world.resource::<ProgressiveBusyPixels>();
+        if self.count_busy {
+            render_context
+                .command_encoder()
+                .clear_buffer(&busy_pixels.counter, 0, None);
+        }
+        let workgroups = (view.params.size + (WORKGROUP_SIZE - 1)) / WORKGROUP_SIZE;
+        {
+            let mut pass = render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor::default());
+            pass.set_bind_group(0, bind_group, &[]);
+            if self.reset {
+                pass.set_pipeline(reset_pipeline);
+                pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+            }
+            if self.iterate {
+                pass.set_pipeline(iterate_pipeline);
+                pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+            }
+            pass.set_pipeline(colorize_pipeline);
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+        if self.count_busy {
+            render_context.command_encoder().copy_buffer_to_buffer(
+                &busy_pixels.counter,
+                0,
+                busy_pixels.readback.buffer(),
+                0,
+                BUSY_PIXELS_SIZE,
+            );
+        }
+
+        if self.capture {
+            copy_output_to_capture(
+                render_context,
+                world.resource::<RenderAssets<Image>>(),
+                view,
+                world.resource::<ProgressiveCapture>(),
+            );
+        }
+        Ok(())
+    }
+}
+
+// Record the copy of the rendered part of the output into the capture readback.
+fn copy_output_to_capture(
+    render_context: &mut RenderContext,
+    gpu_images: &RenderAssets<Image>,
+    view: &ProgressiveView,
+    capture: &ProgressiveCapture,
+) {
+    let (Some(output), Some(readback), Some((_, size, bytes_per_row))) = (
+        gpu_images.get(&view.output),
+        &capture.readback,
+        capture.in_flight,
+    ) else {
+        return;
+    };
+    render_context.command_encoder().copy_texture_to_buffer(
+        output.texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: readback.buffer(),
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> ProgressiveParams {
+        ProgressiveParams {
+            origin: Vec2::new(-1.25, 0.5),
+            pixel_step: Vec2::splat(0.002),
+            julia_c: Vec2::new(0.3, 0.8),
+            size: UVec2::new(800, 600),
+            max_iterations: 1000,
+            periodicity_tolerance: 1e-5,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn float_noise_keeps_the_geometry() {
+        let base = params();
+        let noisy = ProgressiveParams {
+            origin: base.origin + Vec2::splat(1e-8),
+            pixel_step: base.pixel_step * (1.0 + 1e-6),
+            ..base
+        };
+        assert!(base.same_geometry(&noisy));
+        // Coloring alone never changes the geometry.
+        assert!(base.same_geometry(&ProgressiveParams {
+            color_scale: 0.9,
+            ..base
+        }));
+    }
+
+    #[test]
+    fn real_changes_restart_the_image() {
+        let base = params();
+        let panned = ProgressiveParams {
+            origin: base.origin + base.pixel_step,
+            ..base
+        };
+        assert!(!base.same_geometry(&panned));
+        let moved_c = ProgressiveParams {
+            julia_c: base.julia_c + Vec2::new(1e-4, 0.0),
+            ..base
+        };
+        assert!(base.same_view(&moved_c));
+        assert!(!base.same_geometry(&moved_c));
+    }
+
+    #[test]
+    fn captures_unpad_rows_and_encode_srgb() {
+        // Two texels per row padded to eight: half precision 1.0, 0.0, 0.5 and 1.0.
+        let texel = [0x00, 0x3c, 0x00, 0x00, 0x00, 0x38, 0x00, 0x3c];
+        let mut data = Vec::new();
+        for _ in 0..2 {
+            data.extend(texel);
+            data.extend(texel);
+            data.extend([0; 48]);
+        }
+        let frame = CapturedFrame {
+            id: 1,
+            size: UVec2::new(2, 2),
+            bytes_per_row: 64,
+            data,
+        };
+        let pixels = frame.to_rgba8();
+        assert_eq!(pixels.len(), 16);
+        // Linear 0.5 is sRGB 188.
+        assert_eq!(&pixels[..4], &[255, 0, 188, 255]);
+        assert!(pixels.chunks_exact(4).all(|pixel| pixel == &pixels[..4]));
+    }
+}
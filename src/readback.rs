@@ -0,0 +1,78 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+
+use bevy::render::render_resource::{Buffer, BufferDescriptor, BufferUsages, MapMode};
+use bevy::render::renderer::RenderDevice;
+use wgpu::{BufferAsyncError, Maintain};
+
+// Mappable buffer GPU results are copied into and read on the CPU without stalling a frame. The
+// buffer is mapped once the frame that recorded the copy was submitted, and the data is picked up
+// on whichever later frame the map completes.
+pub struct Readback {
+    buffer: Buffer,
+    // A copy into the buffer was recorded this frame.
+    pending: bool,
+    // Where the map result arrives. No new copy may be recorded until the buffer is free again.
+    mapping: Option<Mutex<Receiver<Result<(), BufferAsyncError>>>>,
+}
+
+impl Readback {
+    pub fn new(render_device: &RenderDevice, label: &'static str, size: u64) -> Self {
+        Readback {
+            buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            pending: false,
+            mapping: None,
+        }
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    // Whether a new copy may be recorded into the buffer.
+    pub fn is_free(&self) -> bool {
+        !self.pending && self.mapping.is_none()
+    }
+
+    // Claim the buffer for a copy recorded this frame. Returns false while an earlier copy is
+    // still being read.
+    pub fn begin_copy(&mut self) -> bool {
+        let free = self.is_free();
+        self.pending |= free;
+        free
+    }
+
+    // Call once per frame after the frame's commands were submitted, e.g. in RenderSet::Cleanup.
+    // Returns the buffer contents once a recorded copy has been mapped.
+    pub fn poll(&mut self, render_device: &RenderDevice) -> Option<Vec<u8>> {
+        if std::mem::take(&mut self.pending) {
+            let (sender, receiver) = mpsc::channel();
+            self.buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    let _ = sender.send(result);
+                });
+            self.mapping = Some(Mutex::new(receiver));
+        }
+        let receiver = self.mapping.as_ref()?;
+        render_device.wgpu_device().poll(Maintain::Poll);
+        let result = receiver.lock().ok()?.try_recv();
+        let data = match result {
+            Err(TryRecvError::Empty) => return None,
+            Ok(Ok(())) => {
+                let data = self.buffer.slice(..).get_mapped_range().to_vec();
+                self.buffer.unmap();
+                Some(data)
+            }
+            // A failed map leaves the buffer unmapped; the next copy simply tries again.
+            Ok(Err(_)) | Err(TryRecvError::Disconnected) => None,
+        };
+        self.mapping = None;
+        data
+    }
+}
@@ -0,0 +1,733 @@
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::fractals::{AnimationUpdateToggle, FractalType};
+use crate::materials::{ActiveFractalParams, FractalMaterials};
+use crate::pancam::{PanCamState, PanCamSystemSet};
+
+// Keys closer together than this, in seconds, are the same key.
+const KEY_TIME_EPSILON: f32 = 1e-3;
+// Size of the strip each track's keys are drawn on, in points.
+const STRIP_WIDTH: f32 = 320.0;
+const STRIP_HEIGHT: f32 = 14.0;
+// Clicking within this many points of a key selects it.
+const KEY_PICK_DISTANCE: f32 = 6.0;
+
+// The default timeline swings each fractal's color scale across its range and back once per loop,
+// the ranges the fractals were animated over before there was a timeline.
+const DEFAULT_COLOR_SCALE_RANGES: [(FractalType, (f32, f32)); 3] = [
+    (FractalType::Mandelbrot, (0.05, 0.95)),
+    (FractalType::Julia, (0.05, 0.95)),
+    (FractalType::BurningShip, (0.0, 0.70)),
+];
+// Length of the default timeline in seconds.
+const DEFAULT_DURATION: f32 = 120.0;
+
+// A parameter the timeline can animate.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Track {
+    ColorScale,
+    MaxIterations,
+    PeriodicityTolerance,
+    JuliaCx,
+    JuliaCy,
+    CameraX,
+    CameraY,
+    CameraZoom,
+    CameraRotation,
+}
+
+impl Track {
+    pub const ALL: [Track; 9] = [
+        Track::ColorScale,
+        Track::MaxIterations,
+        Track::PeriodicityTolerance,
+        Track::JuliaCx,
+        Track::JuliaCy,
+        Track::CameraX,
+        Track::CameraY,
+        Track::CameraZoom,
+        Track::CameraRotation,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Track::ColorScale => "Color scale",
+            Track::MaxIterations => "Iterations",
+            Track::PeriodicityTolerance => "Periodicity tolerance",
+            Track::JuliaCx => "Julia c.x",
+            Track::JuliaCy => "Julia c.y",
+            Track::CameraX => "Camera x",
+            Track::CameraY => "Camera y",
+            Track::CameraZoom => "Camera zoom",
+            Track::CameraRotation => "Camera rotation",
+        }
+    }
+
+    // Tracks spanning orders of magnitude are eased in log space, so a zoom keeps a steady pace.
+    fn logarithmic(self) -> bool {
+        matches!(
+            self,
+            Track::MaxIterations | Track::PeriodicityTolerance | Track::CameraZoom
+        )
+    }
+
+    fn is_camera(self) -> bool {
+        matches!(
+            self,
+            Track::CameraX | Track::CameraY | Track::CameraZoom | Track::CameraRotation
+        )
+    }
+
+    // The value the track has right now, for setting a key on it.
+    fn current(
+        self,
+        params: &ActiveFractalParams,
+        transform: &Transform,
+        proj: &OrthographicProjection,
+    ) -> f32 {
+        match self {
+            Track::ColorScale => params.color_scale,
+            Track::MaxIterations => params.max_iterations,
+            Track::PeriodicityTolerance => params.periodicity_tolerance,
+            Track::JuliaCx => params.julia_c.x,
+            Track::JuliaCy => params.julia_c.y,
+            Track::CameraX => transform.translation.x,
+            Track::CameraY => transform.translation.y,
+            Track::CameraZoom => proj.scale,
+            Track::CameraRotation => transform.rotation.to_euler(EulerRot::XYZ).2,
+        }
+    }
+}
+
+// How a segment gets from one key to the next.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Easing {
+    // Keep the key's value until the next key.
+    Hold,
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    // Half a cosine wave, so alternating keys make a sine oscillation.
+    Sine,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 6] = [
+        Easing::Hold,
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+        Easing::Sine,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Easing::Hold => "Hold",
+            Easing::Linear => "Linear",
+            Easing::EaseIn => "Ease in",
+            Easing::EaseOut => "Ease out",
+            Easing::EaseInOut => "Ease in-out",
+            Easing::Sine => "Sine",
+        }
+    }
+
+    // Map progress through a segment, 0 to 1, to progress between the values.
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Hold => 0.0,
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+                }
+            }
+            Easing::Sine => 0.5 * (1.0 - (PI * t).cos()),
+        }
+    }
+}
+
+// Where a track's keys are kept. Each fractal has its own material, so its parameter tracks are
+// its own too; the camera tracks are shared.
+type Lane = (Option<FractalType>, Track);
+
+fn lane(fractal: FractalType, track: Track) -> Lane {
+    (Some(fractal).filter(|_| !track.is_camera()), track)
+}
+
+// A value at a time, and the easing of the segment that follows it.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: f32,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+// Keyframed animation of fractal parameters and the camera. Tracks without keys are left alone.
+// Saved in the session file with the view history.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeline {
+    // Length in seconds.
+    pub duration: f32,
+    pub looping: bool,
+    // Keys of each track, sorted by time.
+    tracks: BTreeMap<Lane, Vec<Keyframe>>,
+    // Edited since the session was last saved.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl Default for Timeline {
+    // The color oscillation the app has always started with. The Julia constant follows the
+    // path set up in the Julia Path window instead.
+    fn default() -> Self {
+        let tracks = DEFAULT_COLOR_SCALE_RANGES
+            .into_iter()
+            .map(|(fractal, (low, high))| {
+                let swing = [0.0, 0.5, 1.0].map(|at| Keyframe {
+                    time: at * DEFAULT_DURATION,
+                    value: if at == 0.5 { high } else { low },
+                    easing: Easing::Sine,
+                });
+                (lane(fractal, Track::ColorScale), swing.to_vec())
+            })
+            .collect();
+        Timeline {
+            duration: DEFAULT_DURATION,
+            looping: true,
+            tracks,
+            dirty: false,
+        }
+    }
+}
+
+impl Timeline {
+    // The keys of a track while `fractal` is shown.
+    pub fn keys(&self, fractal: FractalType, track: Track) -> &[Keyframe] {
+        self.tracks
+            .get(&lane(fractal, track))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn has_keys(&self, fractal: FractalType, track: Track) -> bool {
+        !self.keys(fractal, track).is_empty()
+    }
+
+    // Set a key at `time`, replacing one already there. Returns its index.
+    pub fn set_key(&mut self, fractal: FractalType, track: Track, time: f32, value: f32) -> usize {
+        let keys = self.tracks.entry(lane(fractal, track)).or_default();
+        if let Some(index) = keys
+            .iter()
+            .position(|key| (key.time - time).abs() < KEY_TIME_EPSILON)
+        {
+            keys[index].value = value;
+            return index;
+        }
+        let index = keys.partition_point(|key| key.time < time);
+        // A new key eases like the segment it splits.
+        let easing = index
+            .checked_sub(1)
+            .map_or(Easing::default(), |previous| keys[previous].easing);
+        keys.insert(
+            index,
+            Keyframe {
+                time,
+                value,
+                easing,
+            },
+        );
+        index
+    }
+
+    // Replace a key, moving it into place if its time changed. Returns its new index.
+    pub fn update_key(
+        &mut self,
+        fractal: FractalType,
+        track: Track,
+        index: usize,
+        key: Keyframe,
+    ) -> usize {
+        let keys = self.tracks.entry(lane(fractal, track)).or_default();
+        keys.remove(index);
+        let index = keys.partition_point(|other| other.time < key.time);
+        keys.insert(index, key);
+        index
+    }
+
+    pub fn remove_key(&mut self, fractal: FractalType, track: Track, index: usize) {
+        let lane = lane(fractal, track);
+        if let Some(keys) = self.tracks.get_mut(&lane) {
+            keys.remove(index);
+            if keys.is_empty() {
+                self.tracks.remove(&lane);
+            }
+        }
+    }
+
+    // The track's value at `time`, or None when it has no keys. Holds the end values outside them.
+    pub fn sample(&self, fractal: FractalType, track: Track, time: f32) -> Option<f32> {
+        let keys = self.keys(fractal, track);
+        let next = keys.partition_point(|key| key.time <= time);
+        if next == 0 {
+            return keys.first().map(|key| key.value);
+        }
+        let from = keys[next - 1];
+        let Some(to) = keys.get(next) else {
+            return Some(from.value);
+        };
+        let t = from
+            .easing
+            .apply((time - from.time) / (to.time - from.time));
+        if track.logarithmic() && from.value > 0.0 && to.value > 0.0 {
+            Some((from.value.ln() + (to.value.ln() - from.value.ln()) * t).exp())
+        } else {
+            Some(from.value + (to.value - from.value) * t)
+        }
+    }
+}
+
+// Where playback is. Whether it runs is AnimationUpdateToggle, so the existing pause paths apply.
+#[derive(Resource)]
+pub struct Playhead {
+    // Seconds into the timeline.
+    pub time: f32,
+    // Playback rate, 1 being real time.
+    pub speed: f32,
+}
+
+impl Default for Playhead {
+    fn default() -> Self {
+        Playhead {
+            time: 0.0,
+            speed: 1.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        // The history plugin loads the saved timeline with the session; this is the fallback.
+        app.init_resource::<Timeline>()
+            .init_resource::<Playhead>()
+            .add_systems(
+                Update,
+                (
+                    timeline_playback_system.before(PanCamSystemSet),
+                    timeline_ui_system,
+                ),
+            );
+    }
+}
+
+// Advance the playhead while playing and apply the keyed tracks whenever it moves.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn timeline_playback_system(
+    time: Res<Time>,
+    timeline: Res<Timeline>,
+    mut playhead: ResMut<Playhead>,
+    mut animation: ResMut<AnimationUpdateToggle>,
+    fractal_type: Res<FractalType>,
+    mut materials: FractalMaterials,
+    mut cameras: Query<(
+        &mut PanCamState,
+        &mut OrthographicProjection,
+        &mut Transform,
+    )>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    if animation.active {
+        // Playback never settles, so keep waking the app when it updates reactively.
+        redraw.send(RequestRedraw);
+        playhead.time += time.delta_seconds() * playhead.speed;
+        if playhead.time >= timeline.duration {
+            if timeline.looping && timeline.duration > 0.0 {
+                playhead.time = playhead.time.rem_euclid(timeline.duration);
+            } else {
+                playhead.time = timeline.duration;
+                animation.active = false;
+            }
+        }
+    } else if !playhead.is_changed() {
+        return;
+    }
+    let now = playhead.time;
+    let fractal = *fractal_type;
+
+    let parameter_tracks = Track::ALL.iter().filter(|track| !track.is_camera());
+    if parameter_tracks
+        .clone()
+        .any(|track| timeline.has_keys(fractal, *track))
+    {
+        if let Some(mut params) = materials.active(&fractal_type) {
+            for track in parameter_tracks {
+                let Some(value) = timeline.sample(fractal, *track, now) else {
+                    continue;
+                };
+                match track {
+                    Track::ColorScale => params.color_scale = value,
+                    Track::MaxIterations => params.max_iterations = value,
+                    Track::PeriodicityTolerance => params.periodicity_tolerance = value,
+                    Track::JuliaCx => params.julia_c.x = value,
+                    Track::JuliaCy => params.julia_c.y = value,
+                    _ => {}
+                }
+            }
+            materials.restore(&fractal_type, &params);
+        }
+    }
+
+    if !Track::ALL
+        .iter()
+        .any(|track| track.is_camera() && timeline.has_keys(fractal, *track))
+    {
+        return;
+    }
+    let Ok((mut cam, mut proj, mut transform)) = cameras.get_single_mut() else {
+        return;
+    };
+    if let Some(x) = timeline.sample(fractal, Track::CameraX, now) {
+        transform.translation.x = x;
+    }
+    if let Some(y) = timeline.sample(fractal, Track::CameraY, now) {
+        transform.translation.y = y;
+    }
+    if let Some(angle) = timeline.sample(fractal, Track::CameraRotation, now) {
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+    if let Some(scale) = timeline.sample(fractal, Track::CameraZoom, now) {
+        proj.scale = scale;
+    }
+    // The timeline drives the camera directly; drop whatever it was easing or gliding towards.
+    cam.target_zoom = proj.scale;
+    cam.target_translation = None;
+    cam.is_zooming = false;
+    cam.target_locked = false;
+    cam.stop_glide();
+}
+
+// The Timeline window: transport controls, a strip of keys per track and the selected key.
+#[allow(clippy::too_many_arguments)]
+fn timeline_ui_system(
+    mut ctx: EguiContexts,
+    mut timeline: ResMut<Timeline>,
+    mut playhead: ResMut<Playhead>,
+    mut animation: ResMut<AnimationUpdateToggle>,
+    fractal_type: Res<FractalType>,
+    materials: FractalMaterials,
+    cameras: Query<(&OrthographicProjection, &Transform), With<PanCamState>>,
+    mut selected: Local<Option<(Track, usize)>>,
+) {
+    // Widgets edit copies, so the resources only change, and get applied or saved, on real edits.
+    let mut edited = timeline.clone();
+    let mut time = playhead.time;
+    let mut speed = playhead.speed;
+    let mut playing = animation.active;
+    let params = materials.active(&fractal_type);
+    let camera = cameras.get_single().ok();
+    let fractal = *fractal_type;
+    // The parameter keys shown belong to the fractal on screen.
+    if fractal_type.is_changed() {
+        *selected = None;
+    }
+
+    egui::Window::new("Timeline")
+        .default_open(false)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(if playing { "Pause" } else { "Play" }).clicked() {
+                    playing = !playing;
+                    // Playing again from the end of a timeline that doesn't loop starts over.
+                    if playing && time >= edited.duration {
+                        time = 0.0;
+                    }
+                }
+                ui.checkbox(&mut edited.looping, "Loop");
+                ui.label("Speed:");
+                ui.add(
+                    egui::DragValue::new(&mut speed)
+                        .speed(0.01)
+                        .clamp_range(0.0..=10.0)
+                        .suffix("x"),
+                );
+                ui.label("Length:");
+                ui.add(
+                    egui::DragValue::new(&mut edited.duration)
+                        .speed(0.1)
+                        .clamp_range(0.1..=3600.0)
+                        .suffix(" s"),
+                );
+                if ui
+                    .button("Default")
+                    .on_hover_text("Replace every track with the built-in color swings")
+                    .clicked()
+                {
+                    edited = Timeline::default();
+                    *selected = None;
+                }
+            });
+            ui.add(
+                egui::Slider::new(&mut time, 0.0..=edited.duration)
+                    .suffix(" s")
+                    .text("Time"),
+            );
+
+            egui::Grid::new("timeline_tracks").show(ui, |ui| {
+                for track in Track::ALL {
+                    ui.label(track.label());
+                    let can_key = params.is_some() && (camera.is_some() || !track.is_camera());
+                    if ui
+                        .add_enabled(can_key, egui::Button::new("Key"))
+                        .on_hover_text("Set a key with the current value at the playhead")
+                        .clicked()
+                    {
+                        if let (Some(params), Some((proj, transform))) = (&params, camera) {
+                            let value = track.current(params, transform, proj);
+                            let index = edited.set_key(fractal, track, time, value);
+                            *selected = Some((track, index));
+                        }
+                    }
+                    if let Some(clicked) =
+                        key_strip(ui, &edited, fractal, track, *selected, &mut time)
+                    {
+                        *selected = Some((track, clicked));
+                    }
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            let Some((track, index)) = *selected else {
+                ui.label("Click a key to edit it.");
+                return;
+            };
+            let Some(mut key) = edited.keys(fractal, track).get(index).copied() else {
+                *selected = None;
+                return;
+            };
+            ui.horizontal(|ui| {
+                ui.label(format!("{} key", track.label()));
+                ui.label("at");
+                let mut changed = ui
+                    .add(
+                        egui::DragValue::new(&mut key.time)
+                            .speed(0.05)
+                            .clamp_range(0.0..=edited.duration)
+                            .suffix(" s"),
+                    )
+                    .changed();
+                ui.label("value");
+                let value_speed = (key.value.abs() * 0.01).max(1e-4);
+                changed |= ui
+                    .add(egui::DragValue::new(&mut key.value).speed(value_speed))
+                    .changed();
+                egui::ComboBox::from_id_source("timeline_easing")
+                    .selected_text(key.easing.label())
+                    .show_ui(ui, |ui| {
+                        for easing in Easing::ALL {
+                            changed |= ui
+                                .selectable_value(&mut key.easing, easing, easing.label())
+                                .changed();
+                        }
+                    })
+                    .response
+                    .on_hover_text("Easing towards the next key");
+                if changed {
+                    *selected = Some((track, edited.update_key(fractal, track, index, key)));
+                }
+                if ui.button("Delete").clicked() {
+                    edited.remove_key(fractal, track, index);
+                    *selected = None;
+                }
+            });
+        });
+
+    if edited.duration != timeline.duration
+        || edited.looping != timeline.looping
+        || edited.tracks != timeline.tracks
+    {
+        edited.dirty = true;
+        *timeline = edited;
+    }
+    if time != playhead.time {
+        playhead.time = time;
+    }
+    if speed != playhead.speed {
+        playhead.speed = speed;
+    }
+    if playing != animation.active {
+        animation.active = playing;
+    }
+}
+
+// One track's keys for `fractal` across the timeline, with the playhead at `time`. Clicking a key returns its
+// index; clicking elsewhere moves the playhead there.
+fn key_strip(
+    ui: &mut egui::Ui,
+    timeline: &Timeline,
+    fractal: FractalType,
+    track: Track,
+    selected: Option<(Track, usize)>,
+    time: &mut f32,
+) -> Option<usize> {
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(STRIP_WIDTH, STRIP_HEIGHT), egui::Sense::click());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    let duration = timeline.duration.max(f32::EPSILON);
+    let x_at = |time: f32| rect.left() + (time / duration).clamp(0.0, 1.0) * rect.width();
+
+    let keys = timeline.keys(fractal, track);
+    for (index, key) in keys.iter().enumerate() {
+        let center = egui::pos2(x_at(key.time), rect.center().y);
+        let color = if selected == Some((track, index)) {
+            visuals.selection.bg_fill
+        } else {
+            visuals.strong_text_color()
+        };
+        let half = STRIP_HEIGHT * 0.35;
+        painter.add(egui::Shape::convex_polygon(
+            vec![
+                center - egui::vec2(0.0, half),
+                center + egui::vec2(half, 0.0),
+                center + egui::vec2(0.0, half),
+                center - egui::vec2(half, 0.0),
+            ],
+            color,
+            egui::Stroke::NONE,
+        ));
+    }
+    let x = x_at(*time);
+    painter.line_segment(
+        [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+        egui::Stroke::new(1.5, egui::Color32::from_rgb(230, 80, 60)),
+    );
+
+    let pointer = response
+        .interact_pointer_pos()
+        .filter(|_| response.clicked())?;
+    let nearest = keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| (index, (x_at(key.time) - pointer.x).abs()))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .filter(|(_, distance)| *distance <= KEY_PICK_DISTANCE);
+    if let Some((index, _)) = nearest {
+        return Some(index);
+    }
+    *time = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0) * duration;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANDELBROT: FractalType = FractalType::Mandelbrot;
+
+    #[test]
+    fn sample_eases_between_keys_and_holds_the_ends() {
+        let mut timeline = Timeline {
+            tracks: BTreeMap::new(),
+            ..default()
+        };
+        assert_eq!(timeline.sample(MANDELBROT, Track::ColorScale, 1.0), None);
+        timeline.set_key(MANDELBROT, Track::ColorScale, 2.0, 0.5);
+        timeline.set_key(MANDELBROT, Track::ColorScale, 0.0, 0.1);
+        assert_eq!(
+            timeline.sample(MANDELBROT, Track::ColorScale, -1.0),
+            Some(0.1)
+        );
+        assert!((timeline.sample(MANDELBROT, Track::ColorScale, 1.0).unwrap() - 0.3).abs() < 1e-6);
+        assert_eq!(
+            timeline.sample(MANDELBROT, Track::ColorScale, 5.0),
+            Some(0.5)
+        );
+
+        let mut hold = timeline.keys(MANDELBROT, Track::ColorScale)[0];
+        hold.easing = Easing::Hold;
+        timeline.update_key(MANDELBROT, Track::ColorScale, 0, hold);
+        assert_eq!(
+            timeline.sample(MANDELBROT, Track::ColorScale, 1.9),
+            Some(0.1)
+        );
+    }
+
+    #[test]
+    fn zoom_keys_ease_in_log_space() {
+        let mut timeline = Timeline {
+            tracks: BTreeMap::new(),
+            ..default()
+        };
+        timeline.set_key(MANDELBROT, Track::CameraZoom, 0.0, 1.0);
+        timeline.set_key(MANDELBROT, Track::CameraZoom, 1.0, 100.0);
+        assert!((timeline.sample(MANDELBROT, Track::CameraZoom, 0.5).unwrap() - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn default_timeline_loops_seamlessly() {
+        let timeline = Timeline::default();
+        let start = timeline.sample(MANDELBROT, Track::ColorScale, 0.0).unwrap();
+        let end = timeline
+            .sample(MANDELBROT, Track::ColorScale, timeline.duration)
+            .unwrap();
+        assert!((start - end).abs() < 1e-5);
+        assert_eq!(timeline.sample(MANDELBROT, Track::JuliaCx, 0.0), None);
+    }
+
+    #[test]
+    fn default_timeline_swings_each_fractal_over_its_range() {
+        let timeline = Timeline::default();
+        let half = timeline.duration / 2.0;
+        for (fractal, (low, high)) in DEFAULT_COLOR_SCALE_RANGES {
+            assert_eq!(timeline.sample(fractal, Track::ColorScale, 0.0), Some(low));
+            assert_eq!(
+                timeline.sample(fractal, Track::ColorScale, half),
+                Some(high)
+            );
+        }
+        assert_eq!(
+            timeline.sample(FractalType::BurningShip, Track::ColorScale, half),
+            Some(0.70)
+        );
+    }
+
+    #[test]
+    fn parameter_keys_belong_to_one_fractal_and_camera_keys_to_all() {
+        let mut timeline = Timeline {
+            tracks: BTreeMap::new(),
+            ..default()
+        };
+        timeline.set_key(FractalType::Julia, Track::MaxIterations, 0.0, 500.0);
+        timeline.set_key(FractalType::Julia, Track::CameraX, 0.0, 3.0);
+        assert_eq!(
+            timeline.sample(FractalType::Julia, Track::MaxIterations, 1.0),
+            Some(500.0)
+        );
+        assert_eq!(timeline.sample(MANDELBROT, Track::MaxIterations, 1.0), None);
+        assert_eq!(timeline.sample(MANDELBROT, Track::CameraX, 1.0), Some(3.0));
+        // The per-fractal keys survive the session file.
+        let text = ron::to_string(&timeline).unwrap();
+        let loaded: Timeline = ron::from_str(&text).unwrap();
+        assert_eq!(loaded.tracks, timeline.tracks);
+        timeline.remove_key(MANDELBROT, Track::CameraX, 0);
+        assert!(!timeline.has_keys(FractalType::Julia, Track::CameraX));
+    }
+}
@@ -1,13 +1,46 @@
 
-use crate::fractals::AnimationSpeed;
+use crate::auto_iterations::AutoIterations;
+use crate::buddhabrot::{BuddhabrotSettings, BuddhabrotState, DensityMode, ToneMapping};
+use crate::export::{ExportSettings, ExportState};
+use crate::fractals::FractalType;
+use crate::idle::IdleMode;
+use crate::materials::edit_materials;
 use crate::JuliaMaterial;
 use crate::MandelbrotMaterial;
 use crate::BurningShipMaterial;
+use crate::progressive::{ProgressiveSettings, ProgressiveView, PALETTES};
+use crate::PanCamConfig;
 use crate::PanCamState;
 use bevy::prelude::*;
 
 use bevy_egui::{egui, EguiContexts};
 
+// Entries of the fractal picker: the escape-time fractals and the orbit density views.
+const FRACTAL_CHOICES: [(&str, FractalType, Option<DensityMode>); 6] = [
+    ("Mandelbrot", FractalType::Mandelbrot, None),
+    ("Julia", FractalType::Julia, None),
+    ("Burning Ship", FractalType::BurningShip, None),
+    (
+        "Buddhabrot",
+        FractalType::Mandelbrot,
+        Some(DensityMode::Buddhabrot),
+    ),
+    (
+        "Anti-Buddhabrot",
+        FractalType::Mandelbrot,
+        Some(DensityMode::AntiBuddhabrot),
+    ),
+    // The Julia material stays alive underneath so its constant can be followed.
+    (
+        "Julia Orbit Density",
+        FractalType::Julia,
+        Some(DensityMode::Julia),
+    ),
+];
+// Range of the zoom sliders; f32 coordinates break up not far past the top end.
+const MIN_SLIDER_MAGNIFICATION: f32 = 0.1;
+const MAX_SLIDER_MAGNIFICATION: f32 = 1e6;
+
 #[derive(Default)]
 pub struct UIPlugin;
 
@@ -17,103 +50,357 @@ impl Plugin for UIPlugin {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn uniform_update_ui_system(
     mut ctx: EguiContexts,
     mut materials: ResMut<Assets<MandelbrotMaterial>>,
     mut julia_materials: ResMut<Assets<JuliaMaterial>>,
     mut burning_ship_materials: ResMut<Assets<BurningShipMaterial>>,
-    _pancam_query: Query<&mut PanCamState>,
-    mut animation_speed: ResMut<AnimationSpeed>,
-    mut query: Query<(&mut OrthographicProjection, &mut Transform)>,
+    mut pancam_config_query: Query<&mut PanCamConfig>,
+    mut query: Query<(&mut OrthographicProjection, &mut PanCamState)>,
+    mut progressive_settings: ResMut<ProgressiveSettings>,
+    progressive_view: Res<ProgressiveView>,
+    mut export_settings: ResMut<ExportSettings>,
+    mut export_state: ResMut<ExportState>,
+    mut auto_iterations: ResMut<AutoIterations>,
+    mut idle_mode: ResMut<IdleMode>,
+    mut buddhabrot_settings: ResMut<BuddhabrotSettings>,
+    mut buddhabrot_state: ResMut<BuddhabrotState>,
+    mut fractal_type: ResMut<FractalType>,
 ) {
+    // Widgets edit copies, so resources and materials only count as changed on real edits;
+    // writing through them every frame would keep idle mode from ever sleeping.
+    let mut progressive = progressive_settings.clone();
+    let mut export = export_settings.clone();
+    let mut auto = auto_iterations.clone();
+    let mut idle = idle_mode.clone();
+    let mut buddhabrot = buddhabrot_settings.clone();
     let context = ctx.ctx_mut();
+    let exporting = export_state.is_exporting();
     egui::Window::new("Update Uniforms").show(context, |ui| {
-        if let Some(mandelbrot_material) = materials.iter_mut().next() {
+        // Settings stay put while an export renders with them.
+        ui.set_enabled(!exporting);
+        let current = (*fractal_type, buddhabrot.enabled.then_some(buddhabrot.mode));
+        ui.horizontal(|ui| {
+            ui.label("Fractal:");
+            egui::ComboBox::from_id_source("fractal")
+                .selected_text(
+                    FRACTAL_CHOICES
+                        .iter()
+                        .find(|(_, fractal, mode)| (*fractal, *mode) == current)
+                        .map_or("", |(name, _, _)| *name),
+                )
+                .show_ui(ui, |ui| {
+                    for (name, fractal, mode) in FRACTAL_CHOICES {
+                        if ui
+                            .selectable_label((fractal, mode) == current, name)
+                            .clicked()
+                        {
+                            // Only touch the resource on a real change; it respawns the quad.
+                            if *fractal_type != fractal {
+                                *fractal_type = fractal;
+                            }
+                            buddhabrot.enabled = mode.is_some();
+                            if let Some(mode) = mode {
+                                buddhabrot.mode = mode;
+                            }
+                        }
+                    }
+                });
+        });
+        ui.checkbox(&mut idle.enabled, "Idle Mode (redraw only on change)");
+        for mut config in pancam_config_query.iter_mut() {
+            let mut rotation_enabled = config.rotation_enabled;
+            ui.checkbox(&mut rotation_enabled, "Rotate With Two-Finger Gestures");
+            if rotation_enabled != config.rotation_enabled {
+                config.rotation_enabled = rotation_enabled;
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.label("Palette:");
+            egui::ComboBox::from_id_source("palette")
+                .selected_text(
+                    PALETTES
+                        .get(progressive.palette)
+                        .copied()
+                        .unwrap_or_default(),
+                )
+                .show_ui(ui, |ui| {
+                    for (index, palette) in PALETTES.iter().enumerate() {
+                        ui.selectable_value(&mut progressive.palette, index, *palette);
+                    }
+                });
+        });
+        ui.checkbox(&mut progressive.enabled, "Compute Renderer");
+        if progressive.enabled {
             ui.horizontal(|ui| {
-                ui.label("Animation Speed:");
-                ui.add(egui::Slider::new(&mut animation_speed.0, 0.0..=0.1));
+                ui.label("Iterations Per Frame:");
+                ui.add(
+                    egui::Slider::new(&mut progressive.iterations_per_frame, 1..=100_000)
+                        .logarithmic(true),
+                );
             });
             ui.horizontal(|ui| {
-                ui.label("Mandelbrot Color Scale:");
+                ui.label("Progressive Max Iterations:");
+                ui.add(
+                    egui::Slider::new(&mut progressive.max_iterations, 1..=10_000_000)
+                        .logarithmic(true),
+                );
+            });
+            ui.checkbox(
+                &mut progressive.temporal_accumulation,
+                "Temporal Anti-Aliasing",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Samples Per Pixel:");
                 ui.add(egui::Slider::new(
-                    &mut mandelbrot_material.1.color_scale,
-                    0.0..=1.0,
+                    &mut progressive.samples_per_pixel,
+                    1..=256,
                 ));
             });
+            ui.checkbox(&mut progressive.dynamic_resolution, "Dynamic Resolution");
+            if progressive.dynamic_resolution {
+                ui.horizontal(|ui| {
+                    ui.label("Target Frame Time (ms):");
+                    ui.add(egui::Slider::new(
+                        &mut progressive.target_frame_time,
+                        4.0..=50.0,
+                    ));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Minimum Resolution Scale:");
+                    ui.add(egui::Slider::new(
+                        &mut progressive.min_resolution_scale,
+                        0.1..=1.0,
+                    ));
+                });
+                ui.label(format!(
+                    "Resolution Scale: {:.0}%",
+                    progressive_view.resolution_scale * 100.0
+                ));
+            }
+            ui.add(egui::ProgressBar::new(progressive_view.progress()).show_percentage());
+        }
+        ui.checkbox(&mut auto.enabled, "Automatic Iterations");
+        if auto.enabled {
             ui.horizontal(|ui| {
-                ui.label("Mandelbrot Iterations:");
+                ui.label("Minimum Iterations:");
+                ui.add(egui::Slider::new(&mut auto.min_iterations, 1..=100_000).logarithmic(true));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Maximum Iterations:");
+                ui.add(
+                    egui::Slider::new(&mut auto.max_iterations, 1..=10_000_000).logarithmic(true),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Iterations Per Decade:");
                 ui.add(egui::Slider::new(
-                    &mut mandelbrot_material.1.max_iterations,
-                    0.0..=10000.0,
+                    &mut auto.iterations_per_decade,
+                    0.0..=10_000.0,
                 ));
             });
-
-            for (mut proj, _pos) in &mut query {
+            ui.label(format!("Current Iterations: {}", auto.current));
+        }
+        ui.horizontal(|ui| {
+            ui.label("Export Samples:");
+            ui.add(egui::Slider::new(&mut export.samples_per_pixel, 1..=1024));
+            if exporting || buddhabrot_state.is_exporting() {
+                ui.label("Exporting...");
+            } else if ui.button("Export PNG").clicked() {
+                // Orbit density views render their own high-resolution export in the background.
+                if buddhabrot.enabled {
+                    buddhabrot_state.request_export();
+                } else {
+                    export_state.request(&export);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Export Frame Limit:");
+            ui.add(egui::Slider::new(&mut export.max_frames, 1..=20_000).logarithmic(true))
+                .on_hover_text("Frames an export waits for every pixel to resolve");
+        });
+        if buddhabrot.enabled {
+            ui.checkbox(
+                &mut buddhabrot.nebulabrot,
+                "Nebulabrot (per-channel limits)",
+            );
+            let channels = if buddhabrot.nebulabrot {
+                &["Red", "Green", "Blue"][..]
+            } else {
+                &["All"][..]
+            };
+            for (channel, name) in channels.iter().enumerate() {
                 ui.horizontal(|ui| {
-                    ui.label("Mandelbrot Zoom:");
-                    ui.add(egui::Slider::new(&mut proj.scale, 0.0..=8.0));
+                    ui.label(format!("{name} Iterations:"));
+                    ui.add(
+                        egui::Slider::new(
+                            &mut buddhabrot.channel_iterations[channel],
+                            10..=100_000,
+                        )
+                        .logarithmic(true),
+                    );
                 });
             }
+            ui.horizontal(|ui| {
+                ui.label("Orbits Per Frame:");
+                ui.add(
+                    egui::Slider::new(&mut buddhabrot.samples_per_frame, 1_000..=5_000_000)
+                        .logarithmic(true),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Tone Mapping:");
+                ui.radio_value(&mut buddhabrot.tone_mapping, ToneMapping::Log, "Log");
+                ui.radio_value(&mut buddhabrot.tone_mapping, ToneMapping::Sqrt, "Sqrt");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Exposure:");
+                ui.add(egui::Slider::new(&mut buddhabrot.exposure, 0.1..=4.0));
+            });
+            ui.label(format!("Orbits Traced: {}", buddhabrot_state.samples()));
+            ui.horizontal(|ui| {
+                ui.label("Export Scale:");
+                ui.add(egui::Slider::new(&mut buddhabrot.export_scale, 1..=8));
+                ui.label("Export Orbits (millions):");
+                ui.add(
+                    egui::Slider::new(&mut buddhabrot.export_samples, 1..=10_000).logarithmic(true),
+                );
+            });
         }
-        if let Some(julia_material) = julia_materials.iter_mut().next() {
+        if let Some(mut mandelbrot_material) = first_material(&materials) {
             ui.horizontal(|ui| {
-                ui.label("Animation Speed:");
-                ui.add(egui::Slider::new(&mut animation_speed.0, 0.0..=0.1));
+                ui.label("Mandelbrot Color Scale:");
+                ui.add(egui::Slider::new(
+                    &mut mandelbrot_material.color_scale,
+                    0.0..=1.0,
+                ));
             });
+            ui.horizontal(|ui| {
+                ui.label("Mandelbrot Iterations:");
+                ui.add(egui::Slider::new(
+                    &mut mandelbrot_material.max_iterations,
+                    0.0..=10000.0,
+                ));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Mandelbrot Periodicity Tolerance:");
+                ui.add(
+                    egui::Slider::new(&mut mandelbrot_material.periodicity_tolerance, 0.0..=1e-2)
+                        .logarithmic(true),
+                );
+            });
+
+            zoom_slider(ui, "Mandelbrot Zoom:", &mut query);
+            edit_materials(&mut materials, |material| {
+                *material = mandelbrot_material.clone()
+            });
+        }
+        if let Some(mut julia_material) = first_material(&julia_materials) {
             ui.horizontal(|ui| {
                 ui.label("Julia Color Scale:");
                 ui.add(egui::Slider::new(
-                    &mut julia_material.1.color_scale,
+                    &mut julia_material.color_scale,
                     0.0..=1.0,
                 ));
             });
             ui.horizontal(|ui| {
                 ui.label("Julia Iterations:");
                 ui.add(egui::Slider::new(
-                    &mut julia_material.1.max_iterations,
+                    &mut julia_material.max_iterations,
                     0.0..=10000.0,
                 ));
             });
+            ui.horizontal(|ui| {
+                ui.label("Julia Periodicity Tolerance:");
+                ui.add(
+                    egui::Slider::new(&mut julia_material.periodicity_tolerance, 0.0..=1e-2)
+                        .logarithmic(true),
+                );
+            });
             ui.horizontal(|ui| {
                 ui.label("Julia c.x:");
-                ui.add(egui::Slider::new(&mut julia_material.1.c.x, -2.0..=2.0));
+                ui.add(egui::Slider::new(&mut julia_material.c.x, -2.0..=2.0));
             });
             ui.horizontal(|ui| {
                 ui.label("Julia c.y:");
-                ui.add(egui::Slider::new(&mut julia_material.1.c.y, -2.0..=2.0));
+                ui.add(egui::Slider::new(&mut julia_material.c.y, -2.0..=2.0));
             });
-            for (mut proj, _pos) in &mut query {
-                ui.horizontal(|ui| {
-                    ui.label("Julia Zoom:");
-                    ui.add(egui::Slider::new(&mut proj.scale, 0.0..=8.0));
-                });
-            }
-        }
-        if let Some(burning_ship_material) = burning_ship_materials.iter_mut().next() {
-            ui.horizontal(|ui| {
-                ui.label("Animation Speed:");
-                ui.add(egui::Slider::new(&mut animation_speed.0, 0.0..=0.1));
+            zoom_slider(ui, "Julia Zoom:", &mut query);
+            edit_materials(&mut julia_materials, |material| {
+                *material = julia_material.clone()
             });
+        }
+        if let Some(mut burning_ship_material) = first_material(&burning_ship_materials) {
             ui.horizontal(|ui| {
                 ui.label("Burning Ship Color Scale:");
                 ui.add(egui::Slider::new(
-                    &mut burning_ship_material.1.color_scale,
+                    &mut burning_ship_material.color_scale,
                     0.0..=1.0,
                 ));
             });
             ui.horizontal(|ui| {
                 ui.label("Burning Ship Iterations:");
                 ui.add(egui::Slider::new(
-                    &mut burning_ship_material.1.max_iterations,
+                    &mut burning_ship_material.max_iterations,
                     0.0..=10000.0,
                 ));
             });
-            for (mut proj, _pos) in &mut query {
-                ui.horizontal(|ui| {
-                    ui.label("Burning Ship Zoom:");
-                    ui.add(egui::Slider::new(&mut proj.scale, 0.0..=8.0));
-                });
-            }
+            ui.horizontal(|ui| {
+                ui.label("Burning Ship Periodicity Tolerance:");
+                ui.add(
+                    egui::Slider::new(&mut burning_ship_material.periodicity_tolerance, 0.0..=1e-2)
+                        .logarithmic(true),
+                );
+            });
+            zoom_slider(ui, "Burning Ship Zoom:", &mut query);
+            edit_materials(&mut burning_ship_materials, |material| {
+                *material = burning_ship_material.clone()
+            });
         }
     });
+    progressive_settings.set_if_neq(progressive);
+    export_settings.set_if_neq(export);
+    auto_iterations.set_if_neq(auto);
+    idle_mode.set_if_neq(idle);
+    buddhabrot_settings.set_if_neq(buddhabrot);
+}
+
+// Copy of the first material of a kind, for widgets to edit.
+fn first_material<M: Asset + Clone>(materials: &Assets<M>) -> Option<M> {
+    materials
+        .iter()
+        .next()
+        .map(|(_, material)| material.clone())
+}
+
+// Slider for the camera zoom that only writes the projection while it is dragged.
+// Magnification relative to the initial view, the way the HUD reports it, rather than the raw
+// projection scale.
+fn zoom_slider(
+    ui: &mut egui::Ui,
+    label: &str,
+    query: &mut Query<(&mut OrthographicProjection, &mut PanCamState)>,
+) {
+    for (mut proj, mut cam) in query.iter_mut() {
+        let magnification = cam.magnification(proj.scale);
+        let mut edited = magnification;
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(
+                egui::Slider::new(
+                    &mut edited,
+                    MIN_SLIDER_MAGNIFICATION..=MAX_SLIDER_MAGNIFICATION,
+                )
+                .logarithmic(true)
+                .suffix("x"),
+            );
+        });
+        if edited != magnification {
+            proj.scale = cam.home_zoom / edited;
+            // Keep a running zoom animation from pulling the view back.
+            cam.target_zoom = proj.scale;
+        }
+    }
 }
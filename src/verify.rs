@@ -0,0 +1,291 @@
+use std::path::{Path, PathBuf};
+
+use crate::kernels::{render_rgba8, Colormap, Kernel, KernelParams};
+
+// Stored reference renders, one PNG per scene. They come from the CPU kernels themselves, so a
+// pass means the kernels haven't drifted from their own earlier output, not that the shaders agree.
+const GOLDEN_DIRECTORY: &str = "tests/golden";
+// Where failing scenes leave their render and a difference image.
+const DIFF_DIRECTORY: &str = "renders/verify";
+const ASSET_DIRECTORY: &str = "assets";
+
+const SCENE_WIDTH: u32 = 160;
+const SCENE_HEIGHT: u32 = 120;
+
+// A scene passes when its PSNR against the golden image is at least this many dB and no
+// channel is off by more than MAX_CHANNEL_ERROR; the slack absorbs libm differences.
+const MIN_PSNR: f64 = 40.0;
+const MAX_CHANNEL_ERROR: u8 = 16;
+// Amplification of the per-channel error in diff images.
+const DIFF_GAIN: u8 = 8;
+
+const JULIA_C: [f32; 2] = [-0.8, 0.156];
+
+struct Scene {
+    name: &'static str,
+    kernel: Kernel,
+    // Point at the center of the image, in the shaders' uv * 4.0 - 2.0 coordinates.
+    center: [f32; 2],
+    // Distance between neighbouring pixels.
+    pixel_step: f32,
+    palette: &'static str,
+    max_iterations: f32,
+}
+
+const SCENES: [Scene; 9] = [
+    Scene {
+        name: "mandelbrot-home",
+        kernel: Kernel::Mandelbrot,
+        center: [-0.5, 0.0],
+        pixel_step: 0.02,
+        palette: "gradient.png",
+        max_iterations: 500.0,
+    },
+    Scene {
+        name: "mandelbrot-seahorse",
+        kernel: Kernel::Mandelbrot,
+        center: [-0.745, 0.11],
+        pixel_step: 0.0003,
+        palette: "gradient2.png",
+        max_iterations: 1000.0,
+    },
+    Scene {
+        name: "mandelbrot-deep",
+        kernel: Kernel::Mandelbrot,
+        center: [-0.743_643_9, 0.131_825_9],
+        pixel_step: 0.000_002,
+        palette: "gradient3.png",
+        max_iterations: 2000.0,
+    },
+    Scene {
+        name: "julia-home",
+        kernel: Kernel::Julia { c: JULIA_C },
+        center: [0.0, 0.0],
+        pixel_step: 0.025,
+        palette: "gradient4.png",
+        max_iterations: 500.0,
+    },
+    Scene {
+        name: "julia-mid",
+        kernel: Kernel::Julia { c: JULIA_C },
+        center: [-0.25, -0.2],
+        pixel_step: 0.004,
+        palette: "gradient6.png",
+        max_iterations: 1000.0,
+    },
+    Scene {
+        name: "julia-deep",
+        kernel: Kernel::Julia { c: JULIA_C },
+        center: [-0.25, -0.2],
+        pixel_step: 0.0005,
+        palette: "gradient.png",
+        max_iterations: 1000.0,
+    },
+    Scene {
+        name: "burning-ship-home",
+        kernel: Kernel::BurningShip,
+        center: [-0.5, -0.5],
+        pixel_step: 0.025,
+        palette: "gradient2.png",
+        max_iterations: 500.0,
+    },
+    Scene {
+        name: "burning-ship-ship",
+        kernel: Kernel::BurningShip,
+        center: [-1.755, -0.03],
+        pixel_step: 0.0005,
+        palette: "gradient3.png",
+        max_iterations: 1000.0,
+    },
+    Scene {
+        name: "burning-ship-deep",
+        kernel: Kernel::BurningShip,
+        center: [-1.762, -0.028],
+        pixel_step: 0.000_02,
+        palette: "gradient4.png",
+        max_iterations: 2000.0,
+    },
+];
+
+pub fn load_colormap(palette: &str) -> Result<Colormap, String> {
+    let path = Path::new(ASSET_DIRECTORY).join(palette);
+    let image = image::open(&path)
+        .map_err(|err| format!("could not load {}: {err}", path.display()))?
+        .to_rgba8();
+    Ok(Colormap::from_srgba8(
+        image.width(),
+        image.height(),
+        image.as_raw(),
+    ))
+}
+
+fn render_scene(scene: &Scene) -> Result<image::RgbaImage, String> {
+    let colormap = load_colormap(scene.palette)?;
+    let params = KernelParams {
+        color_scale: 0.5,
+        max_iterations: scene.max_iterations,
+        periodicity_tolerance: 1e-5,
+    };
+    let pixels = render_rgba8(
+        scene.kernel,
+        &params,
+        &colormap,
+        scene.center,
+        scene.pixel_step,
+        SCENE_WIDTH,
+        SCENE_HEIGHT,
+    );
+    image::RgbaImage::from_raw(SCENE_WIDTH, SCENE_HEIGHT, pixels)
+        .ok_or_else(|| "image buffer has the wrong size".to_string())
+}
+
+struct Comparison {
+    psnr: f64,
+    max_error: u8,
+}
+
+impl Comparison {
+    fn passed(&self) -> bool {
+        self.psnr >= MIN_PSNR && self.max_error <= MAX_CHANNEL_ERROR
+    }
+}
+
+fn compare(actual: &image::RgbaImage, golden: &image::RgbaImage) -> Comparison {
+    let mut squared_error = 0.0;
+    let mut max_error = 0;
+    for (a, b) in actual.as_raw().iter().zip(golden.as_raw()) {
+        let error = a.abs_diff(*b);
+        squared_error += (error as f64).powi(2);
+        max_error = max_error.max(error);
+    }
+    let mse = squared_error / actual.as_raw().len().max(1) as f64;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    };
+    Comparison { psnr, max_error }
+}
+
+fn diff_image(actual: &image::RgbaImage, golden: &image::RgbaImage) -> image::RgbaImage {
+    image::RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let (a, b) = (actual.get_pixel(x, y).0, golden.get_pixel(x, y).0);
+        let channel = |i: usize| a[i].abs_diff(b[i]).saturating_mul(DIFF_GAIN);
+        image::Rgba([channel(0), channel(1), channel(2), 255])
+    })
+}
+
+fn golden_path(scene: &Scene) -> PathBuf {
+    Path::new(GOLDEN_DIRECTORY).join(format!("{}.png", scene.name))
+}
+
+// Check one scene against its golden image, writing the render and a diff image on failure.
+fn verify_scene(scene: &Scene) -> Result<Comparison, String> {
+    let actual = render_scene(scene)?;
+    let path = golden_path(scene);
+    let golden = image::open(&path)
+        .map_err(|err| format!("could not load {}: {err}", path.display()))?
+        .to_rgba8();
+    if golden.dimensions() != actual.dimensions() {
+        return Err(format!(
+            "{} is {:?}, expected {:?}",
+            path.display(),
+            golden.dimensions(),
+            actual.dimensions()
+        ));
+    }
+
+    let comparison = compare(&actual, &golden);
+    if !comparison.passed() {
+        std::fs::create_dir_all(DIFF_DIRECTORY).map_err(|err| err.to_string())?;
+        let directory = Path::new(DIFF_DIRECTORY);
+        actual
+            .save(directory.join(format!("{}-actual.png", scene.name)))
+            .map_err(|err| err.to_string())?;
+        diff_image(&actual, &golden)
+            .save(directory.join(format!("{}-diff.png", scene.name)))
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(comparison)
+}
+
+// Rewrite the golden images from the current kernels.
+fn update_goldens() -> Result<(), String> {
+    std::fs::create_dir_all(GOLDEN_DIRECTORY).map_err(|err| err.to_string())?;
+    for scene in &SCENES {
+        let path = golden_path(scene);
+        render_scene(scene)?
+            .save(&path)
+            .map_err(|err| err.to_string())?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+// Entry point of `bevy_mandelbrot verify [--update]`. Returns the process exit code.
+pub fn run(args: impl Iterator<Item = String>) -> i32 {
+    let args: Vec<String> = args.collect();
+    if args.iter().any(|arg| arg == "--update") {
+        return match update_goldens() {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("error: {err}");
+                1
+            }
+        };
+    }
+
+    let mut failures = 0;
+    for scene in &SCENES {
+        match verify_scene(scene) {
+            Ok(comparison) => {
+                let status = if comparison.passed() { "ok" } else { "FAILED" };
+                println!(
+                    "{:<24} {status:<6} psnr {:>6.2} dB, max error {}",
+                    scene.name, comparison.psnr, comparison.max_error
+                );
+                if !comparison.passed() {
+                    failures += 1;
+                }
+            }
+            Err(err) => {
+                println!("{:<24} FAILED {err}", scene.name);
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 {
+        println!("{failures} scene(s) failed; renders and diffs are in {DIFF_DIRECTORY}");
+        1
+    } else {
+        println!("all {} scenes match", SCENES.len());
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenes_match_golden_images() {
+        for scene in &SCENES {
+            let comparison = verify_scene(scene).unwrap();
+            assert!(
+                comparison.passed(),
+                "{}: psnr {} dB, max error {}",
+                scene.name,
+                comparison.psnr,
+                comparison.max_error
+            );
+        }
+    }
+
+    #[test]
+    fn identical_images_have_no_error() {
+        let image = render_scene(&SCENES[0]).unwrap();
+        let comparison = compare(&image, &image);
+        assert_eq!(comparison.max_error, 0);
+        assert!(comparison.psnr.is_infinite());
+    }
+}